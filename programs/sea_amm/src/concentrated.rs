@@ -0,0 +1,1002 @@
+//! Concentrated liquidity math, for pools whose liquidity sits in price
+//! ranges.
+//!
+//! Tick `i` is the price `1.0001^i` of token a in token b. Prices are kept as
+//! their square roots in Q64.64 fixed point, so a position with liquidity `L`
+//! between `sqrt_lower` and `sqrt_upper` holds
+//!
+//! `L * (1 / sqrt_price - 1 / sqrt_upper)` of token a and
+//! `L * (sqrt_price - sqrt_lower)` of token b
+//!
+//! while the price is in its range. Swaps move the price along the liquidity
+//! of the current range and cross initialized ticks to change it.
+//!
+//! Fees are tracked as fee growth per unit of liquidity, in Q64.64, globally
+//! and on the far side of each initialized tick. Fee growth only ever adds
+//! up, and the differences between its values are what matter, so it wraps
+//! around on overflow.
+//!
+//! Ticks are stored in tick arrays of `TICK_ARRAY_SIZE` ticks on the tick
+//! spacing, each starting at a multiple of the ticks it spans. A tick is
+//! initialized while some position starts or ends at it.
+
+use crate::dot::program::{Tick, TICK_ARRAY_SIZE};
+use crate::error::{MathResult, SeaAmmError};
+use crate::math::{
+    self, checked_add, checked_add_u256, checked_div, checked_div_ceil, checked_div_u256,
+    checked_mul, checked_mul_u256, checked_sub, U256,
+};
+use anchor_lang::prelude::*;
+
+/// Lowest tick a position can start at.
+pub const MIN_TICK: i32 = -443_636;
+/// Highest tick a position can end at.
+pub const MAX_TICK: i32 = 443_636;
+/// Square root of the price at `MIN_TICK`, in Q64.64.
+pub const MIN_SQRT_PRICE: u128 = 4_295_048_017;
+/// Square root of the price at `MAX_TICK`, in Q64.64.
+pub const MAX_SQRT_PRICE: u128 = 79_226_673_515_401_279_992_447_579_062;
+
+const RESOLUTION: u32 = 64;
+
+// `2^128 / sqrt(1.0001)^(2^i)`, rounded up, for each bit `i` of a tick
+const TICK_FACTORS: [u128; 19] = [
+    0xfffcb933bd6fad37aa2d162d1a594001,
+    0xfff97272373d413259a46990580e213a,
+    0xfff2e50f5f656932ef12357cf3c7fdcc,
+    0xffe5caca7e10e4e61c3624eaa0941cd0,
+    0xffcb9843d60f6159c9db58835c926644,
+    0xff973b41fa98c081472e6896dfb254c0,
+    0xff2ea16466c96a3843ec78b326b52861,
+    0xfe5dee046a99a2a811c461f1969c3053,
+    0xfcbe86c7900a88aedcffc83b479aa3a4,
+    0xf987a7253ac413176f2b074cf7815e54,
+    0xf3392b0822b70005940c7a398e4b70f3,
+    0xe7159475a2c29b7443b29c7fa6e889d9,
+    0xd097f3bdfd2022b8845ad8f792aa5825,
+    0xa9f746462d870fdf8a65dc1f90e061e5,
+    0x70d869a156d2a1b890bb3df62baf32f7,
+    0x31be135f97d08fd981231505542fcfa6,
+    0x9aa508b5b7a84e1c677de54f3e99bc9,
+    0x5d6af8dedb81196699c329225ee604,
+    0x2216e584f5fa1ea926041bedfe98,
+];
+
+/// The price, liquidity in range and fee growth of a pool, which swaps move.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolState {
+    pub sqrt_price: u128,
+    pub tick_current: i32,
+    pub liquidity: u128,
+    pub fee_growth_global_a: u128,
+    pub fee_growth_global_b: u128,
+}
+
+/// The result of a swap through a pool's ticks, with the pool state it
+/// leaves behind.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SwapResult {
+    pub amount_out: u64,
+    pub state: PoolState,
+}
+
+/// Part of a swap within one range of liquidity.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct SwapStep {
+    sqrt_price_next: u128,
+    amount_in: u64,
+    amount_out: u64,
+    fee_amount: u64,
+}
+
+/// Returns the square root of the price at `tick`, in Q64.64, rounded up.
+pub fn sqrt_price_at_tick(tick: i32) -> MathResult<u128> {
+    if !(MIN_TICK..=MAX_TICK).contains(&tick) {
+        return Err(SeaAmmError::InvalidTickRange);
+    }
+
+    // `1 / sqrt(1.0001)^|tick|` in Q128.128, a product of the factors of the
+    // bits of `|tick|`
+    let abs_tick = tick.unsigned_abs();
+    let mut ratio = U256::one() << 128;
+    for (bit, factor) in TICK_FACTORS.iter().enumerate() {
+        if abs_tick & (1 << bit) != 0 {
+            ratio = (ratio * U256::from(*factor)) >> 128;
+        }
+    }
+
+    if tick > 0 {
+        ratio = U256::MAX / ratio;
+    }
+
+    let remainder = ratio % (U256::one() << RESOLUTION);
+    let sqrt_price = (ratio >> RESOLUTION) + U256::from(!remainder.is_zero() as u8);
+
+    Ok(sqrt_price.as_u128())
+}
+
+/// Returns the highest tick whose square root price is at most `sqrt_price`.
+pub fn tick_at_sqrt_price(sqrt_price: u128) -> MathResult<i32> {
+    if !(MIN_SQRT_PRICE..=MAX_SQRT_PRICE).contains(&sqrt_price) {
+        return Err(SeaAmmError::InvalidSqrtPrice);
+    }
+
+    let (mut low, mut high) = (MIN_TICK, MAX_TICK);
+    while low < high {
+        let middle = low + (high - low + 1) / 2;
+        if sqrt_price_at_tick(middle)? <= sqrt_price {
+            low = middle;
+        } else {
+            high = middle - 1;
+        }
+    }
+
+    Ok(low)
+}
+
+/// Returns the amount of token a that `liquidity` holds between two square
+/// root prices, `L * (sqrt_upper - sqrt_lower) / (sqrt_upper * sqrt_lower)`.
+pub fn get_amount_a_delta(
+    sqrt_price_lower: u128,
+    sqrt_price_upper: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> MathResult<u64> {
+    to_u64(amount_a_delta(
+        sqrt_price_lower,
+        sqrt_price_upper,
+        liquidity,
+        round_up,
+    )?)
+}
+
+/// Returns the amount of token b that `liquidity` holds between two square
+/// root prices, `L * (sqrt_upper - sqrt_lower)`.
+pub fn get_amount_b_delta(
+    sqrt_price_lower: u128,
+    sqrt_price_upper: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> MathResult<u64> {
+    to_u64(amount_b_delta(
+        sqrt_price_lower,
+        sqrt_price_upper,
+        liquidity,
+        round_up,
+    )?)
+}
+
+/// Returns the amounts of token a and token b that `liquidity` between
+/// `tick_lower` and `tick_upper` holds at the pool's current price. A range
+/// above the price holds only token a and a range below it only token b.
+///
+/// Deposits round up and withdrawals round down, in favor of the pool.
+pub fn get_amounts_for_liquidity(
+    sqrt_price: u128,
+    tick_current: i32,
+    tick_lower: i32,
+    tick_upper: i32,
+    liquidity: u128,
+    round_up: bool,
+) -> MathResult<(u64, u64)> {
+    let sqrt_price_lower = sqrt_price_at_tick(tick_lower)?;
+    let sqrt_price_upper = sqrt_price_at_tick(tick_upper)?;
+
+    if tick_current < tick_lower {
+        Ok((
+            get_amount_a_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?,
+            0,
+        ))
+    } else if tick_current < tick_upper {
+        Ok((
+            get_amount_a_delta(sqrt_price, sqrt_price_upper, liquidity, round_up)?,
+            get_amount_b_delta(sqrt_price_lower, sqrt_price, liquidity, round_up)?,
+        ))
+    } else {
+        Ok((
+            0,
+            get_amount_b_delta(sqrt_price_lower, sqrt_price_upper, liquidity, round_up)?,
+        ))
+    }
+}
+
+/// Returns `liquidity` changed by `liquidity_delta`.
+pub fn add_liquidity_delta(liquidity: u128, liquidity_delta: i128) -> MathResult<u128> {
+    if liquidity_delta < 0 {
+        checked_sub(liquidity, liquidity_delta.unsigned_abs())
+    } else {
+        checked_add(liquidity, liquidity_delta as u128)
+    }
+}
+
+/// Returns the first tick of the tick array that holds `tick`.
+pub fn get_tick_array_start_index(tick: i32, tick_spacing: u16) -> i32 {
+    let ticks_in_array = TICK_ARRAY_SIZE as i32 * tick_spacing as i32;
+
+    tick.div_euclid(ticks_in_array) * ticks_in_array
+}
+
+/// Returns the uninitialized ticks of a tick array starting at
+/// `start_tick_index`.
+pub fn get_tick_array_ticks(start_tick_index: i32, tick_spacing: u16) -> Vec<Tick> {
+    (0..TICK_ARRAY_SIZE as i32)
+        .map(|i| Tick {
+            index: start_tick_index + i * tick_spacing as i32,
+            liquidity_net: 0,
+            liquidity_gross: 0,
+            fee_growth_outside_a: 0,
+            fee_growth_outside_b: 0,
+        })
+        .collect()
+}
+
+/// Returns the price a swap through the tick arrays up to the one starting
+/// at `last_start_tick_index` can move to, the first tick of that array going
+/// down and its last tick going up. Past those the swap would reach ticks it
+/// hasn't loaded.
+pub fn get_sqrt_price_limit(
+    last_start_tick_index: i32,
+    tick_spacing: u16,
+    a_to_b: bool,
+) -> MathResult<u128> {
+    if a_to_b {
+        sqrt_price_at_tick(last_start_tick_index.max(MIN_TICK))
+    } else {
+        let last_tick_offset = (TICK_ARRAY_SIZE as i32 - 1) * tick_spacing as i32;
+
+        sqrt_price_at_tick((last_start_tick_index + last_tick_offset).min(MAX_TICK))
+    }
+}
+
+/// Adds `liquidity_delta` to the position liquidity starting (or, with
+/// `is_upper`, ending) at `tick`, which must be one of `ticks`.
+///
+/// A tick that is initialized by the change takes all fee growth so far as
+/// being below it when the price is at or above it, and none otherwise. A
+/// tick left with no liquidity is cleared, so it can be initialized afresh.
+pub fn update_tick(
+    ticks: &mut [Tick],
+    tick: i32,
+    tick_current: i32,
+    liquidity_delta: i128,
+    is_upper: bool,
+    fee_growth_global_a: u128,
+    fee_growth_global_b: u128,
+) -> MathResult<()> {
+    let position = ticks
+        .binary_search_by_key(&tick, |tick| tick.index)
+        .map_err(|_| SeaAmmError::InvalidTickArray)?;
+    let tick = &mut ticks[position];
+
+    if tick.liquidity_gross == 0 && tick.index <= tick_current {
+        tick.fee_growth_outside_a = fee_growth_global_a;
+        tick.fee_growth_outside_b = fee_growth_global_b;
+    }
+
+    tick.liquidity_gross = add_liquidity_delta(tick.liquidity_gross, liquidity_delta)?;
+
+    // liquidity comes into range crossing the lower tick upwards and leaves
+    // crossing the upper tick
+    let liquidity_net_delta = if is_upper {
+        liquidity_delta.checked_neg()
+    } else {
+        Some(liquidity_delta)
+    };
+    tick.liquidity_net = liquidity_net_delta
+        .and_then(|delta| tick.liquidity_net.checked_add(delta))
+        .ok_or(SeaAmmError::MathOverflow)?;
+
+    if tick.liquidity_gross == 0 {
+        tick.liquidity_net = 0;
+        tick.fee_growth_outside_a = 0;
+        tick.fee_growth_outside_b = 0;
+    }
+
+    Ok(())
+}
+
+/// Returns the fee growth of token a and token b between `tick_lower` and
+/// `tick_upper`. Ticks that aren't initialized have had no fee growth
+/// outside them.
+pub fn get_fee_growth_inside(
+    ticks: &[Tick],
+    tick_current: i32,
+    tick_lower: i32,
+    tick_upper: i32,
+    fee_growth_global_a: u128,
+    fee_growth_global_b: u128,
+) -> (u128, u128) {
+    let fee_growth_outside = |tick: i32| {
+        ticks
+            .binary_search_by_key(&tick, |tick| tick.index)
+            .map(|position| {
+                (
+                    ticks[position].fee_growth_outside_a,
+                    ticks[position].fee_growth_outside_b,
+                )
+            })
+            .unwrap_or((0, 0))
+    };
+    let (lower_outside_a, lower_outside_b) = fee_growth_outside(tick_lower);
+    let (upper_outside_a, upper_outside_b) = fee_growth_outside(tick_upper);
+
+    let inside = |global: u128, lower_outside: u128, upper_outside: u128| {
+        let below = if tick_current >= tick_lower {
+            lower_outside
+        } else {
+            global.wrapping_sub(lower_outside)
+        };
+        let above = if tick_current < tick_upper {
+            upper_outside
+        } else {
+            global.wrapping_sub(upper_outside)
+        };
+
+        global.wrapping_sub(below).wrapping_sub(above)
+    };
+
+    (
+        inside(fee_growth_global_a, lower_outside_a, upper_outside_a),
+        inside(fee_growth_global_b, lower_outside_b, upper_outside_b),
+    )
+}
+
+/// Returns the fees earned by `liquidity` since the fee growth inside its
+/// range was `fee_growth_inside_last`, rounded down.
+pub fn get_fees_owed(
+    liquidity: u128,
+    fee_growth_inside: u128,
+    fee_growth_inside_last: u128,
+) -> MathResult<u64> {
+    let fee_growth = U256::from(fee_growth_inside.wrapping_sub(fee_growth_inside_last));
+
+    to_u64(checked_mul_u256(U256::from(liquidity), fee_growth)? >> RESOLUTION)
+}
+
+/// Swaps `amount_in` of token a for token b (or, without `a_to_b`, of token
+/// b for token a) through the liquidity of the pool, crossing the initialized
+/// `ticks` as the price passes them.
+///
+/// `ticks` are sorted by index and are every tick from the price to
+/// `sqrt_price_limit`, which the price can't move past. `fee_numerator /
+/// fee_denominator` of the input is taken as the swap fee and shared by the
+/// liquidity in range when it was paid. The swap fails if it reaches the
+/// limit before all of `amount_in` is used.
+pub fn swap(
+    ticks: &mut [Tick],
+    state: PoolState,
+    amount_in: u64,
+    a_to_b: bool,
+    sqrt_price_limit: u128,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> MathResult<SwapResult> {
+    let mut amount_out = 0;
+    let mut state = state;
+    let mut amount_remaining = amount_in;
+
+    while amount_remaining > 0 {
+        // the next initialized tick the price reaches, at or below the
+        // current tick going down and above it going up
+        let next_tick = if a_to_b {
+            ticks
+                .iter()
+                .rposition(|tick| tick.liquidity_gross > 0 && tick.index <= state.tick_current)
+        } else {
+            ticks
+                .iter()
+                .position(|tick| tick.liquidity_gross > 0 && tick.index > state.tick_current)
+        };
+        let sqrt_price_target = match next_tick {
+            Some(position) => sqrt_price_at_tick(ticks[position].index)?,
+            None => sqrt_price_limit,
+        };
+
+        if next_tick.is_none() && (state.sqrt_price == sqrt_price_target) {
+            return Err(SeaAmmError::InsufficientLiquidity);
+        }
+
+        let step = compute_swap_step(
+            state.sqrt_price,
+            sqrt_price_target,
+            state.liquidity,
+            amount_remaining,
+            fee_numerator,
+            fee_denominator,
+        )?;
+
+        amount_remaining = checked_sub(
+            amount_remaining as u128,
+            checked_add(step.amount_in as u128, step.fee_amount as u128)?,
+        )? as u64;
+        amount_out = math::to_u64(checked_add(amount_out as u128, step.amount_out as u128)?)?;
+
+        if state.liquidity > 0 {
+            let fee_growth = get_fee_growth(step.fee_amount, state.liquidity)?;
+            if a_to_b {
+                state.fee_growth_global_a = state.fee_growth_global_a.wrapping_add(fee_growth);
+            } else {
+                state.fee_growth_global_b = state.fee_growth_global_b.wrapping_add(fee_growth);
+            }
+        }
+
+        state.sqrt_price = step.sqrt_price_next;
+
+        match next_tick {
+            Some(position) if step.sqrt_price_next == sqrt_price_target => {
+                let tick = &mut ticks[position];
+
+                // what was below the tick is now above it, and the other way
+                // around
+                tick.fee_growth_outside_a = state
+                    .fee_growth_global_a
+                    .wrapping_sub(tick.fee_growth_outside_a);
+                tick.fee_growth_outside_b = state
+                    .fee_growth_global_b
+                    .wrapping_sub(tick.fee_growth_outside_b);
+
+                if a_to_b {
+                    state.liquidity = checked_sub_i128(state.liquidity, tick.liquidity_net)?;
+                    state.tick_current = tick.index - 1;
+                } else {
+                    state.liquidity = add_liquidity_delta(state.liquidity, tick.liquidity_net)?;
+                    state.tick_current = tick.index;
+                }
+            }
+            _ => {
+                state.tick_current = tick_at_sqrt_price(step.sqrt_price_next)?;
+            }
+        }
+    }
+
+    Ok(SwapResult { amount_out, state })
+}
+
+/// Swaps as much of `amount_remaining` as moves the price from `sqrt_price`
+/// towards `sqrt_price_target`, within one range of `liquidity`. The price
+/// goes down, selling token a, when the target is below it.
+fn compute_swap_step(
+    sqrt_price: u128,
+    sqrt_price_target: u128,
+    liquidity: u128,
+    amount_remaining: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> MathResult<SwapStep> {
+    let a_to_b = sqrt_price_target <= sqrt_price;
+    let fee_multiplier = checked_sub(fee_denominator as u128, fee_numerator as u128)?;
+    let amount_remaining_after_fee = checked_div(
+        checked_mul(amount_remaining as u128, fee_multiplier)?,
+        fee_denominator as u128,
+    )?;
+
+    let amount_in_to_target = if a_to_b {
+        amount_a_delta(sqrt_price_target, sqrt_price, liquidity, true)?
+    } else {
+        amount_b_delta(sqrt_price, sqrt_price_target, liquidity, true)?
+    };
+
+    let (sqrt_price_next, amount_in) =
+        if U256::from(amount_remaining_after_fee) >= amount_in_to_target {
+            (sqrt_price_target, amount_in_to_target.as_u64())
+        } else {
+            let sqrt_price_next = if a_to_b {
+                get_next_sqrt_price_from_amount_a_in(
+                    sqrt_price,
+                    liquidity,
+                    amount_remaining_after_fee as u64,
+                )?
+            } else {
+                get_next_sqrt_price_from_amount_b_in(
+                    sqrt_price,
+                    liquidity,
+                    amount_remaining_after_fee as u64,
+                )?
+            };
+            let amount_in = if a_to_b {
+                get_amount_a_delta(sqrt_price_next, sqrt_price, liquidity, true)?
+            } else {
+                get_amount_b_delta(sqrt_price, sqrt_price_next, liquidity, true)?
+            };
+
+            (sqrt_price_next, amount_in)
+        };
+
+    let amount_out = if a_to_b {
+        get_amount_b_delta(sqrt_price_next, sqrt_price, liquidity, false)?
+    } else {
+        get_amount_a_delta(sqrt_price, sqrt_price_next, liquidity, false)?
+    };
+
+    // a step that stops short of the target uses up the rest of the input
+    let fee_amount = if sqrt_price_next == sqrt_price_target {
+        math::to_u64(checked_div_ceil(
+            checked_mul(amount_in as u128, fee_numerator as u128)?,
+            fee_multiplier,
+        )?)?
+    } else {
+        math::to_u64(checked_sub(amount_remaining as u128, amount_in as u128)?)?
+    };
+
+    Ok(SwapStep {
+        sqrt_price_next,
+        amount_in,
+        amount_out,
+        fee_amount,
+    })
+}
+
+/// Returns the price after `amount` of token a is sold to `liquidity`,
+/// `L * sqrt_price / (L + amount * sqrt_price)`, rounded up so the price
+/// never drops further than the amount pays for.
+fn get_next_sqrt_price_from_amount_a_in(
+    sqrt_price: u128,
+    liquidity: u128,
+    amount: u64,
+) -> MathResult<u128> {
+    let numerator = U256::from(liquidity) << RESOLUTION;
+    let sqrt_price = U256::from(sqrt_price);
+    let product = U256::from(amount) * sqrt_price;
+
+    let sqrt_price_next = match numerator.checked_mul(sqrt_price) {
+        Some(full_numerator) => {
+            checked_div_ceil_u256(full_numerator, checked_add_u256(numerator, product)?)?
+        }
+        // the same price, as `L / (L / sqrt_price + amount)`
+        None => checked_div_ceil_u256(
+            numerator,
+            checked_add_u256(checked_div_u256(numerator, sqrt_price)?, U256::from(amount))?,
+        )?,
+    };
+
+    to_u128(sqrt_price_next)
+}
+
+/// Returns the price after `amount` of token b is sold to `liquidity`,
+/// `sqrt_price + amount / L`, rounded down so the price never rises further
+/// than the amount pays for.
+fn get_next_sqrt_price_from_amount_b_in(
+    sqrt_price: u128,
+    liquidity: u128,
+    amount: u64,
+) -> MathResult<u128> {
+    let delta = checked_div((amount as u128) << RESOLUTION, liquidity)?;
+
+    checked_add(sqrt_price, delta)
+}
+
+fn amount_a_delta(
+    sqrt_price_lower: u128,
+    sqrt_price_upper: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> MathResult<U256> {
+    let numerator = checked_mul_u256(
+        U256::from(liquidity) << RESOLUTION,
+        U256::from(checked_sub(sqrt_price_upper, sqrt_price_lower)?),
+    )?;
+    let sqrt_price_lower = U256::from(sqrt_price_lower);
+    let sqrt_price_upper = U256::from(sqrt_price_upper);
+
+    if round_up {
+        checked_div_ceil_u256(
+            checked_div_ceil_u256(numerator, sqrt_price_upper)?,
+            sqrt_price_lower,
+        )
+    } else {
+        checked_div_u256(
+            checked_div_u256(numerator, sqrt_price_upper)?,
+            sqrt_price_lower,
+        )
+    }
+}
+
+fn amount_b_delta(
+    sqrt_price_lower: u128,
+    sqrt_price_upper: u128,
+    liquidity: u128,
+    round_up: bool,
+) -> MathResult<U256> {
+    let product = checked_mul_u256(
+        U256::from(liquidity),
+        U256::from(checked_sub(sqrt_price_upper, sqrt_price_lower)?),
+    )?;
+    let amount = product >> RESOLUTION;
+
+    if round_up && !(product % (U256::one() << RESOLUTION)).is_zero() {
+        checked_add_u256(amount, U256::one())
+    } else {
+        Ok(amount)
+    }
+}
+
+/// Returns the fee growth per unit of `liquidity` that `fee_amount` pays, in
+/// Q64.64.
+fn get_fee_growth(fee_amount: u64, liquidity: u128) -> MathResult<u128> {
+    checked_div((fee_amount as u128) << RESOLUTION, liquidity)
+}
+
+fn checked_sub_i128(liquidity: u128, liquidity_delta: i128) -> MathResult<u128> {
+    let negated = liquidity_delta
+        .checked_neg()
+        .ok_or(SeaAmmError::MathOverflow)?;
+
+    add_liquidity_delta(liquidity, negated)
+}
+
+fn checked_div_ceil_u256(a: U256, b: U256) -> MathResult<U256> {
+    let quotient = checked_div_u256(a, b)?;
+
+    if (quotient * b) == a {
+        Ok(quotient)
+    } else {
+        checked_add_u256(quotient, U256::one())
+    }
+}
+
+fn to_u64(a: U256) -> MathResult<u64> {
+    if a > U256::from(u64::MAX) {
+        return Err(SeaAmmError::MathOverflow);
+    }
+
+    Ok(a.as_u64())
+}
+
+fn to_u128(a: U256) -> MathResult<u128> {
+    if a > U256::from(u128::MAX) {
+        return Err(SeaAmmError::MathOverflow);
+    }
+
+    Ok(a.as_u128())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const Q64: u128 = 1 << 64;
+    const TICK_SPACING: u16 = 60;
+
+    // the tick arrays of `tick_arrays` consecutive starts from `start`
+    fn tick_arrays(start: i32, tick_arrays: i32) -> Vec<Tick> {
+        let ticks_in_array = TICK_ARRAY_SIZE as i32 * TICK_SPACING as i32;
+
+        (0..tick_arrays)
+            .flat_map(|i| get_tick_array_ticks(start + i * ticks_in_array, TICK_SPACING))
+            .collect()
+    }
+
+    // pool state as `swap` and the position handlers leave it
+    struct TestPool {
+        ticks: Vec<Tick>,
+        state: PoolState,
+    }
+
+    impl TestPool {
+        fn new(tick: i32) -> Self {
+            TestPool {
+                // from -1920 to 1920
+                ticks: tick_arrays(-1_920, 4),
+                state: PoolState {
+                    sqrt_price: sqrt_price_at_tick(tick).unwrap(),
+                    tick_current: tick,
+                    liquidity: 0,
+                    fee_growth_global_a: 0,
+                    fee_growth_global_b: 0,
+                },
+            }
+        }
+
+        fn deposit(&mut self, tick_lower: i32, tick_upper: i32, liquidity: u128) {
+            for (tick, is_upper) in [(tick_lower, false), (tick_upper, true)] {
+                update_tick(
+                    &mut self.ticks,
+                    tick,
+                    self.state.tick_current,
+                    liquidity as i128,
+                    is_upper,
+                    self.state.fee_growth_global_a,
+                    self.state.fee_growth_global_b,
+                )
+                .unwrap();
+            }
+
+            if (tick_lower..tick_upper).contains(&self.state.tick_current) {
+                self.state.liquidity += liquidity;
+            }
+        }
+
+        fn swap(&mut self, amount_in: u64, a_to_b: bool, fee_numerator: u64) -> MathResult<u64> {
+            let last_start_tick_index = if a_to_b {
+                self.ticks[0].index
+            } else {
+                self.ticks[self.ticks.len() - TICK_ARRAY_SIZE].index
+            };
+            let result = swap(
+                &mut self.ticks,
+                self.state,
+                amount_in,
+                a_to_b,
+                get_sqrt_price_limit(last_start_tick_index, TICK_SPACING, a_to_b)?,
+                fee_numerator,
+                1_000,
+            )?;
+            self.state = result.state;
+
+            Ok(result.amount_out)
+        }
+
+        // fees of a position deposited before any swap
+        fn fees_owed(&self, tick_lower: i32, tick_upper: i32, liquidity: u128) -> (u64, u64) {
+            let (inside_a, inside_b) = get_fee_growth_inside(
+                &self.ticks,
+                self.state.tick_current,
+                tick_lower,
+                tick_upper,
+                self.state.fee_growth_global_a,
+                self.state.fee_growth_global_b,
+            );
+
+            (
+                get_fees_owed(liquidity, inside_a, 0).unwrap(),
+                get_fees_owed(liquidity, inside_b, 0).unwrap(),
+            )
+        }
+    }
+
+    #[test]
+    fn tick_factors_are_successive_squares() {
+        for pair in TICK_FACTORS.windows(2) {
+            let square = (U256::from(pair[0]) * U256::from(pair[0])) >> 128;
+            let difference = if square > U256::from(pair[1]) {
+                square - U256::from(pair[1])
+            } else {
+                U256::from(pair[1]) - square
+            };
+            assert!(difference <= U256::from(pair[1] >> 100));
+        }
+    }
+
+    #[test]
+    fn sqrt_price_matches_float_math() {
+        assert_eq!(sqrt_price_at_tick(0).unwrap(), Q64);
+        assert_eq!(sqrt_price_at_tick(MIN_TICK).unwrap(), MIN_SQRT_PRICE);
+        assert_eq!(sqrt_price_at_tick(MAX_TICK).unwrap(), MAX_SQRT_PRICE);
+        assert!(sqrt_price_at_tick(MIN_TICK - 1).is_err());
+        assert!(sqrt_price_at_tick(MAX_TICK + 1).is_err());
+
+        for tick in [-400_000, -50_000, -600, -1, 1, 60, 12_345, 200_000, 443_000] {
+            let expected = 1.0001f64.powf(tick as f64 / 2.0) * Q64 as f64;
+            let sqrt_price = sqrt_price_at_tick(tick).unwrap() as f64;
+            assert!((sqrt_price - expected).abs() / expected < 1e-9, "{}", tick);
+        }
+    }
+
+    #[test]
+    fn tick_at_sqrt_price_inverts_sqrt_price_at_tick() {
+        for tick in [MIN_TICK, -300_001, -60, -1, 0, 1, 59, 123_456, MAX_TICK] {
+            let sqrt_price = sqrt_price_at_tick(tick).unwrap();
+            assert_eq!(tick_at_sqrt_price(sqrt_price).unwrap(), tick);
+            if tick > MIN_TICK {
+                assert_eq!(tick_at_sqrt_price(sqrt_price - 1).unwrap(), tick - 1);
+            }
+        }
+        assert!(tick_at_sqrt_price(MIN_SQRT_PRICE - 1).is_err());
+        assert!(tick_at_sqrt_price(MAX_SQRT_PRICE + 1).is_err());
+    }
+
+    #[test]
+    fn positions_hold_the_token_on_their_side_of_the_price() {
+        let liquidity = 1_000_000_000_000;
+        let deposit = |tick_lower, tick_upper, round_up| {
+            get_amounts_for_liquidity(Q64, 0, tick_lower, tick_upper, liquidity, round_up).unwrap()
+        };
+
+        let (amount_a, amount_b) = deposit(-600, 600, true);
+        // symmetric around a price of one
+        assert!(amount_a > 0 && amount_a.abs_diff(amount_b) <= 1);
+        let (withdraw_a, withdraw_b) = deposit(-600, 600, false);
+        assert!(withdraw_a <= amount_a && amount_a - withdraw_a <= 1);
+        assert!(withdraw_b <= amount_b && amount_b - withdraw_b <= 1);
+
+        assert_eq!(deposit(60, 600, true).1, 0);
+        assert_eq!(deposit(-600, -60, true).0, 0);
+    }
+
+    #[test]
+    fn swap_in_one_range_is_constant_product_on_virtual_reserves() {
+        let mut pool = TestPool::new(0);
+        pool.deposit(-600, 600, 1_000_000_000_000);
+
+        // virtual reserves of 1e12 of each token at a price of one
+        let amount_out = pool.swap(1_000_000, true, 0).unwrap();
+        assert!((999_998..=999_999).contains(&amount_out));
+        assert!(pool.state.sqrt_price < Q64);
+        assert_eq!(pool.state.tick_current, -1);
+    }
+
+    #[test]
+    fn swaps_cross_ticks_and_change_liquidity() {
+        let mut pool = TestPool::new(0);
+        pool.deposit(-600, 600, 1_000_000_000_000);
+        pool.deposit(-60, 60, 1_000_000_000_000);
+        assert_eq!(pool.state.liquidity, 2_000_000_000_000);
+
+        // far enough down to leave the narrow range
+        let amount_out = pool.swap(10_000_000_000, true, 0).unwrap();
+        assert!(pool.state.tick_current < -60);
+        assert_eq!(pool.state.liquidity, 1_000_000_000_000);
+
+        // and back into it
+        pool.swap(amount_out, false, 0).unwrap();
+        assert!((-60..60).contains(&pool.state.tick_current));
+        assert_eq!(pool.state.liquidity, 2_000_000_000_000);
+    }
+
+    #[test]
+    fn swap_fails_past_the_last_liquidity() {
+        let mut pool = TestPool::new(0);
+        pool.deposit(-60, 60, 1_000_000);
+        assert!(pool.swap(1_000_000_000, true, 3).is_err());
+        assert!(TestPool::new(0).swap(1, false, 3).is_err());
+    }
+
+    #[test]
+    fn swaps_up_stop_at_the_last_loaded_tick() {
+        let mut pool = TestPool::new(0);
+        pool.deposit(-600, 1_200, 1_000_000_000_000);
+        pool.deposit(960, 1_200, 1_000_000_000_000);
+
+        // only the array from 0 to 900 is loaded, so the position starting
+        // at 960 can't be crossed
+        let limit = get_sqrt_price_limit(0, TICK_SPACING, false).unwrap();
+        assert_eq!(limit, sqrt_price_at_tick(900).unwrap());
+        let amount_in = get_amount_b_delta(Q64, limit, 1_000_000_000_000, true).unwrap();
+        let result = swap(
+            &mut pool.ticks[32..48],
+            pool.state,
+            amount_in,
+            false,
+            limit,
+            0,
+            1_000,
+        )
+        .unwrap();
+        assert_eq!(result.state.tick_current, 900);
+        assert_eq!(result.state.liquidity, 1_000_000_000_000);
+
+        // with the next array loaded the swap goes on into that position
+        pool.state = result.state;
+        let result = swap(
+            &mut pool.ticks[32..64],
+            pool.state,
+            10_000_000_000,
+            false,
+            get_sqrt_price_limit(960, TICK_SPACING, false).unwrap(),
+            0,
+            1_000,
+        )
+        .unwrap();
+        assert!((960..1_200).contains(&result.state.tick_current));
+        assert_eq!(result.state.liquidity, 2_000_000_000_000);
+    }
+
+    #[test]
+    fn fees_go_to_the_liquidity_in_range() {
+        let mut pool = TestPool::new(0);
+        pool.deposit(-600, 600, 1_000_000_000_000);
+        pool.deposit(-600, 600, 1_000_000_000_000);
+        pool.deposit(600, 1_200, 1_000_000_000_000);
+
+        pool.swap(1_000_000, true, 3).unwrap();
+        // a 0.3% fee on 1e6, shared by the two positions in range
+        let (fees_a, fees_b) = pool.fees_owed(-600, 600, 1_000_000_000_000);
+        assert!((1_499..=1_500).contains(&fees_a));
+        assert_eq!(fees_b, 0);
+        assert_eq!(pool.fees_owed(600, 1_200, 1_000_000_000_000), (0, 0));
+    }
+
+    #[test]
+    fn fees_stop_when_the_price_leaves_a_range() {
+        let mut pool = TestPool::new(0);
+        pool.deposit(-600, 600, 1_000_000_000_000);
+        pool.deposit(-60, 60, 1_000_000_000_000);
+
+        pool.swap(10_000_000_000, true, 3).unwrap();
+        let (wide_fees, _) = pool.fees_owed(-600, 600, 1_000_000_000_000);
+        let (narrow_fees, _) = pool.fees_owed(-60, 60, 1_000_000_000_000);
+        assert!(narrow_fees > 0 && narrow_fees < wide_fees);
+
+        // swaps below the narrow range leave its fees alone
+        pool.swap(1_000_000, true, 3).unwrap();
+        assert_eq!(pool.fees_owed(-60, 60, 1_000_000_000_000).0, narrow_fees);
+        assert!(pool.fees_owed(-600, 600, 1_000_000_000_000).0 > wide_fees);
+    }
+
+    #[test]
+    fn tick_arrays_start_on_multiples_of_their_ticks() {
+        assert_eq!(get_tick_array_start_index(0, TICK_SPACING), 0);
+        assert_eq!(get_tick_array_start_index(959, TICK_SPACING), 0);
+        assert_eq!(get_tick_array_start_index(960, TICK_SPACING), 960);
+        assert_eq!(get_tick_array_start_index(-1, TICK_SPACING), -960);
+        assert_eq!(get_tick_array_start_index(-960, TICK_SPACING), -960);
+
+        let ticks = get_tick_array_ticks(-960, TICK_SPACING);
+        assert_eq!(ticks.len(), TICK_ARRAY_SIZE);
+        assert_eq!(
+            (ticks[0].index, ticks[TICK_ARRAY_SIZE - 1].index),
+            (-960, -60)
+        );
+    }
+
+    #[test]
+    fn full_tick_arrays_still_take_positions() {
+        let mut pool = TestPool::new(0);
+        // dust on every tick of the pool
+        let indexes: Vec<i32> = pool.ticks.iter().map(|tick| tick.index).collect();
+        for pair in indexes.chunks(2) {
+            pool.deposit(pair[0], pair[1], 1);
+        }
+        assert!(pool.ticks.iter().all(|tick| tick.liquidity_gross > 0));
+
+        // another position shares the ticks it starts and ends at
+        pool.deposit(-600, 600, 1_000_000_000_000);
+        assert_eq!(pool.state.liquidity, 1_000_000_000_001);
+        let amount_out = pool.swap(1_000_000, true, 0).unwrap();
+        assert!((999_997..=999_999).contains(&amount_out));
+    }
+
+    #[test]
+    fn ticks_outside_the_tick_array_are_rejected() {
+        let mut ticks = get_tick_array_ticks(-960, TICK_SPACING);
+        assert!(update_tick(&mut ticks, 0, 0, 1, true, 0, 0).is_err());
+        assert!(update_tick(&mut ticks, -50, 0, 1, false, 0, 0).is_err());
+        update_tick(&mut ticks, -960, 0, 1, false, 0, 0).unwrap();
+    }
+
+    #[test]
+    fn empty_ticks_are_cleared() {
+        let mut ticks = get_tick_array_ticks(-960, TICK_SPACING);
+        update_tick(&mut ticks, -60, 0, 1, false, 100, 200).unwrap();
+        update_tick(&mut ticks, -60, 0, 2, true, 100, 200).unwrap();
+        assert_eq!(ticks[15].liquidity_net, -1);
+        assert_eq!(ticks[15].liquidity_gross, 3);
+
+        update_tick(&mut ticks, -60, 0, -1, false, 300, 400).unwrap();
+        update_tick(&mut ticks, -60, 0, -2, true, 300, 400).unwrap();
+        assert_eq!(ticks, get_tick_array_ticks(-960, TICK_SPACING));
+
+        // and take the fee growth of when they are initialized again
+        update_tick(&mut ticks, -60, 0, 1, false, 300, 400).unwrap();
+        assert_eq!(
+            (
+                ticks[15].fee_growth_outside_a,
+                ticks[15].fee_growth_outside_b
+            ),
+            (300, 400)
+        );
+    }
+
+    #[test]
+    fn new_ticks_count_past_fees_as_below_the_price() {
+        let mut ticks = tick_arrays(-960, 2);
+        update_tick(&mut ticks, -60, 0, 1, false, 100, 200).unwrap();
+        update_tick(&mut ticks, 60, 0, 1, true, 100, 200).unwrap();
+        assert_eq!(
+            (
+                ticks[15].fee_growth_outside_a,
+                ticks[15].fee_growth_outside_b
+            ),
+            (100, 200)
+        );
+        assert_eq!(
+            (
+                ticks[17].fee_growth_outside_a,
+                ticks[17].fee_growth_outside_b
+            ),
+            (0, 0)
+        );
+        // so a new position has earned nothing yet
+        assert_eq!(get_fee_growth_inside(&ticks, 0, -60, 60, 100, 200), (0, 0));
+    }
+}
@@ -1 +1,14 @@
+// program.rs keeps the shape of the Seahorse output it started from: handlers
+// take every account and argument separately, initialize variables they
+// assign later, return early, clone copies and spell checks as
+// `if !(condition)`
+#[allow(
+    unused_assignments,
+    clippy::clone_on_copy,
+    clippy::needless_return,
+    clippy::nonminimal_bool,
+    clippy::result_large_err,
+    clippy::too_many_arguments,
+    clippy::useless_asref
+)]
 pub mod program;
@@ -1,37 +1,72 @@
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 #![allow(unused_mut)]
-use crate::{assign, id, index_assign, seahorse_util::*};
-use anchor_lang::{prelude::*, solana_program};
+use crate::{assign, error::SeaAmmError, id, index_assign, seahorse_util::*};
+use anchor_lang::{prelude::*, solana_program, system_program};
 use anchor_spl::token::{self, Mint, Token, TokenAccount};
 use std::{cell::RefCell, rc::Rc};
 
+/// Most fee tiers a `Config` can list.
+pub const MAX_FEE_TIERS: usize = 8;
+
+/// Fewest tokens a `MultiPool` can hold. Pairs are `Pool`s.
+pub const MIN_MULTI_POOL_TOKENS: usize = 3;
+
+/// Most tokens a `MultiPool` can hold.
+pub const MAX_MULTI_POOL_TOKENS: usize = 8;
+
+/// Ticks in each `TickArray`.
+pub const TICK_ARRAY_SIZE: usize = 16;
+
+/// A pair of tokens whose liquidity is provided in price ranges by
+/// `Position`s, instead of over every price. The ticks that positions start
+/// or end at are kept in the pool's `TickArray`s.
 #[account]
 #[derive(Debug)]
-pub struct Pool {
+pub struct ConcentratedPool {
     pub bump: u8,
     pub authority: Pubkey,
     pub token_mint_a: Pubkey,
     pub token_mint_b: Pubkey,
     pub token_vault_a: Pubkey,
     pub token_vault_b: Pubkey,
-    pub lp_token_mint: Pubkey,
+    pub token_vault_a_bump: u8,
+    pub token_vault_b_bump: u8,
+    pub fee_numerator: u64,
+    pub fee_denominator: u64,
+    pub status: PoolStatus,
+    pub tick_spacing: u16,
+    pub sqrt_price: u128,
+    pub tick_current: i32,
+    pub liquidity: u128,
+    pub fee_growth_global_a: u128,
+    pub fee_growth_global_b: u128,
 }
 
-impl<'info, 'entrypoint> Pool {
+impl<'info, 'entrypoint> ConcentratedPool {
     pub fn load(
         account: &'entrypoint mut Box<Account<'info, Self>>,
         programs_map: &'entrypoint ProgramsMap<'info>,
-    ) -> Mutable<LoadedPool<'info, 'entrypoint>> {
+    ) -> Mutable<LoadedConcentratedPool<'info, 'entrypoint>> {
         let bump = account.bump;
         let authority = account.authority.clone();
         let token_mint_a = account.token_mint_a.clone();
         let token_mint_b = account.token_mint_b.clone();
         let token_vault_a = account.token_vault_a.clone();
         let token_vault_b = account.token_vault_b.clone();
-        let lp_token_mint = account.lp_token_mint.clone();
+        let token_vault_a_bump = account.token_vault_a_bump;
+        let token_vault_b_bump = account.token_vault_b_bump;
+        let fee_numerator = account.fee_numerator;
+        let fee_denominator = account.fee_denominator;
+        let status = account.status.clone();
+        let tick_spacing = account.tick_spacing;
+        let sqrt_price = account.sqrt_price;
+        let tick_current = account.tick_current;
+        let liquidity = account.liquidity;
+        let fee_growth_global_a = account.fee_growth_global_a;
+        let fee_growth_global_b = account.fee_growth_global_b;
 
-        Mutable::new(LoadedPool {
+        Mutable::new(LoadedConcentratedPool {
             __account__: account,
             __programs__: programs_map,
             bump,
@@ -40,12 +75,23 @@ impl<'info, 'entrypoint> Pool {
             token_mint_b,
             token_vault_a,
             token_vault_b,
-            lp_token_mint,
+            token_vault_a_bump,
+            token_vault_b_bump,
+            fee_numerator,
+            fee_denominator,
+            status,
+            tick_spacing,
+            sqrt_price,
+            tick_current,
+            liquidity,
+            fee_growth_global_a,
+            fee_growth_global_b,
         })
     }
 
-    pub fn store(loaded: Mutable<LoadedPool>) {
+    pub fn store(loaded: Mutable<LoadedConcentratedPool>) {
         let mut loaded = loaded.borrow_mut();
+
         let bump = loaded.bump;
 
         loaded.__account__.bump = bump;
@@ -70,15 +116,55 @@ impl<'info, 'entrypoint> Pool {
 
         loaded.__account__.token_vault_b = token_vault_b;
 
-        let lp_token_mint = loaded.lp_token_mint.clone();
+        let token_vault_a_bump = loaded.token_vault_a_bump;
 
-        loaded.__account__.lp_token_mint = lp_token_mint;
+        loaded.__account__.token_vault_a_bump = token_vault_a_bump;
+
+        let token_vault_b_bump = loaded.token_vault_b_bump;
+
+        loaded.__account__.token_vault_b_bump = token_vault_b_bump;
+
+        let fee_numerator = loaded.fee_numerator;
+
+        loaded.__account__.fee_numerator = fee_numerator;
+
+        let fee_denominator = loaded.fee_denominator;
+
+        loaded.__account__.fee_denominator = fee_denominator;
+
+        let status = loaded.status.clone();
+
+        loaded.__account__.status = status;
+
+        let tick_spacing = loaded.tick_spacing;
+
+        loaded.__account__.tick_spacing = tick_spacing;
+
+        let sqrt_price = loaded.sqrt_price;
+
+        loaded.__account__.sqrt_price = sqrt_price;
+
+        let tick_current = loaded.tick_current;
+
+        loaded.__account__.tick_current = tick_current;
+
+        let liquidity = loaded.liquidity;
+
+        loaded.__account__.liquidity = liquidity;
+
+        let fee_growth_global_a = loaded.fee_growth_global_a;
+
+        loaded.__account__.fee_growth_global_a = fee_growth_global_a;
+
+        let fee_growth_global_b = loaded.fee_growth_global_b;
+
+        loaded.__account__.fee_growth_global_b = fee_growth_global_b;
     }
 }
 
 #[derive(Debug)]
-pub struct LoadedPool<'info, 'entrypoint> {
-    pub __account__: &'entrypoint mut Box<Account<'info, Pool>>,
+pub struct LoadedConcentratedPool<'info, 'entrypoint> {
+    pub __account__: &'entrypoint mut Box<Account<'info, ConcentratedPool>>,
     pub __programs__: &'entrypoint ProgramsMap<'info>,
     pub bump: u8,
     pub authority: Pubkey,
@@ -86,159 +172,2476 @@ pub struct LoadedPool<'info, 'entrypoint> {
     pub token_mint_b: Pubkey,
     pub token_vault_a: Pubkey,
     pub token_vault_b: Pubkey,
-    pub lp_token_mint: Pubkey,
+    pub token_vault_a_bump: u8,
+    pub token_vault_b_bump: u8,
+    pub fee_numerator: u64,
+    pub fee_denominator: u64,
+    pub status: PoolStatus,
+    pub tick_spacing: u16,
+    pub sqrt_price: u128,
+    pub tick_current: i32,
+    pub liquidity: u128,
+    pub fee_growth_global_a: u128,
+    pub fee_growth_global_b: u128,
 }
 
-pub fn add_liquidity_handler<'info>(
-    mut user: SeahorseSigner<'info, '_>,
-    mut pool: Mutable<LoadedPool<'info, '_>>,
-    mut token_mint_a: SeahorseAccount<'info, '_, Mint>,
-    mut token_mint_b: SeahorseAccount<'info, '_, Mint>,
-    mut user_token_account_a: SeahorseAccount<'info, '_, TokenAccount>,
-    mut user_token_account_b: SeahorseAccount<'info, '_, TokenAccount>,
-    mut pool_token_vault_a: SeahorseAccount<'info, '_, TokenAccount>,
-    mut pool_token_vault_b: SeahorseAccount<'info, '_, TokenAccount>,
-    mut lp_token_mint: SeahorseAccount<'info, '_, Mint>,
-    mut user_lp_token_account: Empty<SeahorseAccount<'info, '_, TokenAccount>>,
-    mut token_amount_a: u64,
-    mut token_amount_b: u64,
-) -> () {
-    let mut pool_pda = Pubkey::find_program_address(
-        Mutable::new(vec![
-            "pool".to_string().as_bytes().as_ref(),
-            token_mint_a.key().as_ref(),
-            token_mint_b.key().as_ref(),
-        ])
-        .borrow()
-        .as_slice(),
-        &id(),
-    )
-    .0;
+/// Program wide settings, stored in the singleton `["config"]` account.
+#[account]
+#[derive(Debug)]
+pub struct Config {
+    pub bump: u8,
+    pub admin: Pubkey,
+    pub protocol_fee_numerator: u64,
+    pub protocol_fee_denominator: u64,
+    pub pool_creation_fee: u64,
+    pub fee_tiers: Vec<FeeTier>,
+}
 
-    if !(pool_pda == pool.borrow().__account__.key()) {
-        panic!("Pool address is not valid");
+impl<'info, 'entrypoint> Config {
+    pub fn load(
+        account: &'entrypoint mut Box<Account<'info, Self>>,
+        programs_map: &'entrypoint ProgramsMap<'info>,
+    ) -> Mutable<LoadedConfig<'info, 'entrypoint>> {
+        let bump = account.bump;
+        let admin = account.admin.clone();
+        let protocol_fee_numerator = account.protocol_fee_numerator;
+        let protocol_fee_denominator = account.protocol_fee_denominator;
+        let pool_creation_fee = account.pool_creation_fee;
+        let fee_tiers = account.fee_tiers.clone();
+
+        Mutable::new(LoadedConfig {
+            __account__: account,
+            __programs__: programs_map,
+            bump,
+            admin,
+            protocol_fee_numerator,
+            protocol_fee_denominator,
+            pool_creation_fee,
+            fee_tiers,
+        })
     }
 
-    let mut lp_token_mint_pda = Pubkey::find_program_address(
-        Mutable::new(vec![
-            "lp-token-mint".to_string().as_bytes().as_ref(),
-            token_mint_a.key().as_ref(),
-            token_mint_b.key().as_ref(),
-        ])
-        .borrow()
-        .as_slice(),
-        &id(),
-    )
-    .0;
+    pub fn store(loaded: Mutable<LoadedConfig>) {
+        let mut loaded = loaded.borrow_mut();
+        let bump = loaded.bump;
 
-    if !(lp_token_mint_pda == lp_token_mint.key()) {
-        panic!("LP token mint address is not valid");
-    }
+        loaded.__account__.bump = bump;
 
-    if !((token_amount_a > 0) && (token_amount_b > 0)) {
-        panic!("Token amount must be greater than zero");
-    }
+        let admin = loaded.admin.clone();
 
-    token::transfer(
-        CpiContext::new(
-            user_token_account_a.programs.get("token_program"),
-            token::Transfer {
-                from: user_token_account_a.to_account_info(),
-                authority: user.clone().to_account_info(),
-                to: pool_token_vault_a.clone().to_account_info(),
-            },
-        ),
-        token_amount_a.clone(),
-    )
-    .unwrap();
+        loaded.__account__.admin = admin;
 
-    token::transfer(
-        CpiContext::new(
-            user_token_account_b.programs.get("token_program"),
-            token::Transfer {
-                from: user_token_account_b.to_account_info(),
-                authority: user.clone().to_account_info(),
-                to: pool_token_vault_b.clone().to_account_info(),
-            },
-        ),
-        token_amount_b.clone(),
-    )
-    .unwrap();
+        let protocol_fee_numerator = loaded.protocol_fee_numerator;
 
-    if (pool_token_vault_a.amount > 0) || (pool_token_vault_b.amount > 0) {
-        if !((pool_token_vault_a.amount * token_amount_b)
-            == (pool_token_vault_b.amount * token_amount_a))
-        {
-            panic!("Change amount of token a or token b to add liquidity");
-        }
+        loaded.__account__.protocol_fee_numerator = protocol_fee_numerator;
+
+        let protocol_fee_denominator = loaded.protocol_fee_denominator;
+
+        loaded.__account__.protocol_fee_denominator = protocol_fee_denominator;
+
+        let pool_creation_fee = loaded.pool_creation_fee;
+
+        loaded.__account__.pool_creation_fee = pool_creation_fee;
+
+        let fee_tiers = loaded.fee_tiers.clone();
+
+        loaded.__account__.fee_tiers = fee_tiers;
     }
+}
 
-    let mut total_lp_tokens = lp_token_mint.supply;
-    let mut lp_token_mint_amount = 0;
+#[derive(Debug)]
+pub struct LoadedConfig<'info, 'entrypoint> {
+    pub __account__: &'entrypoint mut Box<Account<'info, Config>>,
+    pub __programs__: &'entrypoint ProgramsMap<'info>,
+    pub bump: u8,
+    pub admin: Pubkey,
+    pub protocol_fee_numerator: u64,
+    pub protocol_fee_denominator: u64,
+    pub pool_creation_fee: u64,
+    pub fee_tiers: Vec<FeeTier>,
+}
 
-    if total_lp_tokens == 0 {
-        lp_token_mint_amount = (token_amount_a * token_amount_b).pow(2);
-    } else {
-        lp_token_mint_amount = ((token_amount_a * total_lp_tokens) / pool_token_vault_a.amount)
-            .min((token_amount_b * total_lp_tokens) / pool_token_vault_b.amount);
+/// The curve a pool prices swaps and deposits on, chosen at `create_pool`.
+#[derive(Clone, Copy, Debug, PartialEq, AnchorSerialize, AnchorDeserialize)]
+pub enum CurveType {
+    /// `x * y = k`, for tokens with independent prices.
+    ConstantProduct,
+    /// The StableSwap invariant with the pool's `amp`, for pegged tokens.
+    StableSwap,
+    /// `x^w_a * y^w_b = k` with the pool's `weight_a` and `weight_b`.
+    Weighted,
+}
+
+/// A swap fee of `fee_numerator / fee_denominator` that pools may be created
+/// with.
+#[derive(Clone, Copy, Debug, PartialEq, AnchorSerialize, AnchorDeserialize)]
+pub struct FeeTier {
+    pub fee_numerator: u64,
+    pub fee_denominator: u64,
+}
+
+/// A pool of three or more tokens sharing one lp token mint. It weighs its
+/// tokens equally, so a swap between any two of them is priced on the
+/// constant product of their two reserves. Its vaults and lp token mint
+/// belong to the `["multi-pool-authority", multi_pool]` address.
+#[account]
+#[derive(Debug)]
+pub struct MultiPool {
+    pub authority_bump: u8,
+    pub authority: Pubkey,
+    pub lp_token_mint: Pubkey,
+    pub lp_token_mint_bump: u8,
+    pub locked_lp_token_account_bump: u8,
+    pub fee_numerator: u64,
+    pub fee_denominator: u64,
+    pub status: PoolStatus,
+    pub token_mints: Vec<Pubkey>,
+    pub token_vaults: Vec<Pubkey>,
+}
+
+impl<'info, 'entrypoint> MultiPool {
+    pub fn load(
+        account: &'entrypoint mut Box<Account<'info, Self>>,
+        programs_map: &'entrypoint ProgramsMap<'info>,
+    ) -> Mutable<LoadedMultiPool<'info, 'entrypoint>> {
+        let authority_bump = account.authority_bump;
+        let authority = account.authority.clone();
+        let lp_token_mint = account.lp_token_mint.clone();
+        let lp_token_mint_bump = account.lp_token_mint_bump;
+        let locked_lp_token_account_bump = account.locked_lp_token_account_bump;
+        let fee_numerator = account.fee_numerator;
+        let fee_denominator = account.fee_denominator;
+        let status = account.status.clone();
+        let token_mints = account.token_mints.clone();
+        let token_vaults = account.token_vaults.clone();
+
+        Mutable::new(LoadedMultiPool {
+            __account__: account,
+            __programs__: programs_map,
+            authority_bump,
+            authority,
+            lp_token_mint,
+            lp_token_mint_bump,
+            locked_lp_token_account_bump,
+            fee_numerator,
+            fee_denominator,
+            status,
+            token_mints,
+            token_vaults,
+        })
     }
 
-    if !(lp_token_mint_amount > 0) {
-        panic!("No lp tokens to mint");
+    pub fn store(loaded: Mutable<LoadedMultiPool>) {
+        let mut loaded = loaded.borrow_mut();
+        let authority_bump = loaded.authority_bump;
+
+        loaded.__account__.authority_bump = authority_bump;
+
+        let authority = loaded.authority.clone();
+
+        loaded.__account__.authority = authority;
+
+        let lp_token_mint = loaded.lp_token_mint.clone();
+
+        loaded.__account__.lp_token_mint = lp_token_mint;
+
+        let lp_token_mint_bump = loaded.lp_token_mint_bump;
+
+        loaded.__account__.lp_token_mint_bump = lp_token_mint_bump;
+
+        let locked_lp_token_account_bump = loaded.locked_lp_token_account_bump;
+
+        loaded.__account__.locked_lp_token_account_bump = locked_lp_token_account_bump;
+
+        let fee_numerator = loaded.fee_numerator;
+
+        loaded.__account__.fee_numerator = fee_numerator;
+
+        let fee_denominator = loaded.fee_denominator;
+
+        loaded.__account__.fee_denominator = fee_denominator;
+
+        let status = loaded.status.clone();
+
+        loaded.__account__.status = status;
+
+        let token_mints = loaded.token_mints.clone();
+
+        loaded.__account__.token_mints = token_mints;
+
+        let token_vaults = loaded.token_vaults.clone();
+
+        loaded.__account__.token_vaults = token_vaults;
     }
+}
 
-    let mut user_lp_token_account = user_lp_token_account.account.clone();
+#[derive(Debug)]
+pub struct LoadedMultiPool<'info, 'entrypoint> {
+    pub __account__: &'entrypoint mut Box<Account<'info, MultiPool>>,
+    pub __programs__: &'entrypoint ProgramsMap<'info>,
+    pub authority_bump: u8,
+    pub authority: Pubkey,
+    pub lp_token_mint: Pubkey,
+    pub lp_token_mint_bump: u8,
+    pub locked_lp_token_account_bump: u8,
+    pub fee_numerator: u64,
+    pub fee_denominator: u64,
+    pub status: PoolStatus,
+    pub token_mints: Vec<Pubkey>,
+    pub token_vaults: Vec<Pubkey>,
+}
 
-    token::mint_to(
-        CpiContext::new_with_signer(
-            lp_token_mint.programs.get("token_program"),
-            token::MintTo {
-                mint: lp_token_mint.to_account_info(),
-                authority: pool.borrow().__account__.to_account_info(),
-                to: user_lp_token_account.clone().to_account_info(),
-            },
-            &[Mutable::new(vec![
-                "pool".to_string().as_bytes().as_ref(),
-                token_mint_a.key().as_ref(),
-                token_mint_b.key().as_ref(),
-            ])
-            .borrow()
-            .as_slice()],
-        ),
-        lp_token_mint_amount.clone(),
-    )
-    .unwrap();
+#[account]
+#[derive(Debug)]
+pub struct Pool {
+    pub bump: u8,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_vault_a: Pubkey,
+    pub token_vault_b: Pubkey,
+    pub lp_token_mint: Pubkey,
+    pub fee_numerator: u64,
+    pub fee_denominator: u64,
+    pub protocol_fee_numerator: u64,
+    pub protocol_fee_denominator: u64,
+    pub protocol_fees_a: u64,
+    pub protocol_fees_b: u64,
+    pub token_vault_a_bump: u8,
+    pub token_vault_b_bump: u8,
+    pub lp_token_mint_bump: u8,
+    pub locked_lp_token_account_bump: u8,
+    pub status: PoolStatus,
+    pub curve_type: CurveType,
+    pub initial_amp: u64,
+    pub target_amp: u64,
+    pub ramp_start_timestamp: i64,
+    pub ramp_end_timestamp: i64,
+    pub weight_a: u64,
+    pub weight_b: u64,
+    pub precision_multiplier_a: u64,
+    pub precision_multiplier_b: u64,
 }
 
-pub fn create_pool_handler<'info>(
-    mut authority: SeahorseSigner<'info, '_>,
-    mut pool: Empty<Mutable<LoadedPool<'info, '_>>>,
-    mut token_mint_a: SeahorseAccount<'info, '_, Mint>,
-    mut token_mint_b: SeahorseAccount<'info, '_, Mint>,
-    mut token_vault_a: Empty<SeahorseAccount<'info, '_, TokenAccount>>,
-    mut token_vault_b: Empty<SeahorseAccount<'info, '_, TokenAccount>>,
-    mut lp_token_mint: Empty<SeahorseAccount<'info, '_, Mint>>,
-) -> () {
-    let mut bump = pool.bump.unwrap();
-    let mut pool = pool.account.clone();
-    let mut token_vault_a = token_vault_a.account.clone();
-    let mut token_vault_b = token_vault_b.account.clone();
-    let mut lp_token_mint = lp_token_mint.account.clone();
+impl<'info, 'entrypoint> Pool {
+    pub fn load(
+        account: &'entrypoint mut Box<Account<'info, Self>>,
+        programs_map: &'entrypoint ProgramsMap<'info>,
+    ) -> Mutable<LoadedPool<'info, 'entrypoint>> {
+        let bump = account.bump;
+        let authority = account.authority.clone();
+        let pending_authority = account.pending_authority.clone();
+        let token_mint_a = account.token_mint_a.clone();
+        let token_mint_b = account.token_mint_b.clone();
+        let token_vault_a = account.token_vault_a.clone();
+        let token_vault_b = account.token_vault_b.clone();
+        let lp_token_mint = account.lp_token_mint.clone();
+        let fee_numerator = account.fee_numerator;
+        let fee_denominator = account.fee_denominator;
+        let protocol_fee_numerator = account.protocol_fee_numerator;
+        let protocol_fee_denominator = account.protocol_fee_denominator;
+        let protocol_fees_a = account.protocol_fees_a;
+        let protocol_fees_b = account.protocol_fees_b;
+        let token_vault_a_bump = account.token_vault_a_bump;
+        let token_vault_b_bump = account.token_vault_b_bump;
+        let lp_token_mint_bump = account.lp_token_mint_bump;
+        let locked_lp_token_account_bump = account.locked_lp_token_account_bump;
+        let status = account.status.clone();
+        let curve_type = account.curve_type.clone();
+        let initial_amp = account.initial_amp;
+        let target_amp = account.target_amp;
+        let ramp_start_timestamp = account.ramp_start_timestamp;
+        let ramp_end_timestamp = account.ramp_end_timestamp;
+        let weight_a = account.weight_a;
+        let weight_b = account.weight_b;
+        let precision_multiplier_a = account.precision_multiplier_a;
+        let precision_multiplier_b = account.precision_multiplier_b;
 
-    assign!(pool.borrow_mut().bump, bump);
+        Mutable::new(LoadedPool {
+            __account__: account,
+            __programs__: programs_map,
+            bump,
+            authority,
+            pending_authority,
+            token_mint_a,
+            token_mint_b,
+            token_vault_a,
+            token_vault_b,
+            lp_token_mint,
+            fee_numerator,
+            fee_denominator,
+            protocol_fee_numerator,
+            protocol_fee_denominator,
+            protocol_fees_a,
+            protocol_fees_b,
+            token_vault_a_bump,
+            token_vault_b_bump,
+            lp_token_mint_bump,
+            locked_lp_token_account_bump,
+            status,
+            curve_type,
+            initial_amp,
+            target_amp,
+            ramp_start_timestamp,
+            ramp_end_timestamp,
+            weight_a,
+            weight_b,
+            precision_multiplier_a,
+            precision_multiplier_b,
+        })
+    }
 
-    assign!(pool.borrow_mut().authority, authority.key());
+    pub fn store(loaded: Mutable<LoadedPool>) {
+        let mut loaded = loaded.borrow_mut();
+        let bump = loaded.bump;
 
-    assign!(pool.borrow_mut().token_mint_a, token_mint_a.key());
+        loaded.__account__.bump = bump;
 
-    assign!(pool.borrow_mut().token_mint_b, token_mint_b.key());
+        let authority = loaded.authority.clone();
 
-    assign!(pool.borrow_mut().token_vault_a, token_vault_a.key());
+        loaded.__account__.authority = authority;
 
-    assign!(pool.borrow_mut().token_vault_b, token_vault_b.key());
+        let pending_authority = loaded.pending_authority.clone();
 
-    assign!(pool.borrow_mut().lp_token_mint, lp_token_mint.key());
-}
+        loaded.__account__.pending_authority = pending_authority;
+
+        let token_mint_a = loaded.token_mint_a.clone();
+
+        loaded.__account__.token_mint_a = token_mint_a;
+
+        let token_mint_b = loaded.token_mint_b.clone();
+
+        loaded.__account__.token_mint_b = token_mint_b;
+
+        let token_vault_a = loaded.token_vault_a.clone();
+
+        loaded.__account__.token_vault_a = token_vault_a;
+
+        let token_vault_b = loaded.token_vault_b.clone();
+
+        loaded.__account__.token_vault_b = token_vault_b;
+
+        let lp_token_mint = loaded.lp_token_mint.clone();
+
+        loaded.__account__.lp_token_mint = lp_token_mint;
+
+        let fee_numerator = loaded.fee_numerator;
+
+        loaded.__account__.fee_numerator = fee_numerator;
+
+        let fee_denominator = loaded.fee_denominator;
+
+        loaded.__account__.fee_denominator = fee_denominator;
+
+        let protocol_fee_numerator = loaded.protocol_fee_numerator;
+
+        loaded.__account__.protocol_fee_numerator = protocol_fee_numerator;
+
+        let protocol_fee_denominator = loaded.protocol_fee_denominator;
+
+        loaded.__account__.protocol_fee_denominator = protocol_fee_denominator;
+
+        let protocol_fees_a = loaded.protocol_fees_a;
+
+        loaded.__account__.protocol_fees_a = protocol_fees_a;
+
+        let protocol_fees_b = loaded.protocol_fees_b;
+
+        loaded.__account__.protocol_fees_b = protocol_fees_b;
+
+        let token_vault_a_bump = loaded.token_vault_a_bump;
+
+        loaded.__account__.token_vault_a_bump = token_vault_a_bump;
+
+        let token_vault_b_bump = loaded.token_vault_b_bump;
+
+        loaded.__account__.token_vault_b_bump = token_vault_b_bump;
+
+        let lp_token_mint_bump = loaded.lp_token_mint_bump;
+
+        loaded.__account__.lp_token_mint_bump = lp_token_mint_bump;
+
+        let locked_lp_token_account_bump = loaded.locked_lp_token_account_bump;
+
+        loaded.__account__.locked_lp_token_account_bump = locked_lp_token_account_bump;
+
+        let status = loaded.status.clone();
+
+        loaded.__account__.status = status;
+
+        let curve_type = loaded.curve_type.clone();
+
+        loaded.__account__.curve_type = curve_type;
+
+        let initial_amp = loaded.initial_amp;
+
+        loaded.__account__.initial_amp = initial_amp;
+
+        let target_amp = loaded.target_amp;
+
+        loaded.__account__.target_amp = target_amp;
+
+        let ramp_start_timestamp = loaded.ramp_start_timestamp;
+
+        loaded.__account__.ramp_start_timestamp = ramp_start_timestamp;
+
+        let ramp_end_timestamp = loaded.ramp_end_timestamp;
+
+        loaded.__account__.ramp_end_timestamp = ramp_end_timestamp;
+
+        let weight_a = loaded.weight_a;
+
+        loaded.__account__.weight_a = weight_a;
+
+        let weight_b = loaded.weight_b;
+
+        loaded.__account__.weight_b = weight_b;
+
+        let precision_multiplier_a = loaded.precision_multiplier_a;
+
+        loaded.__account__.precision_multiplier_a = precision_multiplier_a;
+
+        let precision_multiplier_b = loaded.precision_multiplier_b;
+
+        loaded.__account__.precision_multiplier_b = precision_multiplier_b;
+    }
+}
+
+#[derive(Debug)]
+pub struct LoadedPool<'info, 'entrypoint> {
+    pub __account__: &'entrypoint mut Box<Account<'info, Pool>>,
+    pub __programs__: &'entrypoint ProgramsMap<'info>,
+    pub bump: u8,
+    pub authority: Pubkey,
+    pub pending_authority: Pubkey,
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub token_vault_a: Pubkey,
+    pub token_vault_b: Pubkey,
+    pub lp_token_mint: Pubkey,
+    pub fee_numerator: u64,
+    pub fee_denominator: u64,
+    pub protocol_fee_numerator: u64,
+    pub protocol_fee_denominator: u64,
+    pub protocol_fees_a: u64,
+    pub protocol_fees_b: u64,
+    pub token_vault_a_bump: u8,
+    pub token_vault_b_bump: u8,
+    pub lp_token_mint_bump: u8,
+    pub locked_lp_token_account_bump: u8,
+    pub status: PoolStatus,
+    pub curve_type: CurveType,
+    pub initial_amp: u64,
+    pub target_amp: u64,
+    pub ramp_start_timestamp: i64,
+    pub ramp_end_timestamp: i64,
+    pub weight_a: u64,
+    pub weight_b: u64,
+    pub precision_multiplier_a: u64,
+    pub precision_multiplier_b: u64,
+}
+
+/// What a pool currently allows. The pool authority sets it with
+/// `set_pool_status`, e.g. to halt a pool while a vulnerability is fixed.
+#[derive(Clone, Copy, Debug, PartialEq, AnchorSerialize, AnchorDeserialize)]
+pub enum PoolStatus {
+    /// Swaps, deposits and withdrawals are all allowed.
+    Active,
+    /// Deposits and withdrawals are allowed.
+    SwapsPaused,
+    /// Swaps and withdrawals are allowed.
+    DepositsPaused,
+    /// Nothing moves in or out of the pool.
+    Frozen,
+}
+
+/// Liquidity of `owner` in a concentrated pool between `tick_lower` and
+/// `tick_upper`, with the swap fees it has earned.
+#[account]
+#[derive(Debug)]
+pub struct Position {
+    pub bump: u8,
+    pub owner: Pubkey,
+    pub concentrated_pool: Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    pub fee_growth_inside_last_a: u128,
+    pub fee_growth_inside_last_b: u128,
+    pub tokens_owed_a: u64,
+    pub tokens_owed_b: u64,
+}
+
+impl<'info, 'entrypoint> Position {
+    pub fn load(
+        account: &'entrypoint mut Box<Account<'info, Self>>,
+        programs_map: &'entrypoint ProgramsMap<'info>,
+    ) -> Mutable<LoadedPosition<'info, 'entrypoint>> {
+        let bump = account.bump;
+        let owner = account.owner.clone();
+        let concentrated_pool = account.concentrated_pool.clone();
+        let tick_lower = account.tick_lower;
+        let tick_upper = account.tick_upper;
+        let liquidity = account.liquidity;
+        let fee_growth_inside_last_a = account.fee_growth_inside_last_a;
+        let fee_growth_inside_last_b = account.fee_growth_inside_last_b;
+        let tokens_owed_a = account.tokens_owed_a;
+        let tokens_owed_b = account.tokens_owed_b;
+
+        Mutable::new(LoadedPosition {
+            __account__: account,
+            __programs__: programs_map,
+            bump,
+            owner,
+            concentrated_pool,
+            tick_lower,
+            tick_upper,
+            liquidity,
+            fee_growth_inside_last_a,
+            fee_growth_inside_last_b,
+            tokens_owed_a,
+            tokens_owed_b,
+        })
+    }
+
+    pub fn store(loaded: Mutable<LoadedPosition>) {
+        let mut loaded = loaded.borrow_mut();
+
+        let bump = loaded.bump;
+
+        loaded.__account__.bump = bump;
+
+        let owner = loaded.owner.clone();
+
+        loaded.__account__.owner = owner;
+
+        let concentrated_pool = loaded.concentrated_pool.clone();
+
+        loaded.__account__.concentrated_pool = concentrated_pool;
+
+        let tick_lower = loaded.tick_lower;
+
+        loaded.__account__.tick_lower = tick_lower;
+
+        let tick_upper = loaded.tick_upper;
+
+        loaded.__account__.tick_upper = tick_upper;
+
+        let liquidity = loaded.liquidity;
+
+        loaded.__account__.liquidity = liquidity;
+
+        let fee_growth_inside_last_a = loaded.fee_growth_inside_last_a;
+
+        loaded.__account__.fee_growth_inside_last_a = fee_growth_inside_last_a;
+
+        let fee_growth_inside_last_b = loaded.fee_growth_inside_last_b;
+
+        loaded.__account__.fee_growth_inside_last_b = fee_growth_inside_last_b;
+
+        let tokens_owed_a = loaded.tokens_owed_a;
+
+        loaded.__account__.tokens_owed_a = tokens_owed_a;
+
+        let tokens_owed_b = loaded.tokens_owed_b;
+
+        loaded.__account__.tokens_owed_b = tokens_owed_b;
+    }
+}
+
+#[derive(Debug)]
+pub struct LoadedPosition<'info, 'entrypoint> {
+    pub __account__: &'entrypoint mut Box<Account<'info, Position>>,
+    pub __programs__: &'entrypoint ProgramsMap<'info>,
+    pub bump: u8,
+    pub owner: Pubkey,
+    pub concentrated_pool: Pubkey,
+    pub tick_lower: i32,
+    pub tick_upper: i32,
+    pub liquidity: u128,
+    pub fee_growth_inside_last_a: u128,
+    pub fee_growth_inside_last_b: u128,
+    pub tokens_owed_a: u64,
+    pub tokens_owed_b: u64,
+}
+
+/// A tick of a concentrated pool, initialized while its `liquidity_gross` is
+/// more than zero.
+#[derive(Clone, Copy, Debug, PartialEq, AnchorSerialize, AnchorDeserialize)]
+pub struct Tick {
+    pub index: i32,
+    /// Liquidity that comes into range when the price crosses the tick
+    /// upwards, negative when more positions end than start at it.
+    pub liquidity_net: i128,
+    /// Liquidity of every position that starts or ends at the tick.
+    pub liquidity_gross: u128,
+    /// Fee growth on the other side of the tick from the current price.
+    pub fee_growth_outside_a: u128,
+    pub fee_growth_outside_b: u128,
+}
+
+/// `TICK_ARRAY_SIZE` consecutive ticks on the tick spacing of a concentrated
+/// pool, from `start_tick_index`. Anyone can create the tick array of a
+/// range, and positions and swaps pass the tick arrays of the ticks they use.
+#[account]
+#[derive(Debug)]
+pub struct TickArray {
+    pub bump: u8,
+    pub concentrated_pool: Pubkey,
+    pub start_tick_index: i32,
+    pub ticks: Vec<Tick>,
+}
+
+impl<'info, 'entrypoint> TickArray {
+    pub fn load(
+        account: &'entrypoint mut Box<Account<'info, Self>>,
+        programs_map: &'entrypoint ProgramsMap<'info>,
+    ) -> Mutable<LoadedTickArray<'info, 'entrypoint>> {
+        let bump = account.bump;
+        let concentrated_pool = account.concentrated_pool.clone();
+        let start_tick_index = account.start_tick_index;
+        let ticks = account.ticks.clone();
+
+        Mutable::new(LoadedTickArray {
+            __account__: account,
+            __programs__: programs_map,
+            bump,
+            concentrated_pool,
+            start_tick_index,
+            ticks,
+        })
+    }
+
+    pub fn store(loaded: Mutable<LoadedTickArray>) {
+        let mut loaded = loaded.borrow_mut();
+        let bump = loaded.bump;
+
+        loaded.__account__.bump = bump;
+
+        let concentrated_pool = loaded.concentrated_pool.clone();
+
+        loaded.__account__.concentrated_pool = concentrated_pool;
+
+        let start_tick_index = loaded.start_tick_index;
+
+        loaded.__account__.start_tick_index = start_tick_index;
+
+        let ticks = loaded.ticks.clone();
+
+        loaded.__account__.ticks = ticks;
+    }
+}
+
+#[derive(Debug)]
+pub struct LoadedTickArray<'info, 'entrypoint> {
+    pub __account__: &'entrypoint mut Box<Account<'info, TickArray>>,
+    pub __programs__: &'entrypoint ProgramsMap<'info>,
+    pub bump: u8,
+    pub concentrated_pool: Pubkey,
+    pub start_tick_index: i32,
+    pub ticks: Vec<Tick>,
+}
+
+pub fn accept_authority_handler<'info>(
+    mut pending_authority: SeahorseSigner<'info, '_>,
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+) -> Result<()> {
+    assign!(pool.borrow_mut().authority, pending_authority.key());
+
+    assign!(pool.borrow_mut().pending_authority, Pubkey::default());
+
+    Ok(())
+}
+
+/// Books the protocol's share of the swap fee on `token_in_amount` against
+/// the input token and returns it. The fee stays in the vault until it is
+/// collected, but is no longer part of the pool's reserves.
+pub fn accrue_protocol_fee<'info>(
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut is_token_a: bool,
+    mut token_in_amount: u64,
+) -> Result<u64> {
+    let mut protocol_fee = crate::math::get_protocol_fee(
+        token_in_amount,
+        pool.borrow().fee_numerator,
+        pool.borrow().fee_denominator,
+        pool.borrow().protocol_fee_numerator,
+        pool.borrow().protocol_fee_denominator,
+    )?;
+
+    if is_token_a {
+        let mut protocol_fees_a = pool
+            .borrow()
+            .protocol_fees_a
+            .checked_add(protocol_fee)
+            .ok_or(error!(SeaAmmError::MathOverflow))?;
+
+        assign!(pool.borrow_mut().protocol_fees_a, protocol_fees_a);
+    } else {
+        let mut protocol_fees_b = pool
+            .borrow()
+            .protocol_fees_b
+            .checked_add(protocol_fee)
+            .ok_or(error!(SeaAmmError::MathOverflow))?;
+
+        assign!(pool.borrow_mut().protocol_fees_b, protocol_fees_b);
+    }
+
+    Ok(protocol_fee)
+}
+
+pub fn add_liquidity_handler<'info>(
+    mut user: SeahorseSigner<'info, '_>,
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut token_mint_a: SeahorseAccount<'info, '_, Mint>,
+    mut token_mint_b: SeahorseAccount<'info, '_, Mint>,
+    mut user_token_account_a: SeahorseAccount<'info, '_, TokenAccount>,
+    mut user_token_account_b: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_token_vault_a: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_token_vault_b: SeahorseAccount<'info, '_, TokenAccount>,
+    mut lp_token_mint: SeahorseAccount<'info, '_, Mint>,
+    mut locked_lp_token_account: SeahorseAccount<'info, '_, TokenAccount>,
+    mut user_lp_token_account: SeahorseAccount<'info, '_, TokenAccount>,
+    mut max_amount_a: u64,
+    mut max_amount_b: u64,
+    mut min_lp_out: u64,
+) -> Result<()> {
+    check_can_deposit(pool.borrow().status)?;
+
+    let mut pool_pda = Pubkey::create_program_address(
+        Mutable::new(vec![
+            "pool".to_string().as_bytes().as_ref(),
+            token_mint_a.key().as_ref(),
+            token_mint_b.key().as_ref(),
+            pool.borrow().fee_numerator.to_le_bytes().as_ref(),
+            pool.borrow().fee_denominator.to_le_bytes().as_ref(),
+            [pool.borrow().bump].as_ref(),
+        ])
+        .borrow()
+        .as_slice(),
+        &id(),
+    )
+    .unwrap_or_default();
+
+    if !(pool_pda == pool.borrow().__account__.key()) {
+        return err!(SeaAmmError::InvalidPool);
+    }
+
+    let mut lp_token_mint_pda = Pubkey::create_program_address(
+        Mutable::new(vec![
+            "lp-token-mint".to_string().as_bytes().as_ref(),
+            pool.borrow().__account__.key().as_ref(),
+            [pool.borrow().lp_token_mint_bump].as_ref(),
+        ])
+        .borrow()
+        .as_slice(),
+        &id(),
+    )
+    .unwrap_or_default();
+
+    if !(lp_token_mint_pda == lp_token_mint.key()) {
+        return err!(SeaAmmError::InvalidLpMint);
+    }
+
+    if !((max_amount_a > 0) && (max_amount_b > 0)) {
+        return err!(SeaAmmError::ZeroAmount);
+    }
+
+    // snapshot the pool before any transfer. Deposit amounts and lp shares are
+    // all priced against these pre-deposit reserves, and nothing below reads
+    // the vaults again after the transfers.
+    let (mut reserve_a, mut reserve_b) = get_reserves(
+        pool.clone(),
+        pool_token_vault_a.clone(),
+        pool_token_vault_b.clone(),
+    )?;
+    let mut total_lp_tokens = lp_token_mint.supply;
+    let mut token_amount_a = max_amount_a;
+    let mut token_amount_b = max_amount_b;
+    let mut locked_lp_token_amount = 0;
+    let mut lp_token_mint_amount = 0;
+
+    if total_lp_tokens == 0 {
+        let mut initial_lp_token_amount =
+            get_initial_lp_amount(pool.clone(), token_amount_a, token_amount_b)?;
+
+        if !(initial_lp_token_amount > crate::math::MINIMUM_LIQUIDITY) {
+            return err!(SeaAmmError::InsufficientInitialLiquidity);
+        }
+
+        locked_lp_token_amount = crate::math::MINIMUM_LIQUIDITY;
+        lp_token_mint_amount = initial_lp_token_amount - crate::math::MINIMUM_LIQUIDITY;
+    } else {
+        // only take as much of each token as the current pool ratio needs
+        let mut deposit_amounts =
+            crate::math::get_deposit_amounts(max_amount_a, max_amount_b, reserve_a, reserve_b)?;
+
+        token_amount_a = deposit_amounts.0;
+        token_amount_b = deposit_amounts.1;
+        lp_token_mint_amount = crate::math::get_lp_amount(
+            token_amount_a,
+            token_amount_b,
+            reserve_a,
+            reserve_b,
+            total_lp_tokens,
+        )?;
+    }
+
+    if !(lp_token_mint_amount > 0) {
+        return err!(SeaAmmError::NoLpToMint);
+    }
+
+    if !(lp_token_mint_amount >= min_lp_out) {
+        return err!(SeaAmmError::SlippageExceeded);
+    }
+
+    token::transfer(
+        CpiContext::new(
+            user_token_account_a.programs.get("token_program"),
+            token::Transfer {
+                from: user_token_account_a.to_account_info(),
+                authority: user.clone().to_account_info(),
+                to: pool_token_vault_a.clone().to_account_info(),
+            },
+        ),
+        token_amount_a.clone(),
+    )?;
+
+    token::transfer(
+        CpiContext::new(
+            user_token_account_b.programs.get("token_program"),
+            token::Transfer {
+                from: user_token_account_b.to_account_info(),
+                authority: user.clone().to_account_info(),
+                to: pool_token_vault_b.clone().to_account_info(),
+            },
+        ),
+        token_amount_b.clone(),
+    )?;
+
+    if locked_lp_token_amount > 0 {
+        // lock the minimum liquidity in a pool owned account, so the lp token
+        // supply can never be brought back down to a few base units
+        token::mint_to(
+            CpiContext::new_with_signer(
+                lp_token_mint.programs.get("token_program"),
+                token::MintTo {
+                    mint: lp_token_mint.to_account_info(),
+                    authority: pool.borrow().__account__.to_account_info(),
+                    to: locked_lp_token_account.clone().to_account_info(),
+                },
+                &[Mutable::new(vec![
+                    "pool".to_string().as_bytes().as_ref(),
+                    token_mint_a.key().as_ref(),
+                    token_mint_b.key().as_ref(),
+                    pool.borrow().fee_numerator.to_le_bytes().as_ref(),
+                    pool.borrow().fee_denominator.to_le_bytes().as_ref(),
+                    [pool.borrow().bump].as_ref(),
+                ])
+                .borrow()
+                .as_slice()],
+            ),
+            locked_lp_token_amount.clone(),
+        )?;
+    }
+
+    token::mint_to(
+        CpiContext::new_with_signer(
+            lp_token_mint.programs.get("token_program"),
+            token::MintTo {
+                mint: lp_token_mint.to_account_info(),
+                authority: pool.borrow().__account__.to_account_info(),
+                to: user_lp_token_account.clone().to_account_info(),
+            },
+            &[Mutable::new(vec![
+                "pool".to_string().as_bytes().as_ref(),
+                token_mint_a.key().as_ref(),
+                token_mint_b.key().as_ref(),
+                pool.borrow().fee_numerator.to_le_bytes().as_ref(),
+                pool.borrow().fee_denominator.to_le_bytes().as_ref(),
+                [pool.borrow().bump].as_ref(),
+            ])
+            .borrow()
+            .as_slice()],
+        ),
+        lp_token_mint_amount.clone(),
+    )?;
+
+    Ok(())
+}
+
+pub fn add_liquidity_multi_handler<'info>(
+    mut user: SeahorseSigner<'info, '_>,
+    mut multi_pool: Mutable<LoadedMultiPool<'info, '_>>,
+    mut multi_pool_authority: &UncheckedAccount<'info>,
+    mut lp_token_mint: SeahorseAccount<'info, '_, Mint>,
+    mut locked_lp_token_account: SeahorseAccount<'info, '_, TokenAccount>,
+    mut user_lp_token_account: SeahorseAccount<'info, '_, TokenAccount>,
+    mut user_token_accounts: Vec<SeahorseAccount<'info, '_, TokenAccount>>,
+    mut pool_token_vaults: Vec<SeahorseAccount<'info, '_, TokenAccount>>,
+    mut max_amounts: Vec<u64>,
+    mut min_lp_out: u64,
+) -> Result<()> {
+    check_can_deposit(multi_pool.borrow().status)?;
+
+    check_multi_pool_accounts(
+        multi_pool.clone(),
+        user.clone(),
+        user_token_accounts.clone(),
+        pool_token_vaults.clone(),
+    )?;
+
+    if !(max_amounts.len() == pool_token_vaults.len()) {
+        return err!(SeaAmmError::InvalidTokenCount);
+    }
+
+    if !max_amounts.iter().all(|max_amount| *max_amount > 0) {
+        return err!(SeaAmmError::ZeroAmount);
+    }
+
+    // priced against the pre-deposit balances, as in add_liquidity
+    let mut reserves = pool_token_vaults
+        .iter()
+        .map(|pool_token_vault| pool_token_vault.amount)
+        .collect::<Vec<u64>>();
+    let mut total_lp_tokens = lp_token_mint.supply;
+    let mut token_amounts = max_amounts.clone();
+    let mut locked_lp_token_amount = 0;
+    let mut lp_token_mint_amount = 0;
+
+    if total_lp_tokens == 0 {
+        let mut initial_lp_token_amount = crate::weighted::get_geometric_mean(&token_amounts)?;
+
+        if !(initial_lp_token_amount > crate::math::MINIMUM_LIQUIDITY) {
+            return err!(SeaAmmError::InsufficientInitialLiquidity);
+        }
+
+        locked_lp_token_amount = crate::math::MINIMUM_LIQUIDITY;
+        lp_token_mint_amount = initial_lp_token_amount - crate::math::MINIMUM_LIQUIDITY;
+    } else {
+        (token_amounts, lp_token_mint_amount) =
+            crate::math::get_multi_deposit_amounts(&max_amounts, &reserves, total_lp_tokens)?;
+    }
+
+    if !(lp_token_mint_amount > 0) {
+        return err!(SeaAmmError::NoLpToMint);
+    }
+
+    if !(lp_token_mint_amount >= min_lp_out) {
+        return err!(SeaAmmError::SlippageExceeded);
+    }
+
+    for (user_token_account, (pool_token_vault, token_amount)) in user_token_accounts
+        .iter()
+        .zip(pool_token_vaults.iter().zip(token_amounts))
+    {
+        token::transfer(
+            CpiContext::new(
+                user_token_account.programs.get("token_program"),
+                token::Transfer {
+                    from: user_token_account.to_account_info(),
+                    authority: user.clone().to_account_info(),
+                    to: pool_token_vault.to_account_info(),
+                },
+            ),
+            token_amount,
+        )?;
+    }
+
+    if locked_lp_token_amount > 0 {
+        token::mint_to(
+            CpiContext::new_with_signer(
+                lp_token_mint.programs.get("token_program"),
+                token::MintTo {
+                    mint: lp_token_mint.to_account_info(),
+                    authority: multi_pool_authority.to_account_info(),
+                    to: locked_lp_token_account.clone().to_account_info(),
+                },
+                &[Mutable::new(vec![
+                    "multi-pool-authority".to_string().as_bytes().as_ref(),
+                    multi_pool.borrow().__account__.key().as_ref(),
+                    [multi_pool.borrow().authority_bump].as_ref(),
+                ])
+                .borrow()
+                .as_slice()],
+            ),
+            locked_lp_token_amount.clone(),
+        )?;
+    }
+
+    token::mint_to(
+        CpiContext::new_with_signer(
+            lp_token_mint.programs.get("token_program"),
+            token::MintTo {
+                mint: lp_token_mint.to_account_info(),
+                authority: multi_pool_authority.to_account_info(),
+                to: user_lp_token_account.clone().to_account_info(),
+            },
+            &[Mutable::new(vec![
+                "multi-pool-authority".to_string().as_bytes().as_ref(),
+                multi_pool.borrow().__account__.key().as_ref(),
+                [multi_pool.borrow().authority_bump].as_ref(),
+            ])
+            .borrow()
+            .as_slice()],
+        ),
+        lp_token_mint_amount.clone(),
+    )?;
+
+    Ok(())
+}
+
+pub fn add_liquidity_single_handler<'info>(
+    mut user: SeahorseSigner<'info, '_>,
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut user_source: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_source_vault: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_destination_vault: SeahorseAccount<'info, '_, TokenAccount>,
+    mut lp_token_mint: SeahorseAccount<'info, '_, Mint>,
+    mut user_lp_token_account: SeahorseAccount<'info, '_, TokenAccount>,
+    mut token_in_amount: u64,
+    mut minimum_lp_out: u64,
+) -> Result<()> {
+    // a single sided deposit swaps part of the input
+    check_can_deposit(pool.borrow().status)?;
+    check_can_swap(pool.borrow().status)?;
+
+    let mut is_token_a = check_swap_accounts(
+        pool.clone(),
+        pool_source_vault.clone(),
+        pool_destination_vault.clone(),
+    )?;
+
+    if !(token_in_amount > 0) {
+        return err!(SeaAmmError::ZeroAmount);
+    }
+
+    let (mut reserve_in, mut reserve_out) = get_swap_reserves(
+        pool.clone(),
+        pool_source_vault.clone(),
+        pool_destination_vault.clone(),
+        is_token_a,
+    )?;
+    let mut total_lp_tokens = lp_token_mint.supply;
+
+    // the first deposit sets the pool price, so it has to bring both tokens
+    if !(total_lp_tokens > 0) {
+        return err!(SeaAmmError::InsufficientLiquidity);
+    }
+
+    // the swapped part pays the protocol fee like any other swap, and what
+    // the protocol takes is not deposited into the reserves
+    let mut swap_amount = get_single_sided_swap_amount(
+        pool.clone(),
+        is_token_a,
+        token_in_amount,
+        reserve_in,
+        reserve_out,
+    )?;
+    let mut protocol_fee = accrue_protocol_fee(pool.clone(), is_token_a, swap_amount)?;
+    let mut lp_token_mint_amount = get_single_sided_lp_amount(
+        pool.clone(),
+        is_token_a,
+        token_in_amount - protocol_fee,
+        reserve_in,
+        reserve_out,
+        total_lp_tokens,
+    )?;
+
+    if !(lp_token_mint_amount > 0) {
+        return err!(SeaAmmError::NoLpToMint);
+    }
+
+    if !(lp_token_mint_amount >= minimum_lp_out) {
+        return err!(SeaAmmError::SlippageExceeded);
+    }
+
+    let mut token_mint_a = pool.borrow().token_mint_a;
+    let mut token_mint_b = pool.borrow().token_mint_b;
+
+    // the swapped part never leaves the pool, so the whole deposit goes into
+    // the source vault
+    token::transfer(
+        CpiContext::new(
+            user_source.programs.get("token_program"),
+            token::Transfer {
+                from: user_source.to_account_info(),
+                authority: user.clone().to_account_info(),
+                to: pool_source_vault.clone().to_account_info(),
+            },
+        ),
+        token_in_amount.clone(),
+    )?;
+
+    token::mint_to(
+        CpiContext::new_with_signer(
+            lp_token_mint.programs.get("token_program"),
+            token::MintTo {
+                mint: lp_token_mint.to_account_info(),
+                authority: pool.borrow().__account__.to_account_info(),
+                to: user_lp_token_account.clone().to_account_info(),
+            },
+            &[Mutable::new(vec![
+                "pool".to_string().as_bytes().as_ref(),
+                token_mint_a.as_ref(),
+                token_mint_b.as_ref(),
+                pool.borrow().fee_numerator.to_le_bytes().as_ref(),
+                pool.borrow().fee_denominator.to_le_bytes().as_ref(),
+                [pool.borrow().bump].as_ref(),
+            ])
+            .borrow()
+            .as_slice()],
+        ),
+        lp_token_mint_amount.clone(),
+    )?;
+
+    Ok(())
+}
+
+/// Pays the config's pool creation fee from `authority` to the config admin.
+pub fn charge_pool_creation_fee<'info>(
+    mut authority: SeahorseSigner<'info, '_>,
+    mut config: Mutable<LoadedConfig<'info, '_>>,
+    mut admin: &UncheckedAccount<'info>,
+) -> Result<()> {
+    let mut pool_creation_fee = config.borrow().pool_creation_fee;
+
+    if pool_creation_fee > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                authority.programs.get("system_program"),
+                system_program::Transfer {
+                    from: authority.to_account_info(),
+                    to: admin.to_account_info(),
+                },
+            ),
+            pool_creation_fee,
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn check_can_deposit(mut status: PoolStatus) -> Result<()> {
+    match status {
+        PoolStatus::Active | PoolStatus::SwapsPaused => Ok(()),
+        PoolStatus::DepositsPaused => err!(SeaAmmError::DepositsPaused),
+        PoolStatus::Frozen => err!(SeaAmmError::PoolFrozen),
+    }
+}
+
+pub fn check_can_swap(mut status: PoolStatus) -> Result<()> {
+    match status {
+        PoolStatus::Active | PoolStatus::DepositsPaused => Ok(()),
+        PoolStatus::SwapsPaused => err!(SeaAmmError::SwapsPaused),
+        PoolStatus::Frozen => err!(SeaAmmError::PoolFrozen),
+    }
+}
+
+pub fn check_can_withdraw(mut status: PoolStatus) -> Result<()> {
+    match status {
+        PoolStatus::Frozen => err!(SeaAmmError::PoolFrozen),
+        _ => Ok(()),
+    }
+}
+
+/// Returns whether a swap from `pool_source_vault` to `pool_destination_vault`
+/// sells token a of the concentrated pool.
+pub fn check_concentrated_swap_accounts<'info>(
+    mut concentrated_pool: Mutable<LoadedConcentratedPool<'info, '_>>,
+    mut pool_source_vault: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_destination_vault: SeahorseAccount<'info, '_, TokenAccount>,
+) -> Result<bool> {
+    let mut token_vault_a = concentrated_pool.borrow().token_vault_a;
+    let mut token_vault_b = concentrated_pool.borrow().token_vault_b;
+
+    if (pool_source_vault.key() == token_vault_a) && (pool_destination_vault.key() == token_vault_b)
+    {
+        return Ok(true);
+    }
+
+    if (pool_source_vault.key() == token_vault_b) && (pool_destination_vault.key() == token_vault_a)
+    {
+        return Ok(false);
+    }
+
+    return err!(SeaAmmError::InvalidVault);
+}
+
+pub fn check_config(
+    mut fee_tiers: Vec<FeeTier>,
+    mut protocol_fee_numerator: u64,
+    mut protocol_fee_denominator: u64,
+) -> Result<()> {
+    if !(fee_tiers.len() <= MAX_FEE_TIERS) {
+        return err!(SeaAmmError::TooManyFeeTiers);
+    }
+
+    for fee_tier in fee_tiers.iter() {
+        if !((fee_tier.fee_denominator > 0) && (fee_tier.fee_numerator < fee_tier.fee_denominator))
+        {
+            return err!(SeaAmmError::InvalidFee);
+        }
+    }
+
+    if !((protocol_fee_denominator > 0) && (protocol_fee_numerator <= protocol_fee_denominator)) {
+        return err!(SeaAmmError::InvalidProtocolFee);
+    }
+
+    Ok(())
+}
+
+pub fn check_fee_tier<'info>(
+    mut config: Mutable<LoadedConfig<'info, '_>>,
+    mut fee_numerator: u64,
+    mut fee_denominator: u64,
+) -> Result<()> {
+    let mut fee_tier = FeeTier {
+        fee_numerator,
+        fee_denominator,
+    };
+
+    if !config.borrow().fee_tiers.contains(&fee_tier) {
+        return err!(SeaAmmError::InvalidFeeTier);
+    }
+
+    Ok(())
+}
+
+/// Checks that `pool_token_vaults` are the multi pool's vaults in order, and
+/// that `user_token_accounts` are the user's accounts of the matching mints.
+pub fn check_multi_pool_accounts<'info>(
+    mut multi_pool: Mutable<LoadedMultiPool<'info, '_>>,
+    mut user: SeahorseSigner<'info, '_>,
+    mut user_token_accounts: Vec<SeahorseAccount<'info, '_, TokenAccount>>,
+    mut pool_token_vaults: Vec<SeahorseAccount<'info, '_, TokenAccount>>,
+) -> Result<()> {
+    let mut token_count = multi_pool.borrow().token_mints.len();
+
+    if !((user_token_accounts.len() == token_count) && (pool_token_vaults.len() == token_count)) {
+        return err!(SeaAmmError::InvalidTokenCount);
+    }
+
+    for (i, (user_token_account, pool_token_vault)) in user_token_accounts
+        .iter()
+        .zip(pool_token_vaults.iter())
+        .enumerate()
+    {
+        if !(pool_token_vault.key() == multi_pool.borrow().token_vaults[i]) {
+            return err!(SeaAmmError::InvalidVault);
+        }
+
+        if !((user_token_account.mint == multi_pool.borrow().token_mints[i])
+            && (user_token_account.owner == user.key()))
+        {
+            return err!(SeaAmmError::InvalidTokenAccount);
+        }
+    }
+
+    Ok(())
+}
+
+pub fn check_swap_accounts<'info>(
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut pool_source_vault: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_destination_vault: SeahorseAccount<'info, '_, TokenAccount>,
+) -> Result<bool> {
+    let mut token_vault_a = pool.borrow().token_vault_a;
+    let mut token_vault_b = pool.borrow().token_vault_b;
+
+    if (pool_source_vault.key() == token_vault_a) && (pool_destination_vault.key() == token_vault_b)
+    {
+        return Ok(true);
+    }
+
+    if (pool_source_vault.key() == token_vault_b) && (pool_destination_vault.key() == token_vault_a)
+    {
+        return Ok(false);
+    }
+
+    return err!(SeaAmmError::InvalidVault);
+}
+
+/// Checks that a position is empty before it is closed and its rent returned
+/// to the owner.
+pub fn close_position_handler<'info>(
+    mut owner: SeahorseSigner<'info, '_>,
+    mut position: Mutable<LoadedPosition<'info, '_>>,
+) -> Result<()> {
+    if !((position.borrow().liquidity == 0)
+        && (position.borrow().tokens_owed_a == 0)
+        && (position.borrow().tokens_owed_b == 0))
+    {
+        return err!(SeaAmmError::PositionNotEmpty);
+    }
+
+    Ok(())
+}
+
+/// Pays the owner of a position the swap fees it has earned.
+pub fn collect_fees_handler<'info>(
+    mut owner: SeahorseSigner<'info, '_>,
+    mut concentrated_pool: Mutable<LoadedConcentratedPool<'info, '_>>,
+    mut position: Mutable<LoadedPosition<'info, '_>>,
+    mut tick_array_lower: Mutable<LoadedTickArray<'info, '_>>,
+    mut tick_array_upper: Mutable<LoadedTickArray<'info, '_>>,
+    mut user_token_account_a: SeahorseAccount<'info, '_, TokenAccount>,
+    mut user_token_account_b: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_token_vault_a: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_token_vault_b: SeahorseAccount<'info, '_, TokenAccount>,
+) -> Result<()> {
+    check_can_withdraw(concentrated_pool.borrow().status)?;
+
+    // brings the fees owed up to date without changing the liquidity
+    update_position(
+        concentrated_pool.clone(),
+        position.clone(),
+        tick_array_lower.clone(),
+        tick_array_upper.clone(),
+        0,
+    )?;
+
+    let mut tokens_owed_a = position.borrow().tokens_owed_a;
+    let mut tokens_owed_b = position.borrow().tokens_owed_b;
+
+    assign!(position.borrow_mut().tokens_owed_a, 0);
+
+    assign!(position.borrow_mut().tokens_owed_b, 0);
+
+    let mut token_mint_a = concentrated_pool.borrow().token_mint_a;
+    let mut token_mint_b = concentrated_pool.borrow().token_mint_b;
+
+    if tokens_owed_a > 0 {
+        token::transfer(
+            CpiContext::new_with_signer(
+                pool_token_vault_a.programs.get("token_program"),
+                token::Transfer {
+                    from: pool_token_vault_a.to_account_info(),
+                    authority: concentrated_pool.borrow().__account__.to_account_info(),
+                    to: user_token_account_a.clone().to_account_info(),
+                },
+                &[Mutable::new(vec![
+                    "concentrated-pool".to_string().as_bytes().as_ref(),
+                    token_mint_a.as_ref(),
+                    token_mint_b.as_ref(),
+                    concentrated_pool
+                        .borrow()
+                        .fee_numerator
+                        .to_le_bytes()
+                        .as_ref(),
+                    concentrated_pool
+                        .borrow()
+                        .fee_denominator
+                        .to_le_bytes()
+                        .as_ref(),
+                    [concentrated_pool.borrow().bump].as_ref(),
+                ])
+                .borrow()
+                .as_slice()],
+            ),
+            tokens_owed_a.clone(),
+        )?;
+    }
+
+    if tokens_owed_b > 0 {
+        token::transfer(
+            CpiContext::new_with_signer(
+                pool_token_vault_b.programs.get("token_program"),
+                token::Transfer {
+                    from: pool_token_vault_b.to_account_info(),
+                    authority: concentrated_pool.borrow().__account__.to_account_info(),
+                    to: user_token_account_b.clone().to_account_info(),
+                },
+                &[Mutable::new(vec![
+                    "concentrated-pool".to_string().as_bytes().as_ref(),
+                    token_mint_a.as_ref(),
+                    token_mint_b.as_ref(),
+                    concentrated_pool
+                        .borrow()
+                        .fee_numerator
+                        .to_le_bytes()
+                        .as_ref(),
+                    concentrated_pool
+                        .borrow()
+                        .fee_denominator
+                        .to_le_bytes()
+                        .as_ref(),
+                    [concentrated_pool.borrow().bump].as_ref(),
+                ])
+                .borrow()
+                .as_slice()],
+            ),
+            tokens_owed_b.clone(),
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn collect_protocol_fees_handler<'info>(
+    mut authority: SeahorseSigner<'info, '_>,
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut pool_token_vault_a: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_token_vault_b: SeahorseAccount<'info, '_, TokenAccount>,
+    mut authority_token_account_a: SeahorseAccount<'info, '_, TokenAccount>,
+    mut authority_token_account_b: SeahorseAccount<'info, '_, TokenAccount>,
+) -> Result<()> {
+    check_can_withdraw(pool.borrow().status)?;
+
+    let mut protocol_fees_a = pool.borrow().protocol_fees_a;
+    let mut protocol_fees_b = pool.borrow().protocol_fees_b;
+    let mut token_mint_a = pool.borrow().token_mint_a;
+    let mut token_mint_b = pool.borrow().token_mint_b;
+
+    assign!(pool.borrow_mut().protocol_fees_a, 0);
+
+    assign!(pool.borrow_mut().protocol_fees_b, 0);
+
+    if protocol_fees_a > 0 {
+        token::transfer(
+            CpiContext::new_with_signer(
+                pool_token_vault_a.programs.get("token_program"),
+                token::Transfer {
+                    from: pool_token_vault_a.to_account_info(),
+                    authority: pool.borrow().__account__.to_account_info(),
+                    to: authority_token_account_a.clone().to_account_info(),
+                },
+                &[Mutable::new(vec![
+                    "pool".to_string().as_bytes().as_ref(),
+                    token_mint_a.as_ref(),
+                    token_mint_b.as_ref(),
+                    pool.borrow().fee_numerator.to_le_bytes().as_ref(),
+                    pool.borrow().fee_denominator.to_le_bytes().as_ref(),
+                    [pool.borrow().bump].as_ref(),
+                ])
+                .borrow()
+                .as_slice()],
+            ),
+            protocol_fees_a.clone(),
+        )?;
+    }
+
+    if protocol_fees_b > 0 {
+        token::transfer(
+            CpiContext::new_with_signer(
+                pool_token_vault_b.programs.get("token_program"),
+                token::Transfer {
+                    from: pool_token_vault_b.to_account_info(),
+                    authority: pool.borrow().__account__.to_account_info(),
+                    to: authority_token_account_b.clone().to_account_info(),
+                },
+                &[Mutable::new(vec![
+                    "pool".to_string().as_bytes().as_ref(),
+                    token_mint_a.as_ref(),
+                    token_mint_b.as_ref(),
+                    pool.borrow().fee_numerator.to_le_bytes().as_ref(),
+                    pool.borrow().fee_denominator.to_le_bytes().as_ref(),
+                    [pool.borrow().bump].as_ref(),
+                ])
+                .borrow()
+                .as_slice()],
+            ),
+            protocol_fees_b.clone(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Creates a concentrated pool of a pair of tokens at the price whose square
+/// root is `initial_sqrt_price`, in Q64.64. Positions start and end on
+/// multiples of `tick_spacing`.
+pub fn create_concentrated_pool_handler<'info>(
+    mut authority: SeahorseSigner<'info, '_>,
+    mut config: Mutable<LoadedConfig<'info, '_>>,
+    mut admin: &UncheckedAccount<'info>,
+    mut concentrated_pool: Empty<Mutable<LoadedConcentratedPool<'info, '_>>>,
+    mut token_mint_a: SeahorseAccount<'info, '_, Mint>,
+    mut token_mint_b: SeahorseAccount<'info, '_, Mint>,
+    mut token_vault_a: Empty<SeahorseAccount<'info, '_, TokenAccount>>,
+    mut token_vault_b: Empty<SeahorseAccount<'info, '_, TokenAccount>>,
+    mut fee_numerator: u64,
+    mut fee_denominator: u64,
+    mut tick_spacing: u16,
+    mut initial_sqrt_price: u128,
+) -> Result<()> {
+    check_fee_tier(config.clone(), fee_numerator, fee_denominator)?;
+
+    // one pool per pair and fee tier, see `crate::pda::sort_mints`
+    if !(token_mint_a.key() < token_mint_b.key()) {
+        return err!(SeaAmmError::UnsortedMints);
+    }
+
+    if !(tick_spacing > 0) {
+        return err!(SeaAmmError::InvalidTickSpacing);
+    }
+
+    let mut tick_current = crate::concentrated::tick_at_sqrt_price(initial_sqrt_price)?;
+
+    charge_pool_creation_fee(authority.clone(), config.clone(), admin)?;
+
+    let mut bump = concentrated_pool.bump.unwrap();
+    let mut token_vault_a_bump = token_vault_a.bump.unwrap();
+    let mut token_vault_b_bump = token_vault_b.bump.unwrap();
+    let mut concentrated_pool = concentrated_pool.account.clone();
+    let mut token_vault_a = token_vault_a.account.clone();
+    let mut token_vault_b = token_vault_b.account.clone();
+
+    assign!(concentrated_pool.borrow_mut().bump, bump);
+
+    assign!(concentrated_pool.borrow_mut().authority, authority.key());
+
+    assign!(
+        concentrated_pool.borrow_mut().token_mint_a,
+        token_mint_a.key()
+    );
+
+    assign!(
+        concentrated_pool.borrow_mut().token_mint_b,
+        token_mint_b.key()
+    );
+
+    assign!(
+        concentrated_pool.borrow_mut().token_vault_a,
+        token_vault_a.key()
+    );
+
+    assign!(
+        concentrated_pool.borrow_mut().token_vault_b,
+        token_vault_b.key()
+    );
+
+    assign!(
+        concentrated_pool.borrow_mut().token_vault_a_bump,
+        token_vault_a_bump
+    );
+
+    assign!(
+        concentrated_pool.borrow_mut().token_vault_b_bump,
+        token_vault_b_bump
+    );
+
+    assign!(concentrated_pool.borrow_mut().fee_numerator, fee_numerator);
+
+    assign!(
+        concentrated_pool.borrow_mut().fee_denominator,
+        fee_denominator
+    );
+
+    assign!(concentrated_pool.borrow_mut().status, PoolStatus::Active);
+
+    assign!(concentrated_pool.borrow_mut().tick_spacing, tick_spacing);
+
+    assign!(
+        concentrated_pool.borrow_mut().sqrt_price,
+        initial_sqrt_price
+    );
+
+    assign!(concentrated_pool.borrow_mut().tick_current, tick_current);
+
+    assign!(concentrated_pool.borrow_mut().liquidity, 0);
+
+    assign!(concentrated_pool.borrow_mut().fee_growth_global_a, 0);
+
+    assign!(concentrated_pool.borrow_mut().fee_growth_global_b, 0);
+
+    Ok(())
+}
+
+/// Creates a pool of `token_mints`, which must be in ascending order, with
+/// the empty `token_vaults` the caller made for them. The vaults must belong
+/// to `multi_pool_authority`, which nobody else can sign for.
+pub fn create_multi_pool_handler<'info>(
+    mut authority: SeahorseSigner<'info, '_>,
+    mut config: Mutable<LoadedConfig<'info, '_>>,
+    mut admin: &UncheckedAccount<'info>,
+    mut multi_pool: Empty<Mutable<LoadedMultiPool<'info, '_>>>,
+    mut multi_pool_authority: &UncheckedAccount<'info>,
+    mut multi_pool_authority_bump: u8,
+    mut lp_token_mint: Empty<SeahorseAccount<'info, '_, Mint>>,
+    mut locked_lp_token_account: Empty<SeahorseAccount<'info, '_, TokenAccount>>,
+    mut token_mints: Vec<SeahorseAccount<'info, '_, Mint>>,
+    mut token_vaults: Vec<SeahorseAccount<'info, '_, TokenAccount>>,
+    mut fee_numerator: u64,
+    mut fee_denominator: u64,
+) -> Result<()> {
+    check_fee_tier(config.clone(), fee_numerator, fee_denominator)?;
+
+    if !((MIN_MULTI_POOL_TOKENS..=MAX_MULTI_POOL_TOKENS).contains(&token_mints.len())
+        && (token_vaults.len() == token_mints.len()))
+    {
+        return err!(SeaAmmError::InvalidTokenCount);
+    }
+
+    // ascending order also rules out a token being in the pool twice
+    if !token_mints
+        .windows(2)
+        .all(|pair| pair[0].key() < pair[1].key())
+    {
+        return err!(SeaAmmError::UnsortedMints);
+    }
+
+    // an empty vault that only the pool can move tokens out of
+    for (token_mint, token_vault) in token_mints.iter().zip(token_vaults.iter()) {
+        if !((token_vault.mint == token_mint.key())
+            && (token_vault.owner == multi_pool_authority.key())
+            && token_vault.delegate.is_none()
+            && token_vault.close_authority.is_none()
+            && (token_vault.amount == 0))
+        {
+            return err!(SeaAmmError::InvalidVault);
+        }
+    }
+
+    charge_pool_creation_fee(authority.clone(), config.clone(), admin)?;
+
+    let mut lp_token_mint_bump = lp_token_mint.bump.unwrap();
+    let mut locked_lp_token_account_bump = locked_lp_token_account.bump.unwrap();
+    let mut multi_pool = multi_pool.account.clone();
+    let mut lp_token_mint = lp_token_mint.account.clone();
+
+    assign!(
+        multi_pool.borrow_mut().authority_bump,
+        multi_pool_authority_bump
+    );
+
+    assign!(multi_pool.borrow_mut().authority, authority.key());
+
+    assign!(multi_pool.borrow_mut().lp_token_mint, lp_token_mint.key());
+
+    assign!(
+        multi_pool.borrow_mut().lp_token_mint_bump,
+        lp_token_mint_bump
+    );
+
+    assign!(
+        multi_pool.borrow_mut().locked_lp_token_account_bump,
+        locked_lp_token_account_bump
+    );
+
+    assign!(multi_pool.borrow_mut().fee_numerator, fee_numerator);
+
+    assign!(multi_pool.borrow_mut().fee_denominator, fee_denominator);
+
+    assign!(multi_pool.borrow_mut().status, PoolStatus::Active);
+
+    assign!(
+        multi_pool.borrow_mut().token_mints,
+        token_mints
+            .iter()
+            .map(|token_mint| token_mint.key())
+            .collect()
+    );
+
+    assign!(
+        multi_pool.borrow_mut().token_vaults,
+        token_vaults
+            .iter()
+            .map(|token_vault| token_vault.key())
+            .collect()
+    );
+
+    Ok(())
+}
+
+pub fn create_pool_handler<'info>(
+    mut authority: SeahorseSigner<'info, '_>,
+    mut config: Mutable<LoadedConfig<'info, '_>>,
+    mut admin: &UncheckedAccount<'info>,
+    mut pool: Empty<Mutable<LoadedPool<'info, '_>>>,
+    mut token_mint_a: SeahorseAccount<'info, '_, Mint>,
+    mut token_mint_b: SeahorseAccount<'info, '_, Mint>,
+    mut token_vault_a: Empty<SeahorseAccount<'info, '_, TokenAccount>>,
+    mut token_vault_b: Empty<SeahorseAccount<'info, '_, TokenAccount>>,
+    mut lp_token_mint: Empty<SeahorseAccount<'info, '_, Mint>>,
+    mut locked_lp_token_account: Empty<SeahorseAccount<'info, '_, TokenAccount>>,
+    mut fee_numerator: u64,
+    mut fee_denominator: u64,
+    mut curve_type: CurveType,
+    mut amp: u64,
+    mut weight_a: u64,
+    mut weight_b: u64,
+) -> Result<()> {
+    check_fee_tier(config.clone(), fee_numerator, fee_denominator)?;
+
+    // one pool per pair and fee tier, see `crate::pda::sort_mints`
+    if !(token_mint_a.key() < token_mint_b.key()) {
+        return err!(SeaAmmError::UnsortedMints);
+    }
+
+    // amp, the weights and the precision multipliers only mean something on
+    // their own curves
+    let mut amp = match curve_type {
+        CurveType::ConstantProduct | CurveType::Weighted => 0,
+        CurveType::StableSwap => {
+            if !(crate::stable_swap::MIN_AMP..=crate::stable_swap::MAX_AMP).contains(&amp) {
+                return err!(SeaAmmError::InvalidAmp);
+            }
+
+            amp
+        }
+    };
+
+    let (mut precision_multiplier_a, mut precision_multiplier_b) = match curve_type {
+        CurveType::ConstantProduct | CurveType::Weighted => (1, 1),
+        CurveType::StableSwap => crate::stable_swap::get_precision_multipliers(
+            token_mint_a.decimals,
+            token_mint_b.decimals,
+        )?,
+    };
+
+    let (mut weight_a, mut weight_b) = match curve_type {
+        CurveType::ConstantProduct | CurveType::StableSwap => (0, 0),
+        CurveType::Weighted => {
+            if !crate::weighted::check_weights(weight_a, weight_b)? {
+                return err!(SeaAmmError::InvalidWeights);
+            }
+
+            (weight_a, weight_b)
+        }
+    };
+
+    charge_pool_creation_fee(authority.clone(), config.clone(), admin)?;
+
+    let mut protocol_fee_numerator = config.borrow().protocol_fee_numerator;
+    let mut protocol_fee_denominator = config.borrow().protocol_fee_denominator;
+
+    let mut bump = pool.bump.unwrap();
+    let mut token_vault_a_bump = token_vault_a.bump.unwrap();
+    let mut token_vault_b_bump = token_vault_b.bump.unwrap();
+    let mut lp_token_mint_bump = lp_token_mint.bump.unwrap();
+    let mut locked_lp_token_account_bump = locked_lp_token_account.bump.unwrap();
+    let mut pool = pool.account.clone();
+    let mut token_vault_a = token_vault_a.account.clone();
+    let mut token_vault_b = token_vault_b.account.clone();
+    let mut lp_token_mint = lp_token_mint.account.clone();
+    let mut locked_lp_token_account = locked_lp_token_account.account.clone();
+
+    assign!(pool.borrow_mut().bump, bump);
+
+    assign!(pool.borrow_mut().authority, authority.key());
+
+    assign!(pool.borrow_mut().pending_authority, Pubkey::default());
+
+    assign!(pool.borrow_mut().token_mint_a, token_mint_a.key());
+
+    assign!(pool.borrow_mut().token_mint_b, token_mint_b.key());
+
+    assign!(pool.borrow_mut().token_vault_a, token_vault_a.key());
+
+    assign!(pool.borrow_mut().token_vault_b, token_vault_b.key());
+
+    assign!(pool.borrow_mut().lp_token_mint, lp_token_mint.key());
+
+    assign!(pool.borrow_mut().fee_numerator, fee_numerator);
+
+    assign!(pool.borrow_mut().fee_denominator, fee_denominator);
+
+    assign!(pool.borrow_mut().status, PoolStatus::Active);
+
+    assign!(pool.borrow_mut().curve_type, curve_type);
+
+    assign!(pool.borrow_mut().initial_amp, amp);
+
+    assign!(pool.borrow_mut().target_amp, amp);
+
+    assign!(pool.borrow_mut().ramp_start_timestamp, 0);
+
+    assign!(pool.borrow_mut().ramp_end_timestamp, 0);
+
+    assign!(pool.borrow_mut().weight_a, weight_a);
+
+    assign!(pool.borrow_mut().weight_b, weight_b);
+
+    assign!(
+        pool.borrow_mut().precision_multiplier_a,
+        precision_multiplier_a
+    );
+
+    assign!(
+        pool.borrow_mut().precision_multiplier_b,
+        precision_multiplier_b
+    );
+
+    assign!(
+        pool.borrow_mut().protocol_fee_numerator,
+        protocol_fee_numerator
+    );
+
+    assign!(
+        pool.borrow_mut().protocol_fee_denominator,
+        protocol_fee_denominator
+    );
+
+    assign!(pool.borrow_mut().token_vault_a_bump, token_vault_a_bump);
+
+    assign!(pool.borrow_mut().token_vault_b_bump, token_vault_b_bump);
+
+    assign!(pool.borrow_mut().lp_token_mint_bump, lp_token_mint_bump);
+
+    assign!(
+        pool.borrow_mut().locked_lp_token_account_bump,
+        locked_lp_token_account_bump
+    );
+
+    Ok(())
+}
+
+/// Withdraws `liquidity_amount` of a position's liquidity, paying out the
+/// tokens it holds at the current price. Fees stay in the position until
+/// `collect_fees`.
+pub fn decrease_liquidity_handler<'info>(
+    mut owner: SeahorseSigner<'info, '_>,
+    mut concentrated_pool: Mutable<LoadedConcentratedPool<'info, '_>>,
+    mut position: Mutable<LoadedPosition<'info, '_>>,
+    mut tick_array_lower: Mutable<LoadedTickArray<'info, '_>>,
+    mut tick_array_upper: Mutable<LoadedTickArray<'info, '_>>,
+    mut user_token_account_a: SeahorseAccount<'info, '_, TokenAccount>,
+    mut user_token_account_b: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_token_vault_a: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_token_vault_b: SeahorseAccount<'info, '_, TokenAccount>,
+    mut liquidity_amount: u128,
+    mut minimum_amount_a: u64,
+    mut minimum_amount_b: u64,
+) -> Result<()> {
+    check_can_withdraw(concentrated_pool.borrow().status)?;
+
+    if !(liquidity_amount > 0) {
+        return err!(SeaAmmError::ZeroAmount);
+    }
+
+    if !(liquidity_amount <= position.borrow().liquidity) {
+        return err!(SeaAmmError::InsufficientPositionLiquidity);
+    }
+
+    let mut liquidity_delta =
+        i128::try_from(liquidity_amount).map_err(|_| error!(SeaAmmError::MathOverflow))?;
+    let (mut amount_a, mut amount_b) = crate::concentrated::get_amounts_for_liquidity(
+        concentrated_pool.borrow().sqrt_price,
+        concentrated_pool.borrow().tick_current,
+        position.borrow().tick_lower,
+        position.borrow().tick_upper,
+        liquidity_amount,
+        false,
+    )?;
+
+    if !((amount_a >= minimum_amount_a) && (amount_b >= minimum_amount_b)) {
+        return err!(SeaAmmError::SlippageExceeded);
+    }
+
+    update_position(
+        concentrated_pool.clone(),
+        position.clone(),
+        tick_array_lower.clone(),
+        tick_array_upper.clone(),
+        -liquidity_delta,
+    )?;
+
+    let mut token_mint_a = concentrated_pool.borrow().token_mint_a;
+    let mut token_mint_b = concentrated_pool.borrow().token_mint_b;
+
+    if amount_a > 0 {
+        token::transfer(
+            CpiContext::new_with_signer(
+                pool_token_vault_a.programs.get("token_program"),
+                token::Transfer {
+                    from: pool_token_vault_a.to_account_info(),
+                    authority: concentrated_pool.borrow().__account__.to_account_info(),
+                    to: user_token_account_a.clone().to_account_info(),
+                },
+                &[Mutable::new(vec![
+                    "concentrated-pool".to_string().as_bytes().as_ref(),
+                    token_mint_a.as_ref(),
+                    token_mint_b.as_ref(),
+                    concentrated_pool
+                        .borrow()
+                        .fee_numerator
+                        .to_le_bytes()
+                        .as_ref(),
+                    concentrated_pool
+                        .borrow()
+                        .fee_denominator
+                        .to_le_bytes()
+                        .as_ref(),
+                    [concentrated_pool.borrow().bump].as_ref(),
+                ])
+                .borrow()
+                .as_slice()],
+            ),
+            amount_a.clone(),
+        )?;
+    }
+
+    if amount_b > 0 {
+        token::transfer(
+            CpiContext::new_with_signer(
+                pool_token_vault_b.programs.get("token_program"),
+                token::Transfer {
+                    from: pool_token_vault_b.to_account_info(),
+                    authority: concentrated_pool.borrow().__account__.to_account_info(),
+                    to: user_token_account_b.clone().to_account_info(),
+                },
+                &[Mutable::new(vec![
+                    "concentrated-pool".to_string().as_bytes().as_ref(),
+                    token_mint_a.as_ref(),
+                    token_mint_b.as_ref(),
+                    concentrated_pool
+                        .borrow()
+                        .fee_numerator
+                        .to_le_bytes()
+                        .as_ref(),
+                    concentrated_pool
+                        .borrow()
+                        .fee_denominator
+                        .to_le_bytes()
+                        .as_ref(),
+                    [concentrated_pool.borrow().bump].as_ref(),
+                ])
+                .borrow()
+                .as_slice()],
+            ),
+            amount_b.clone(),
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Returns the amplification of a stable pool now, part way through any
+/// ramp.
+pub fn get_amp<'info>(mut pool: Mutable<LoadedPool<'info, '_>>) -> Result<u64> {
+    Ok(crate::stable_swap::get_amp(
+        pool.borrow().initial_amp,
+        pool.borrow().target_amp,
+        pool.borrow().ramp_start_timestamp,
+        pool.borrow().ramp_end_timestamp,
+        Clock::get()?.unix_timestamp,
+    ))
+}
+
+/// Returns the price, liquidity in range and fee growth of a concentrated
+/// pool.
+pub fn get_concentrated_pool_state<'info>(
+    mut concentrated_pool: Mutable<LoadedConcentratedPool<'info, '_>>,
+) -> crate::concentrated::PoolState {
+    crate::concentrated::PoolState {
+        sqrt_price: concentrated_pool.borrow().sqrt_price,
+        tick_current: concentrated_pool.borrow().tick_current,
+        liquidity: concentrated_pool.borrow().liquidity,
+        fee_growth_global_a: concentrated_pool.borrow().fee_growth_global_a,
+        fee_growth_global_b: concentrated_pool.borrow().fee_growth_global_b,
+    }
+}
+
+/// Returns the LP tokens minted for the first deposit into `pool`.
+pub fn get_initial_lp_amount<'info>(
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut amount_a: u64,
+    mut amount_b: u64,
+) -> Result<u64> {
+    let mut lp_amount = match pool.borrow().curve_type {
+        CurveType::ConstantProduct => crate::math::get_initial_lp_amount(amount_a, amount_b)?,
+        CurveType::StableSwap => {
+            let (mut deposit_a, mut deposit_b) =
+                get_stable_reserves(pool.clone(), true, amount_a, amount_b);
+
+            crate::stable_swap::get_initial_lp_amount(get_amp(pool.clone())?, deposit_a, deposit_b)?
+        }
+        CurveType::Weighted => crate::weighted::get_initial_lp_amount(
+            amount_a,
+            amount_b,
+            pool.borrow().weight_a,
+            pool.borrow().weight_b,
+        )?,
+    };
+
+    Ok(lp_amount)
+}
+
+/// Returns the reserves the pool prices against, which are the vault balances
+/// less the protocol fees held in them.
+pub fn get_reserves<'info>(
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut pool_token_vault_a: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_token_vault_b: SeahorseAccount<'info, '_, TokenAccount>,
+) -> Result<(u64, u64)> {
+    let mut reserve_a = pool_token_vault_a
+        .amount
+        .checked_sub(pool.borrow().protocol_fees_a)
+        .ok_or(error!(SeaAmmError::MathOverflow))?;
+    let mut reserve_b = pool_token_vault_b
+        .amount
+        .checked_sub(pool.borrow().protocol_fees_b)
+        .ok_or(error!(SeaAmmError::MathOverflow))?;
+
+    Ok((reserve_a, reserve_b))
+}
+
+/// Returns the LP tokens minted for a single sided deposit on the pool's
+/// curve.
+pub fn get_single_sided_lp_amount<'info>(
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut is_token_a: bool,
+    mut amount_in: u64,
+    mut reserve_in: u64,
+    mut reserve_out: u64,
+    mut lp_supply: u64,
+) -> Result<u64> {
+    let mut fee_numerator = pool.borrow().fee_numerator;
+    let mut fee_denominator = pool.borrow().fee_denominator;
+    let (mut weight_in, mut weight_out) = get_swap_weights(pool.clone(), is_token_a);
+    let (mut stable_reserve_in, mut stable_reserve_out) =
+        get_stable_reserves(pool.clone(), is_token_a, reserve_in, reserve_out);
+
+    let mut lp_amount = match pool.borrow().curve_type {
+        CurveType::ConstantProduct => crate::math::get_single_sided_lp_amount(
+            amount_in,
+            reserve_in,
+            reserve_out,
+            lp_supply,
+            fee_numerator,
+            fee_denominator,
+        )?,
+        CurveType::StableSwap => crate::stable_swap::get_single_sided_lp_amount(
+            amount_in,
+            stable_reserve_in,
+            stable_reserve_out,
+            lp_supply,
+            get_amp(pool.clone())?,
+            fee_numerator,
+            fee_denominator,
+        )?,
+        CurveType::Weighted => crate::weighted::get_single_sided_lp_amount(
+            amount_in,
+            reserve_in,
+            lp_supply,
+            weight_in,
+            weight_out,
+            fee_numerator,
+            fee_denominator,
+        )?,
+    };
+
+    Ok(lp_amount)
+}
+
+/// Returns the part of a single sided deposit of `amount_in` that is in
+/// effect swapped into the other token, which is the part the swap fee is
+/// charged on.
+pub fn get_single_sided_swap_amount<'info>(
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut is_token_a: bool,
+    mut amount_in: u64,
+    mut reserve_in: u64,
+    mut reserve_out: u64,
+) -> Result<u64> {
+    let (mut weight_in, mut weight_out) = get_swap_weights(pool.clone(), is_token_a);
+    let (mut stable_reserve_in, mut stable_reserve_out) =
+        get_stable_reserves(pool.clone(), is_token_a, reserve_in, reserve_out);
+
+    let mut swap_amount = match pool.borrow().curve_type {
+        CurveType::ConstantProduct => crate::math::get_single_sided_swap_amount(
+            amount_in,
+            reserve_in,
+            pool.borrow().fee_numerator,
+            pool.borrow().fee_denominator,
+        )?,
+        CurveType::StableSwap => crate::stable_swap::get_single_sided_swap_amount(
+            amount_in,
+            stable_reserve_in,
+            stable_reserve_out,
+        )?,
+        CurveType::Weighted => {
+            crate::weighted::get_single_sided_swap_amount(amount_in, weight_in, weight_out)?
+        }
+    };
+
+    Ok(swap_amount)
+}
+
+/// Returns the reserves of a swap from token a if `is_token_a`, or from token
+/// b, with the precision multipliers of their tokens for the stable curve.
+pub fn get_stable_reserves<'info>(
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut is_token_a: bool,
+    mut reserve_in: u64,
+    mut reserve_out: u64,
+) -> (crate::stable_swap::Reserve, crate::stable_swap::Reserve) {
+    let (mut multiplier_in, mut multiplier_out) = if is_token_a {
+        (
+            pool.borrow().precision_multiplier_a,
+            pool.borrow().precision_multiplier_b,
+        )
+    } else {
+        (
+            pool.borrow().precision_multiplier_b,
+            pool.borrow().precision_multiplier_a,
+        )
+    };
+
+    (
+        crate::stable_swap::Reserve::new(reserve_in, multiplier_in),
+        crate::stable_swap::Reserve::new(reserve_out, multiplier_out),
+    )
+}
+
+/// Returns the input, fee included, that buys `amount_out` on the pool's
+/// curve.
+pub fn get_swap_amount_in<'info>(
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut is_token_a: bool,
+    mut amount_out: u64,
+    mut reserve_in: u64,
+    mut reserve_out: u64,
+) -> Result<u64> {
+    let mut fee_numerator = pool.borrow().fee_numerator;
+    let mut fee_denominator = pool.borrow().fee_denominator;
+    let (mut weight_in, mut weight_out) = get_swap_weights(pool.clone(), is_token_a);
+    let (mut stable_reserve_in, mut stable_reserve_out) =
+        get_stable_reserves(pool.clone(), is_token_a, reserve_in, reserve_out);
+
+    let mut amount_in = match pool.borrow().curve_type {
+        CurveType::ConstantProduct => crate::math::get_amount_in(
+            amount_out,
+            reserve_in,
+            reserve_out,
+            fee_numerator,
+            fee_denominator,
+        )?,
+        CurveType::StableSwap => crate::stable_swap::get_amount_in(
+            amount_out,
+            stable_reserve_in,
+            stable_reserve_out,
+            get_amp(pool.clone())?,
+            fee_numerator,
+            fee_denominator,
+        )?,
+        CurveType::Weighted => crate::weighted::get_amount_in(
+            amount_out,
+            reserve_in,
+            reserve_out,
+            weight_in,
+            weight_out,
+            fee_numerator,
+            fee_denominator,
+        )?,
+    };
+
+    Ok(amount_in)
+}
+
+/// Returns the output paid for `amount_in` on the pool's curve.
+pub fn get_swap_amount_out<'info>(
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut is_token_a: bool,
+    mut amount_in: u64,
+    mut reserve_in: u64,
+    mut reserve_out: u64,
+) -> Result<u64> {
+    let mut fee_numerator = pool.borrow().fee_numerator;
+    let mut fee_denominator = pool.borrow().fee_denominator;
+    let (mut weight_in, mut weight_out) = get_swap_weights(pool.clone(), is_token_a);
+    let (mut stable_reserve_in, mut stable_reserve_out) =
+        get_stable_reserves(pool.clone(), is_token_a, reserve_in, reserve_out);
+
+    let mut amount_out = match pool.borrow().curve_type {
+        CurveType::ConstantProduct => crate::math::get_amount_out(
+            amount_in,
+            reserve_in,
+            reserve_out,
+            fee_numerator,
+            fee_denominator,
+        )?,
+        CurveType::StableSwap => crate::stable_swap::get_amount_out(
+            amount_in,
+            stable_reserve_in,
+            stable_reserve_out,
+            get_amp(pool.clone())?,
+            fee_numerator,
+            fee_denominator,
+        )?,
+        CurveType::Weighted => crate::weighted::get_amount_out(
+            amount_in,
+            reserve_in,
+            reserve_out,
+            weight_in,
+            weight_out,
+            fee_numerator,
+            fee_denominator,
+        )?,
+    };
+
+    Ok(amount_out)
+}
+
+/// Returns the `(reserve_in, reserve_out)` of a swap from `pool_source_vault`
+/// to `pool_destination_vault`.
+pub fn get_swap_reserves<'info>(
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut pool_source_vault: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_destination_vault: SeahorseAccount<'info, '_, TokenAccount>,
+    mut is_token_a: bool,
+) -> Result<(u64, u64)> {
+    if is_token_a {
+        return get_reserves(pool, pool_source_vault, pool_destination_vault);
+    }
+
+    let (mut reserve_a, mut reserve_b) =
+        get_reserves(pool, pool_destination_vault, pool_source_vault)?;
+
+    Ok((reserve_b, reserve_a))
+}
+
+/// Returns the `(weight_in, weight_out)` of a swap from token a if
+/// `is_token_a`, or from token b.
+pub fn get_swap_weights<'info>(
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut is_token_a: bool,
+) -> (u64, u64) {
+    if is_token_a {
+        (pool.borrow().weight_a, pool.borrow().weight_b)
+    } else {
+        (pool.borrow().weight_b, pool.borrow().weight_a)
+    }
+}
+
+/// Adds `liquidity_amount` of liquidity to a position, taking the tokens it
+/// holds at the current price. The deposit fails if it needs more than
+/// `max_amount_a` or `max_amount_b`.
+pub fn increase_liquidity_handler<'info>(
+    mut owner: SeahorseSigner<'info, '_>,
+    mut concentrated_pool: Mutable<LoadedConcentratedPool<'info, '_>>,
+    mut position: Mutable<LoadedPosition<'info, '_>>,
+    mut tick_array_lower: Mutable<LoadedTickArray<'info, '_>>,
+    mut tick_array_upper: Mutable<LoadedTickArray<'info, '_>>,
+    mut user_token_account_a: SeahorseAccount<'info, '_, TokenAccount>,
+    mut user_token_account_b: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_token_vault_a: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_token_vault_b: SeahorseAccount<'info, '_, TokenAccount>,
+    mut liquidity_amount: u128,
+    mut max_amount_a: u64,
+    mut max_amount_b: u64,
+) -> Result<()> {
+    check_can_deposit(concentrated_pool.borrow().status)?;
+
+    if !(liquidity_amount > 0) {
+        return err!(SeaAmmError::ZeroAmount);
+    }
+
+    let mut liquidity_delta =
+        i128::try_from(liquidity_amount).map_err(|_| error!(SeaAmmError::MathOverflow))?;
+    let (mut amount_a, mut amount_b) = crate::concentrated::get_amounts_for_liquidity(
+        concentrated_pool.borrow().sqrt_price,
+        concentrated_pool.borrow().tick_current,
+        position.borrow().tick_lower,
+        position.borrow().tick_upper,
+        liquidity_amount,
+        true,
+    )?;
+
+    if !((amount_a <= max_amount_a) && (amount_b <= max_amount_b)) {
+        return err!(SeaAmmError::SlippageExceeded);
+    }
+
+    update_position(
+        concentrated_pool.clone(),
+        position.clone(),
+        tick_array_lower.clone(),
+        tick_array_upper.clone(),
+        liquidity_delta,
+    )?;
+
+    if amount_a > 0 {
+        token::transfer(
+            CpiContext::new(
+                user_token_account_a.programs.get("token_program"),
+                token::Transfer {
+                    from: user_token_account_a.to_account_info(),
+                    authority: owner.clone().to_account_info(),
+                    to: pool_token_vault_a.clone().to_account_info(),
+                },
+            ),
+            amount_a.clone(),
+        )?;
+    }
+
+    if amount_b > 0 {
+        token::transfer(
+            CpiContext::new(
+                user_token_account_b.programs.get("token_program"),
+                token::Transfer {
+                    from: user_token_account_b.to_account_info(),
+                    authority: owner.clone().to_account_info(),
+                    to: pool_token_vault_b.clone().to_account_info(),
+                },
+            ),
+            amount_b.clone(),
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn initialize_config_handler<'info>(
+    mut admin: SeahorseSigner<'info, '_>,
+    mut config: Empty<Mutable<LoadedConfig<'info, '_>>>,
+    mut fee_tiers: Vec<FeeTier>,
+    mut protocol_fee_numerator: u64,
+    mut protocol_fee_denominator: u64,
+    mut pool_creation_fee: u64,
+) -> Result<()> {
+    let mut bump = config.bump.unwrap();
+    let mut config = config.account.clone();
+
+    assign!(config.borrow_mut().bump, bump);
+
+    assign!(config.borrow_mut().admin, admin.key());
+
+    update_config_handler(
+        admin,
+        config,
+        fee_tiers,
+        protocol_fee_numerator,
+        protocol_fee_denominator,
+        pool_creation_fee,
+    )
+}
+
+/// Creates the tick array of a concentrated pool starting at
+/// `start_tick_index`, with every tick uninitialized.
+pub fn initialize_tick_array_handler<'info>(
+    mut payer: SeahorseSigner<'info, '_>,
+    mut concentrated_pool: Mutable<LoadedConcentratedPool<'info, '_>>,
+    mut tick_array: Empty<Mutable<LoadedTickArray<'info, '_>>>,
+    mut start_tick_index: i32,
+) -> Result<()> {
+    let mut tick_spacing = concentrated_pool.borrow().tick_spacing;
+
+    // the tick array must hold a tick a position can use
+    if !((start_tick_index
+        == crate::concentrated::get_tick_array_start_index(start_tick_index, tick_spacing))
+        && (start_tick_index <= crate::concentrated::MAX_TICK)
+        && (start_tick_index + (TICK_ARRAY_SIZE as i32) * (tick_spacing as i32)
+            > crate::concentrated::MIN_TICK))
+    {
+        return err!(SeaAmmError::InvalidTickArray);
+    }
+
+    let mut bump = tick_array.bump.unwrap();
+    let mut tick_array = tick_array.account.clone();
+
+    assign!(tick_array.borrow_mut().bump, bump);
+
+    assign!(
+        tick_array.borrow_mut().concentrated_pool,
+        concentrated_pool.borrow().__account__.key()
+    );
+
+    assign!(tick_array.borrow_mut().start_tick_index, start_tick_index);
+
+    assign!(
+        tick_array.borrow_mut().ticks,
+        crate::concentrated::get_tick_array_ticks(start_tick_index, tick_spacing)
+    );
+
+    Ok(())
+}
+
+/// Opens an empty position of `owner` between `tick_lower` and `tick_upper`,
+/// which must be multiples of the pool's tick spacing.
+pub fn open_position_handler<'info>(
+    mut owner: SeahorseSigner<'info, '_>,
+    mut concentrated_pool: Mutable<LoadedConcentratedPool<'info, '_>>,
+    mut position: Empty<Mutable<LoadedPosition<'info, '_>>>,
+    mut tick_lower: i32,
+    mut tick_upper: i32,
+) -> Result<()> {
+    let mut tick_spacing = concentrated_pool.borrow().tick_spacing as i32;
+
+    if !((crate::concentrated::MIN_TICK <= tick_lower)
+        && (tick_lower < tick_upper)
+        && (tick_upper <= crate::concentrated::MAX_TICK)
+        && (tick_lower % tick_spacing == 0)
+        && (tick_upper % tick_spacing == 0))
+    {
+        return err!(SeaAmmError::InvalidTickRange);
+    }
+
+    let mut bump = position.bump.unwrap();
+    let mut position = position.account.clone();
+
+    assign!(position.borrow_mut().bump, bump);
+
+    assign!(position.borrow_mut().owner, owner.key());
+
+    assign!(
+        position.borrow_mut().concentrated_pool,
+        concentrated_pool.borrow().__account__.key()
+    );
+
+    assign!(position.borrow_mut().tick_lower, tick_lower);
+
+    assign!(position.borrow_mut().tick_upper, tick_upper);
+
+    assign!(position.borrow_mut().liquidity, 0);
+
+    assign!(position.borrow_mut().fee_growth_inside_last_a, 0);
+
+    assign!(position.borrow_mut().fee_growth_inside_last_b, 0);
+
+    assign!(position.borrow_mut().tokens_owed_a, 0);
+
+    assign!(position.borrow_mut().tokens_owed_b, 0);
+
+    Ok(())
+}
+
+/// Proposes `new_authority` as the next pool authority, which only takes over
+/// once it signs `accept_authority`. Proposing the default pubkey cancels a
+/// pending transfer.
+pub fn propose_authority_handler<'info>(
+    mut authority: SeahorseSigner<'info, '_>,
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut new_authority: Pubkey,
+) -> Result<()> {
+    assign!(pool.borrow_mut().pending_authority, new_authority);
+
+    Ok(())
+}
+
+pub fn ramp_amp_handler<'info>(
+    mut authority: SeahorseSigner<'info, '_>,
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut target_amp: u64,
+    mut ramp_end_timestamp: i64,
+) -> Result<()> {
+    if !(pool.borrow().curve_type == CurveType::StableSwap) {
+        return err!(SeaAmmError::NotStablePool);
+    }
+
+    // a new ramp starts from wherever the current one has got to
+    let mut now = Clock::get()?.unix_timestamp;
+    let mut amp = get_amp(pool.clone())?;
+
+    crate::stable_swap::check_ramp(
+        amp,
+        target_amp,
+        pool.borrow().ramp_start_timestamp,
+        now,
+        ramp_end_timestamp,
+    )?;
+
+    assign!(pool.borrow_mut().initial_amp, amp);
+
+    assign!(pool.borrow_mut().target_amp, target_amp);
+
+    assign!(pool.borrow_mut().ramp_start_timestamp, now);
+
+    assign!(pool.borrow_mut().ramp_end_timestamp, ramp_end_timestamp);
+
+    Ok(())
+}
 
 pub fn remove_liquidity_handler<'info>(
     mut user: SeahorseSigner<'info, '_>,
@@ -251,62 +2654,82 @@ pub fn remove_liquidity_handler<'info>(
     mut pool_token_vault_b: SeahorseAccount<'info, '_, TokenAccount>,
     mut lp_token_mint: SeahorseAccount<'info, '_, Mint>,
     mut user_lp_token_account: SeahorseAccount<'info, '_, TokenAccount>,
-) -> () {
-    let mut pool_pda = Pubkey::find_program_address(
+    mut lp_amount: u64,
+    mut minimum_amount_a: u64,
+    mut minimum_amount_b: u64,
+) -> Result<()> {
+    check_can_withdraw(pool.borrow().status)?;
+
+    let mut pool_pda = Pubkey::create_program_address(
         Mutable::new(vec![
             "pool".to_string().as_bytes().as_ref(),
             token_mint_a.key().as_ref(),
             token_mint_b.key().as_ref(),
+            pool.borrow().fee_numerator.to_le_bytes().as_ref(),
+            pool.borrow().fee_denominator.to_le_bytes().as_ref(),
+            [pool.borrow().bump].as_ref(),
         ])
         .borrow()
         .as_slice(),
         &id(),
     )
-    .0;
+    .unwrap_or_default();
 
     if !(pool_pda == pool.borrow().__account__.key()) {
-        panic!("Pool address is not valid");
+        return err!(SeaAmmError::InvalidPool);
     }
 
-    let mut lp_token_mint_pda = Pubkey::find_program_address(
+    let mut lp_token_mint_pda = Pubkey::create_program_address(
         Mutable::new(vec![
             "lp-token-mint".to_string().as_bytes().as_ref(),
-            token_mint_a.key().as_ref(),
-            token_mint_b.key().as_ref(),
+            pool.borrow().__account__.key().as_ref(),
+            [pool.borrow().lp_token_mint_bump].as_ref(),
         ])
         .borrow()
         .as_slice(),
         &id(),
     )
-    .0;
+    .unwrap_or_default();
 
     if !(lp_token_mint_pda == lp_token_mint.key()) {
-        panic!("LP token mint address is not valid");
+        return err!(SeaAmmError::InvalidLpMint);
+    }
+
+    if !(lp_amount > 0) {
+        return err!(SeaAmmError::ZeroAmount);
     }
 
-    let mut token_burn_amount = user_lp_token_account.amount;
-    let mut token_amount_a = (pool_token_vault_a.amount * token_burn_amount) / lp_token_mint.supply;
-    let mut token_amount_b = (pool_token_vault_b.amount * token_burn_amount) / lp_token_mint.supply;
+    if !(lp_amount <= user_lp_token_account.amount) {
+        return err!(SeaAmmError::InsufficientLpTokens);
+    }
+
+    // withdrawals are priced before the lp tokens are burned
+    let (mut reserve_a, mut reserve_b) = get_reserves(
+        pool.clone(),
+        pool_token_vault_a.clone(),
+        pool_token_vault_b.clone(),
+    )?;
+    let mut total_lp_tokens = lp_token_mint.supply;
+    let mut token_amount_a =
+        crate::math::get_withdraw_amount(lp_amount, reserve_a, total_lp_tokens)?;
+    let mut token_amount_b =
+        crate::math::get_withdraw_amount(lp_amount, reserve_b, total_lp_tokens)?;
+
+    if !((token_amount_a >= minimum_amount_a) && (token_amount_b >= minimum_amount_b)) {
+        return err!(SeaAmmError::SlippageExceeded);
+    }
 
     token::burn(
-        CpiContext::new_with_signer(
+        CpiContext::new(
             lp_token_mint.programs.get("token_program"),
             token::Burn {
                 mint: lp_token_mint.to_account_info(),
-                authority: pool.borrow().__account__.to_account_info(),
+                authority: user.clone().to_account_info(),
                 from: user_lp_token_account.clone().to_account_info(),
             },
-            &[Mutable::new(vec![
-                "pool".to_string().as_bytes().as_ref(),
-                token_mint_a.key().as_ref(),
-                token_mint_b.key().as_ref(),
-            ])
-            .borrow()
-            .as_slice()],
         ),
-        token_burn_amount.clone(),
-    )
-    .unwrap();
+        lp_amount.clone(),
+    )?;
 
     token::transfer(
         CpiContext::new_with_signer(
@@ -320,13 +2743,15 @@ pub fn remove_liquidity_handler<'info>(
                 "pool".to_string().as_bytes().as_ref(),
                 token_mint_a.key().as_ref(),
                 token_mint_b.key().as_ref(),
+                pool.borrow().fee_numerator.to_le_bytes().as_ref(),
+                pool.borrow().fee_denominator.to_le_bytes().as_ref(),
+                [pool.borrow().bump].as_ref(),
             ])
             .borrow()
             .as_slice()],
         ),
         token_amount_a.clone(),
-    )
-    .unwrap();
+    )?;
 
     token::transfer(
         CpiContext::new_with_signer(
@@ -340,91 +2765,746 @@ pub fn remove_liquidity_handler<'info>(
                 "pool".to_string().as_bytes().as_ref(),
                 token_mint_a.key().as_ref(),
                 token_mint_b.key().as_ref(),
+                pool.borrow().fee_numerator.to_le_bytes().as_ref(),
+                pool.borrow().fee_denominator.to_le_bytes().as_ref(),
+                [pool.borrow().bump].as_ref(),
             ])
             .borrow()
             .as_slice()],
         ),
         token_amount_b.clone(),
-    )
-    .unwrap();
+    )?;
+
+    Ok(())
+}
+
+pub fn remove_liquidity_multi_handler<'info>(
+    mut user: SeahorseSigner<'info, '_>,
+    mut multi_pool: Mutable<LoadedMultiPool<'info, '_>>,
+    mut multi_pool_authority: &UncheckedAccount<'info>,
+    mut lp_token_mint: SeahorseAccount<'info, '_, Mint>,
+    mut user_lp_token_account: SeahorseAccount<'info, '_, TokenAccount>,
+    mut user_token_accounts: Vec<SeahorseAccount<'info, '_, TokenAccount>>,
+    mut pool_token_vaults: Vec<SeahorseAccount<'info, '_, TokenAccount>>,
+    mut lp_amount: u64,
+    mut minimum_amounts: Vec<u64>,
+) -> Result<()> {
+    check_can_withdraw(multi_pool.borrow().status)?;
+
+    check_multi_pool_accounts(
+        multi_pool.clone(),
+        user.clone(),
+        user_token_accounts.clone(),
+        pool_token_vaults.clone(),
+    )?;
+
+    if !(minimum_amounts.len() == pool_token_vaults.len()) {
+        return err!(SeaAmmError::InvalidTokenCount);
+    }
+
+    if !(lp_amount > 0) {
+        return err!(SeaAmmError::ZeroAmount);
+    }
+
+    if !(lp_amount <= user_lp_token_account.amount) {
+        return err!(SeaAmmError::InsufficientLpTokens);
+    }
+
+    // withdrawals are priced before the lp tokens are burned
+    let mut total_lp_tokens = lp_token_mint.supply;
+    let mut token_amounts = pool_token_vaults
+        .iter()
+        .map(|pool_token_vault| {
+            crate::math::get_withdraw_amount(lp_amount, pool_token_vault.amount, total_lp_tokens)
+        })
+        .collect::<crate::error::MathResult<Vec<u64>>>()?;
+
+    if !token_amounts
+        .iter()
+        .zip(minimum_amounts.iter())
+        .all(|(token_amount, minimum_amount)| token_amount >= minimum_amount)
+    {
+        return err!(SeaAmmError::SlippageExceeded);
+    }
+
+    token::burn(
+        CpiContext::new(
+            lp_token_mint.programs.get("token_program"),
+            token::Burn {
+                mint: lp_token_mint.to_account_info(),
+                authority: user.clone().to_account_info(),
+                from: user_lp_token_account.clone().to_account_info(),
+            },
+        ),
+        lp_amount.clone(),
+    )?;
+
+    for (pool_token_vault, (user_token_account, token_amount)) in pool_token_vaults
+        .iter()
+        .zip(user_token_accounts.iter().zip(token_amounts))
+    {
+        token::transfer(
+            CpiContext::new_with_signer(
+                pool_token_vault.programs.get("token_program"),
+                token::Transfer {
+                    from: pool_token_vault.to_account_info(),
+                    authority: multi_pool_authority.to_account_info(),
+                    to: user_token_account.to_account_info(),
+                },
+                &[Mutable::new(vec![
+                    "multi-pool-authority".to_string().as_bytes().as_ref(),
+                    multi_pool.borrow().__account__.key().as_ref(),
+                    [multi_pool.borrow().authority_bump].as_ref(),
+                ])
+                .borrow()
+                .as_slice()],
+            ),
+            token_amount,
+        )?;
+    }
+
+    Ok(())
+}
+
+/// Gives up the pool authority for good. Nobody can collect protocol fees,
+/// change the pool status or propose a new authority after this.
+pub fn renounce_authority_handler<'info>(
+    mut authority: SeahorseSigner<'info, '_>,
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+) -> Result<()> {
+    assign!(pool.borrow_mut().authority, Pubkey::default());
+
+    assign!(pool.borrow_mut().pending_authority, Pubkey::default());
+
+    // nobody can collect protocol fees any more, so the ones accrued and the
+    // whole fee of later swaps go to the liquidity providers
+    assign!(pool.borrow_mut().protocol_fee_numerator, 0);
+
+    assign!(pool.borrow_mut().protocol_fees_a, 0);
+
+    assign!(pool.borrow_mut().protocol_fees_b, 0);
+
+    Ok(())
+}
+
+pub fn set_concentrated_pool_status_handler<'info>(
+    mut authority: SeahorseSigner<'info, '_>,
+    mut concentrated_pool: Mutable<LoadedConcentratedPool<'info, '_>>,
+    mut status: PoolStatus,
+) -> Result<()> {
+    assign!(concentrated_pool.borrow_mut().status, status);
+
+    Ok(())
+}
+
+pub fn set_multi_pool_status_handler<'info>(
+    mut authority: SeahorseSigner<'info, '_>,
+    mut multi_pool: Mutable<LoadedMultiPool<'info, '_>>,
+    mut status: PoolStatus,
+) -> Result<()> {
+    assign!(multi_pool.borrow_mut().status, status);
+
+    Ok(())
+}
+
+pub fn set_pool_status_handler<'info>(
+    mut authority: SeahorseSigner<'info, '_>,
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut status: PoolStatus,
+) -> Result<()> {
+    assign!(pool.borrow_mut().status, status);
+
+    Ok(())
+}
+
+pub fn stop_ramp_amp_handler<'info>(
+    mut authority: SeahorseSigner<'info, '_>,
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+) -> Result<()> {
+    if !(pool.borrow().curve_type == CurveType::StableSwap) {
+        return err!(SeaAmmError::NotStablePool);
+    }
+
+    // hold the amplification where the ramp has got to
+    let mut now = Clock::get()?.unix_timestamp;
+    let mut amp = get_amp(pool.clone())?;
+
+    assign!(pool.borrow_mut().initial_amp, amp);
+
+    assign!(pool.borrow_mut().target_amp, amp);
+
+    assign!(pool.borrow_mut().ramp_start_timestamp, now);
+
+    assign!(pool.borrow_mut().ramp_end_timestamp, now);
+
+    Ok(())
 }
 
 pub fn swap_handler<'info>(
     mut user: SeahorseSigner<'info, '_>,
     mut pool: Mutable<LoadedPool<'info, '_>>,
-    mut token_in_mint: SeahorseAccount<'info, '_, Mint>,
-    mut token_in_vault: SeahorseAccount<'info, '_, TokenAccount>,
+    mut user_source: SeahorseAccount<'info, '_, TokenAccount>,
+    mut user_destination: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_source_vault: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_destination_vault: SeahorseAccount<'info, '_, TokenAccount>,
     mut token_in_amount: u64,
-    mut token_mint_a: SeahorseAccount<'info, '_, Mint>,
-    mut token_mint_b: SeahorseAccount<'info, '_, Mint>,
-    mut token_vault_a: SeahorseAccount<'info, '_, TokenAccount>,
-    mut token_vault_b: SeahorseAccount<'info, '_, TokenAccount>,
-) -> () {
-    let mut pool_pda = Pubkey::find_program_address(
-        Mutable::new(vec![
-            "pool".to_string().as_bytes().as_ref(),
-            token_mint_a.key().as_ref(),
-            token_mint_b.key().as_ref(),
-        ])
-        .borrow()
-        .as_slice(),
-        &id(),
-    )
-    .0;
+    mut minimum_amount_out: u64,
+) -> Result<()> {
+    check_can_swap(pool.borrow().status)?;
 
-    if !(pool_pda == pool.borrow().__account__.key()) {
-        panic!("Pool address is not valid");
+    let mut is_token_a = check_swap_accounts(
+        pool.clone(),
+        pool_source_vault.clone(),
+        pool_destination_vault.clone(),
+    )?;
+
+    if !(token_in_amount > 0) {
+        return err!(SeaAmmError::ZeroAmount);
     }
 
-    if !((token_in_mint.key() == token_mint_a.key()) || (token_in_mint.key() == token_mint_b.key()))
-    {
-        panic!("Token not available in pool");
+    let (mut reserve_in, mut reserve_out) = get_swap_reserves(
+        pool.clone(),
+        pool_source_vault.clone(),
+        pool_destination_vault.clone(),
+        is_token_a,
+    )?;
+    let mut token_out_amount = get_swap_amount_out(
+        pool.clone(),
+        is_token_a,
+        token_in_amount,
+        reserve_in,
+        reserve_out,
+    )?;
+
+    if !(token_out_amount >= minimum_amount_out) {
+        return err!(SeaAmmError::SlippageExceeded);
     }
 
+    accrue_protocol_fee(pool.clone(), is_token_a, token_in_amount)?;
+
+    let mut token_mint_a = pool.borrow().token_mint_a;
+    let mut token_mint_b = pool.borrow().token_mint_b;
+
+    token::transfer(
+        CpiContext::new(
+            user_source.programs.get("token_program"),
+            token::Transfer {
+                from: user_source.to_account_info(),
+                authority: user.clone().to_account_info(),
+                to: pool_source_vault.clone().to_account_info(),
+            },
+        ),
+        token_in_amount.clone(),
+    )?;
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            pool_destination_vault.programs.get("token_program"),
+            token::Transfer {
+                from: pool_destination_vault.to_account_info(),
+                authority: pool.borrow().__account__.to_account_info(),
+                to: user_destination.clone().to_account_info(),
+            },
+            &[Mutable::new(vec![
+                "pool".to_string().as_bytes().as_ref(),
+                token_mint_a.as_ref(),
+                token_mint_b.as_ref(),
+                pool.borrow().fee_numerator.to_le_bytes().as_ref(),
+                pool.borrow().fee_denominator.to_le_bytes().as_ref(),
+                [pool.borrow().bump].as_ref(),
+            ])
+            .borrow()
+            .as_slice()],
+        ),
+        token_out_amount.clone(),
+    )?;
+
+    Ok(())
+}
+
+/// Swaps on a concentrated pool, crossing ticks as the price moves through
+/// the ranges of liquidity. The fee accrues to the positions in range.
+pub fn swap_concentrated_handler<'info>(
+    mut user: SeahorseSigner<'info, '_>,
+    mut concentrated_pool: Mutable<LoadedConcentratedPool<'info, '_>>,
+    mut user_source: SeahorseAccount<'info, '_, TokenAccount>,
+    mut user_destination: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_source_vault: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_destination_vault: SeahorseAccount<'info, '_, TokenAccount>,
+    mut tick_arrays: Vec<Mutable<LoadedTickArray<'info, '_>>>,
+    mut token_in_amount: u64,
+    mut minimum_amount_out: u64,
+) -> Result<()> {
+    check_can_swap(concentrated_pool.borrow().status)?;
+
+    let mut a_to_b = check_concentrated_swap_accounts(
+        concentrated_pool.clone(),
+        pool_source_vault.clone(),
+        pool_destination_vault.clone(),
+    )?;
+
     if !(token_in_amount > 0) {
-        panic!("Token amount must be greater than zero");
+        return err!(SeaAmmError::ZeroAmount);
     }
 
-    let mut is_token_a = token_in_mint.key() == token_mint_a.key();
-    let mut token_out_vault = token_vault_a;
+    if tick_arrays.is_empty() {
+        return err!(SeaAmmError::InvalidTickArray);
+    }
 
-    if is_token_a {
-        token_out_vault = token_vault_b;
+    // the tick arrays run from the one holding the price in the direction of
+    // the swap, and their ticks are put in index order
+    let mut tick_spacing = concentrated_pool.borrow().tick_spacing;
+    let mut ticks_in_array = (TICK_ARRAY_SIZE as i32) * (tick_spacing as i32);
+    let mut start_tick_index = crate::concentrated::get_tick_array_start_index(
+        concentrated_pool.borrow().tick_current,
+        tick_spacing,
+    );
+    let mut ticks = Vec::new();
+
+    for tick_array in tick_arrays.iter() {
+        if !((tick_array.borrow().concentrated_pool
+            == concentrated_pool.borrow().__account__.key())
+            && (tick_array.borrow().start_tick_index == start_tick_index))
+        {
+            return err!(SeaAmmError::InvalidTickArray);
+        }
+
+        if a_to_b {
+            ticks.splice(0..0, tick_array.borrow().ticks.iter().copied());
+
+            start_tick_index -= ticks_in_array;
+        } else {
+            ticks.extend(tick_array.borrow().ticks.iter().copied());
+
+            start_tick_index += ticks_in_array;
+        }
+    }
+
+    let mut sqrt_price_limit = crate::concentrated::get_sqrt_price_limit(
+        tick_arrays[tick_arrays.len() - 1].borrow().start_tick_index,
+        tick_spacing,
+        a_to_b,
+    )?;
+    let mut result = crate::concentrated::swap(
+        &mut ticks,
+        get_concentrated_pool_state(concentrated_pool.clone()),
+        token_in_amount,
+        a_to_b,
+        sqrt_price_limit,
+        concentrated_pool.borrow().fee_numerator,
+        concentrated_pool.borrow().fee_denominator,
+    )?;
+
+    if !(result.amount_out >= minimum_amount_out) {
+        return err!(SeaAmmError::SlippageExceeded);
+    }
+
+    assign!(
+        concentrated_pool.borrow_mut().sqrt_price,
+        result.state.sqrt_price
+    );
+
+    assign!(
+        concentrated_pool.borrow_mut().tick_current,
+        result.state.tick_current
+    );
+
+    assign!(
+        concentrated_pool.borrow_mut().liquidity,
+        result.state.liquidity
+    );
+
+    assign!(
+        concentrated_pool.borrow_mut().fee_growth_global_a,
+        result.state.fee_growth_global_a
+    );
+
+    assign!(
+        concentrated_pool.borrow_mut().fee_growth_global_b,
+        result.state.fee_growth_global_b
+    );
+
+    for (mut i, mut tick_array) in tick_arrays.iter().enumerate() {
+        let mut position = if a_to_b { tick_arrays.len() - 1 - i } else { i };
+
+        assign!(
+            tick_array.borrow_mut().ticks,
+            ticks[(position * TICK_ARRAY_SIZE)..((position + 1) * TICK_ARRAY_SIZE)].to_vec()
+        );
     }
 
-    let mut token_out_amount =
-        (token_out_vault.amount * token_in_amount) / (token_in_vault.amount + token_in_amount);
+    let mut token_mint_a = concentrated_pool.borrow().token_mint_a;
+    let mut token_mint_b = concentrated_pool.borrow().token_mint_b;
 
     token::transfer(
         CpiContext::new(
-            token_in_vault.programs.get("token_program"),
+            user_source.programs.get("token_program"),
             token::Transfer {
-                from: token_in_vault.to_account_info(),
+                from: user_source.to_account_info(),
                 authority: user.clone().to_account_info(),
-                to: token_out_vault.clone().to_account_info(),
+                to: pool_source_vault.clone().to_account_info(),
             },
         ),
         token_in_amount.clone(),
-    )
-    .unwrap();
+    )?;
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            pool_destination_vault.programs.get("token_program"),
+            token::Transfer {
+                from: pool_destination_vault.to_account_info(),
+                authority: concentrated_pool.borrow().__account__.to_account_info(),
+                to: user_destination.clone().to_account_info(),
+            },
+            &[Mutable::new(vec![
+                "concentrated-pool".to_string().as_bytes().as_ref(),
+                token_mint_a.as_ref(),
+                token_mint_b.as_ref(),
+                concentrated_pool
+                    .borrow()
+                    .fee_numerator
+                    .to_le_bytes()
+                    .as_ref(),
+                concentrated_pool
+                    .borrow()
+                    .fee_denominator
+                    .to_le_bytes()
+                    .as_ref(),
+                [concentrated_pool.borrow().bump].as_ref(),
+            ])
+            .borrow()
+            .as_slice()],
+        ),
+        result.amount_out.clone(),
+    )?;
+
+    Ok(())
+}
+
+pub fn swap_exact_out_handler<'info>(
+    mut user: SeahorseSigner<'info, '_>,
+    mut pool: Mutable<LoadedPool<'info, '_>>,
+    mut user_source: SeahorseAccount<'info, '_, TokenAccount>,
+    mut user_destination: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_source_vault: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_destination_vault: SeahorseAccount<'info, '_, TokenAccount>,
+    mut amount_out: u64,
+    mut maximum_amount_in: u64,
+) -> Result<()> {
+    check_can_swap(pool.borrow().status)?;
+
+    let mut is_token_a = check_swap_accounts(
+        pool.clone(),
+        pool_source_vault.clone(),
+        pool_destination_vault.clone(),
+    )?;
+
+    if !(amount_out > 0) {
+        return err!(SeaAmmError::ZeroAmount);
+    }
+
+    let (mut reserve_in, mut reserve_out) = get_swap_reserves(
+        pool.clone(),
+        pool_source_vault.clone(),
+        pool_destination_vault.clone(),
+        is_token_a,
+    )?;
+
+    if !(amount_out < reserve_out) {
+        return err!(SeaAmmError::InsufficientLiquidity);
+    }
+
+    let mut token_in_amount = get_swap_amount_in(
+        pool.clone(),
+        is_token_a,
+        amount_out,
+        reserve_in,
+        reserve_out,
+    )?;
+
+    if !(token_in_amount <= maximum_amount_in) {
+        return err!(SeaAmmError::SlippageExceeded);
+    }
+
+    accrue_protocol_fee(pool.clone(), is_token_a, token_in_amount)?;
+
+    let mut token_mint_a = pool.borrow().token_mint_a;
+    let mut token_mint_b = pool.borrow().token_mint_b;
+
+    token::transfer(
+        CpiContext::new(
+            user_source.programs.get("token_program"),
+            token::Transfer {
+                from: user_source.to_account_info(),
+                authority: user.clone().to_account_info(),
+                to: pool_source_vault.clone().to_account_info(),
+            },
+        ),
+        token_in_amount.clone(),
+    )?;
 
     token::transfer(
         CpiContext::new_with_signer(
-            token_out_vault.programs.get("token_program"),
+            pool_destination_vault.programs.get("token_program"),
             token::Transfer {
-                from: token_out_vault.to_account_info(),
+                from: pool_destination_vault.to_account_info(),
                 authority: pool.borrow().__account__.to_account_info(),
-                to: token_in_vault.clone().to_account_info(),
+                to: user_destination.clone().to_account_info(),
             },
             &[Mutable::new(vec![
                 "pool".to_string().as_bytes().as_ref(),
-                token_mint_a.key().as_ref(),
-                token_mint_b.key().as_ref(),
+                token_mint_a.as_ref(),
+                token_mint_b.as_ref(),
+                pool.borrow().fee_numerator.to_le_bytes().as_ref(),
+                pool.borrow().fee_denominator.to_le_bytes().as_ref(),
+                [pool.borrow().bump].as_ref(),
+            ])
+            .borrow()
+            .as_slice()],
+        ),
+        amount_out.clone(),
+    )?;
+
+    Ok(())
+}
+
+/// Swaps between any two tokens of a multi pool. Only their two reserves
+/// change, so pricing on their constant product keeps the product of all
+/// reserves from decreasing.
+pub fn swap_multi_handler<'info>(
+    mut user: SeahorseSigner<'info, '_>,
+    mut multi_pool: Mutable<LoadedMultiPool<'info, '_>>,
+    mut multi_pool_authority: &UncheckedAccount<'info>,
+    mut user_source: SeahorseAccount<'info, '_, TokenAccount>,
+    mut user_destination: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_source_vault: SeahorseAccount<'info, '_, TokenAccount>,
+    mut pool_destination_vault: SeahorseAccount<'info, '_, TokenAccount>,
+    mut token_in_amount: u64,
+    mut minimum_amount_out: u64,
+) -> Result<()> {
+    check_can_swap(multi_pool.borrow().status)?;
+
+    let mut token_vaults = multi_pool.borrow().token_vaults.clone();
+
+    if !(token_vaults.contains(&pool_source_vault.key())
+        && token_vaults.contains(&pool_destination_vault.key())
+        && (pool_source_vault.key() != pool_destination_vault.key()))
+    {
+        return err!(SeaAmmError::InvalidVault);
+    }
+
+    if !(token_in_amount > 0) {
+        return err!(SeaAmmError::ZeroAmount);
+    }
+
+    let mut token_out_amount = crate::math::get_amount_out(
+        token_in_amount,
+        pool_source_vault.amount,
+        pool_destination_vault.amount,
+        multi_pool.borrow().fee_numerator,
+        multi_pool.borrow().fee_denominator,
+    )?;
+
+    if !(token_out_amount >= minimum_amount_out) {
+        return err!(SeaAmmError::SlippageExceeded);
+    }
+
+    token::transfer(
+        CpiContext::new(
+            user_source.programs.get("token_program"),
+            token::Transfer {
+                from: user_source.to_account_info(),
+                authority: user.clone().to_account_info(),
+                to: pool_source_vault.clone().to_account_info(),
+            },
+        ),
+        token_in_amount.clone(),
+    )?;
+
+    token::transfer(
+        CpiContext::new_with_signer(
+            pool_destination_vault.programs.get("token_program"),
+            token::Transfer {
+                from: pool_destination_vault.to_account_info(),
+                authority: multi_pool_authority.to_account_info(),
+                to: user_destination.clone().to_account_info(),
+            },
+            &[Mutable::new(vec![
+                "multi-pool-authority".to_string().as_bytes().as_ref(),
+                multi_pool.borrow().__account__.key().as_ref(),
+                [multi_pool.borrow().authority_bump].as_ref(),
             ])
             .borrow()
             .as_slice()],
         ),
         token_out_amount.clone(),
-    )
-    .unwrap();
+    )?;
+
+    Ok(())
+}
+
+/// Changes the liquidity of a position by `liquidity_delta`, updating its
+/// ticks in `tick_array_lower` and `tick_array_upper` and the pool's
+/// liquidity in range, and adds the fees it earned since it last changed to
+/// what it is owed. Both ticks can be in the same tick array.
+pub fn update_position<'info>(
+    mut concentrated_pool: Mutable<LoadedConcentratedPool<'info, '_>>,
+    mut position: Mutable<LoadedPosition<'info, '_>>,
+    mut tick_array_lower: Mutable<LoadedTickArray<'info, '_>>,
+    mut tick_array_upper: Mutable<LoadedTickArray<'info, '_>>,
+    mut liquidity_delta: i128,
+) -> Result<()> {
+    let mut tick_spacing = concentrated_pool.borrow().tick_spacing;
+    let mut tick_current = concentrated_pool.borrow().tick_current;
+    let mut fee_growth_global_a = concentrated_pool.borrow().fee_growth_global_a;
+    let mut fee_growth_global_b = concentrated_pool.borrow().fee_growth_global_b;
+    let mut tick_lower = position.borrow().tick_lower;
+    let mut tick_upper = position.borrow().tick_upper;
+    let mut liquidity = position.borrow().liquidity;
+
+    if !((tick_array_lower.borrow().start_tick_index
+        == crate::concentrated::get_tick_array_start_index(tick_lower, tick_spacing))
+        && (tick_array_upper.borrow().start_tick_index
+            == crate::concentrated::get_tick_array_start_index(tick_upper, tick_spacing)))
+    {
+        return err!(SeaAmmError::InvalidTickArray);
+    }
+
+    // both ticks are updated in one list, which is split back into the tick
+    // arrays at the end, so a tick array passed twice is stored the same
+    // both times
+    let mut is_same_tick_array =
+        tick_array_lower.borrow().__account__.key() == tick_array_upper.borrow().__account__.key();
+    let mut ticks = tick_array_lower.borrow().ticks.clone();
+
+    if !is_same_tick_array {
+        ticks.extend(tick_array_upper.borrow().ticks.iter().copied());
+    }
+
+    if liquidity_delta != 0 {
+        crate::concentrated::update_tick(
+            &mut ticks,
+            tick_lower,
+            tick_current,
+            liquidity_delta,
+            false,
+            fee_growth_global_a,
+            fee_growth_global_b,
+        )?;
+        crate::concentrated::update_tick(
+            &mut ticks,
+            tick_upper,
+            tick_current,
+            liquidity_delta,
+            true,
+            fee_growth_global_a,
+            fee_growth_global_b,
+        )?;
+    }
+
+    let (mut fee_growth_inside_a, mut fee_growth_inside_b) =
+        crate::concentrated::get_fee_growth_inside(
+            &ticks,
+            tick_current,
+            tick_lower,
+            tick_upper,
+            fee_growth_global_a,
+            fee_growth_global_b,
+        );
+    let mut fees_a = crate::concentrated::get_fees_owed(
+        liquidity,
+        fee_growth_inside_a,
+        position.borrow().fee_growth_inside_last_a,
+    )?;
+    let mut fees_b = crate::concentrated::get_fees_owed(
+        liquidity,
+        fee_growth_inside_b,
+        position.borrow().fee_growth_inside_last_b,
+    )?;
+
+    assign!(
+        position.borrow_mut().tokens_owed_a,
+        crate::math::to_u64(crate::math::checked_add(
+            position.borrow().tokens_owed_a as u128,
+            fees_a as u128,
+        )?)?
+    );
+
+    assign!(
+        position.borrow_mut().tokens_owed_b,
+        crate::math::to_u64(crate::math::checked_add(
+            position.borrow().tokens_owed_b as u128,
+            fees_b as u128,
+        )?)?
+    );
+
+    assign!(
+        position.borrow_mut().fee_growth_inside_last_a,
+        fee_growth_inside_a
+    );
+
+    assign!(
+        position.borrow_mut().fee_growth_inside_last_b,
+        fee_growth_inside_b
+    );
+
+    assign!(
+        position.borrow_mut().liquidity,
+        crate::concentrated::add_liquidity_delta(liquidity, liquidity_delta)?
+    );
+
+    if (tick_lower..tick_upper).contains(&tick_current) {
+        assign!(
+            concentrated_pool.borrow_mut().liquidity,
+            crate::concentrated::add_liquidity_delta(
+                concentrated_pool.borrow().liquidity,
+                liquidity_delta
+            )?
+        );
+    }
+
+    let mut ticks_upper = if is_same_tick_array {
+        ticks.clone()
+    } else {
+        ticks.split_off(TICK_ARRAY_SIZE)
+    };
+
+    assign!(tick_array_lower.borrow_mut().ticks, ticks);
+
+    assign!(tick_array_upper.borrow_mut().ticks, ticks_upper);
+
+    Ok(())
+}
+
+/// Replaces the program wide settings. Pools keep the protocol fee share they
+/// were created with.
+pub fn update_config_handler<'info>(
+    mut admin: SeahorseSigner<'info, '_>,
+    mut config: Mutable<LoadedConfig<'info, '_>>,
+    mut fee_tiers: Vec<FeeTier>,
+    mut protocol_fee_numerator: u64,
+    mut protocol_fee_denominator: u64,
+    mut pool_creation_fee: u64,
+) -> Result<()> {
+    check_config(
+        fee_tiers.clone(),
+        protocol_fee_numerator,
+        protocol_fee_denominator,
+    )?;
+
+    assign!(config.borrow_mut().fee_tiers, fee_tiers);
+
+    assign!(
+        config.borrow_mut().protocol_fee_numerator,
+        protocol_fee_numerator
+    );
+
+    assign!(
+        config.borrow_mut().protocol_fee_denominator,
+        protocol_fee_denominator
+    );
+
+    assign!(config.borrow_mut().pool_creation_fee, pool_creation_fee);
+
+    Ok(())
 }
@@ -0,0 +1,86 @@
+use anchor_lang::prelude::*;
+
+/// Result of the curve and tick math. It fails with a `SeaAmmError` alone,
+/// which converts into an Anchor error where a handler returns it, so the
+/// math doesn't pass Anchor's much larger error around.
+pub type MathResult<T> = std::result::Result<T, SeaAmmError>;
+
+#[error_code]
+pub enum SeaAmmError {
+    #[msg("Pool address is not valid")]
+    InvalidPool,
+    #[msg("LP token mint address is not valid")]
+    InvalidLpMint,
+    #[msg("Token amount must be greater than zero")]
+    ZeroAmount,
+    #[msg("Token not available in pool")]
+    TokenNotInPool,
+    #[msg("Change amount of token a or token b to add liquidity")]
+    RatioMismatch,
+    #[msg("No lp tokens to mint")]
+    NoLpToMint,
+    #[msg("Swap amount is outside the slippage bound")]
+    SlippageExceeded,
+    #[msg("Fee must be less than one")]
+    InvalidFee,
+    #[msg("Not enough liquidity in pool")]
+    InsufficientLiquidity,
+    #[msg("Math operation overflowed")]
+    MathOverflow,
+    #[msg("Initial liquidity must be more than the minimum liquidity")]
+    InsufficientInitialLiquidity,
+    #[msg("Token vault address is not valid")]
+    InvalidVault,
+    #[msg("Not enough lp tokens in user account")]
+    InsufficientLpTokens,
+    #[msg("Token mint a must be less than token mint b")]
+    UnsortedMints,
+    #[msg("Protocol fee must be at most the whole swap fee")]
+    InvalidProtocolFee,
+    #[msg("Signer is not the pool authority")]
+    Unauthorized,
+    #[msg("Swaps are paused on this pool")]
+    SwapsPaused,
+    #[msg("Deposits are paused on this pool")]
+    DepositsPaused,
+    #[msg("Pool is frozen")]
+    PoolFrozen,
+    #[msg("Fee is not one of the config fee tiers")]
+    InvalidFeeTier,
+    #[msg("Too many fee tiers")]
+    TooManyFeeTiers,
+    #[msg("Admin account does not match the config admin")]
+    InvalidAdmin,
+    #[msg("Amplification is out of range")]
+    InvalidAmp,
+    #[msg("Curve math did not converge")]
+    CurveNotConverged,
+    #[msg("Token weights are out of range")]
+    InvalidWeights,
+    #[msg("Amount is more than a weighted pool trades at once")]
+    TradeTooLarge,
+    #[msg("Pool is not a stable pool")]
+    NotStablePool,
+    #[msg("Amplification changes too much in one ramp")]
+    AmpChangeTooLarge,
+    #[msg("Ramp ends too soon")]
+    InvalidRampTime,
+    #[msg("Wrong number of tokens for a multi pool")]
+    InvalidTokenCount,
+    #[msg("Token account does not belong to the user or holds another mint")]
+    InvalidTokenAccount,
+    #[msg("Tick spacing must be greater than zero")]
+    InvalidTickSpacing,
+    #[msg("Price is out of range")]
+    InvalidSqrtPrice,
+    #[msg("Ticks are out of range or not on the tick spacing")]
+    InvalidTickRange,
+    #[msg("Tick array does not belong to the pool or hold the right ticks")]
+    InvalidTickArray,
+    #[msg("Not enough liquidity in position")]
+    InsufficientPositionLiquidity,
+    #[msg("Position still has liquidity or fees to collect")]
+    PositionNotEmpty,
+    #[msg("Last ramp started too recently")]
+    RampTooSoon,
+}
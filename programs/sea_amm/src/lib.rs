@@ -1,8 +1,15 @@
 #![allow(unused_imports)]
 #![allow(unused_variables)]
 #![allow(unused_mut)]
+#![allow(unexpected_cfgs)]
 
+pub mod concentrated;
 pub mod dot;
+pub mod error;
+pub mod math;
+pub mod pda;
+pub mod stable_swap;
+pub mod weighted;
 
 use anchor_lang::prelude::*;
 use anchor_spl::{
@@ -11,6 +18,7 @@ use anchor_spl::{
 };
 
 use dot::program::*;
+use error::SeaAmmError;
 use std::{cell::RefCell, rc::Rc};
 
 declare_id!("BBFDagoxxEadDkckRhXwRH2TmycytSjws4cErd6qKTYY");
@@ -64,7 +72,7 @@ pub mod seahorse_util {
 
             index += self.borrow().len() as i128;
 
-            return index.try_into().unwrap();
+            index.try_into().unwrap()
         }
     }
 
@@ -76,7 +84,7 @@ pub mod seahorse_util {
 
             index += self.borrow().len() as i128;
 
-            return index.try_into().unwrap();
+            index.try_into().unwrap()
         }
     }
 
@@ -105,7 +113,7 @@ pub mod seahorse_util {
         type Target = A;
 
         fn deref(&self) -> &Self::Target {
-            &self.account
+            self.account
         }
     }
 
@@ -114,6 +122,22 @@ pub mod seahorse_util {
 
     pub type SeahorseSigner<'info, 'entrypoint> = WithPrograms<'info, 'entrypoint, Signer<'info>>;
 
+    /// Loads `infos`, which come from `ctx.remaining_accounts`, as accounts of
+    /// type `A`. Instructions of multi pools take their per token accounts
+    /// this way, as their number depends on the pool, and swaps on
+    /// concentrated pools take the tick arrays they cross.
+    #[allow(clippy::result_large_err)]
+    pub fn load_remaining_accounts<'a, 'info: 'a, A>(
+        infos: impl Iterator<Item = &'a AccountInfo<'info>>,
+    ) -> Result<Vec<Box<Account<'info, A>>>>
+    where
+        A: AccountSerialize + AccountDeserialize + Owner + Clone,
+    {
+        infos
+            .map(|info| Account::try_from(info).map(Box::new))
+            .collect()
+    }
+
     #[derive(Clone, Debug)]
     pub struct CpiAccount<'info> {
         #[doc = "CHECK: CpiAccounts temporarily store AccountInfos."]
@@ -143,6 +167,9 @@ pub mod seahorse_util {
     }
 }
 
+// the instructions return Anchor's `Result`, whose error is larger than
+// clippy likes, so that lint is off for the entrypoints alone
+#[allow(clippy::result_large_err)]
 #[program]
 mod sea_amm {
     use super::*;
@@ -150,27 +177,53 @@ mod sea_amm {
     use std::collections::HashMap;
 
     #[derive(Accounts)]
-    # [instruction (token_amount_a : u64 , token_amount_b : u64)]
+    pub struct AcceptAuthority<'info> {
+        pub pending_authority: Signer<'info>,
+        # [account (mut , has_one = pending_authority @ SeaAmmError :: Unauthorized)]
+        pub pool: Box<Account<'info, dot::program::Pool>>,
+    }
+
+    pub fn accept_authority(ctx: Context<AcceptAuthority>) -> Result<()> {
+        let mut programs = HashMap::new();
+        let programs_map = ProgramsMap(programs);
+        let pending_authority = SeahorseSigner {
+            account: &ctx.accounts.pending_authority,
+            programs: &programs_map,
+        };
+
+        let pool = dot::program::Pool::load(&mut ctx.accounts.pool, &programs_map);
+
+        accept_authority_handler(pending_authority.clone(), pool.clone())?;
+
+        dot::program::Pool::store(pool);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    # [instruction (max_amount_a : u64 , max_amount_b : u64 , min_lp_out : u64)]
     pub struct AddLiquidity<'info> {
         #[account(mut)]
         pub user: Signer<'info>,
-        #[account(mut)]
+        # [account (mut , has_one = token_mint_a @ SeaAmmError :: InvalidPool , has_one = token_mint_b @ SeaAmmError :: InvalidPool , has_one = lp_token_mint @ SeaAmmError :: InvalidLpMint)]
         pub pool: Box<Account<'info, dot::program::Pool>>,
         #[account(mut)]
         pub token_mint_a: Box<Account<'info, Mint>>,
         #[account(mut)]
         pub token_mint_b: Box<Account<'info, Mint>>,
-        #[account(mut)]
+        # [account (mut , token :: mint = token_mint_a , token :: authority = user)]
         pub user_token_account_a: Box<Account<'info, TokenAccount>>,
-        #[account(mut)]
+        # [account (mut , token :: mint = token_mint_b , token :: authority = user)]
         pub user_token_account_b: Box<Account<'info, TokenAccount>>,
-        #[account(mut)]
+        # [account (mut , address = pool . token_vault_a @ SeaAmmError :: InvalidVault)]
         pub pool_token_vault_a: Box<Account<'info, TokenAccount>>,
-        #[account(mut)]
+        # [account (mut , address = pool . token_vault_b @ SeaAmmError :: InvalidVault)]
         pub pool_token_vault_b: Box<Account<'info, TokenAccount>>,
         #[account(mut)]
         pub lp_token_mint: Box<Account<'info, Mint>>,
-        # [account (init , payer = user , seeds = ["lp-token-account" . as_bytes () . as_ref () , lp_token_mint . key () . as_ref () , user . key () . as_ref ()] , bump , token :: mint = lp_token_mint , token :: authority = user)]
+        # [account (mut , seeds = ["locked-lp-token-account" . as_bytes () , lp_token_mint . key () . as_ref ()] , bump = pool . locked_lp_token_account_bump)]
+        pub locked_lp_token_account: Box<Account<'info, TokenAccount>>,
+        # [account (init_if_needed , payer = user , seeds = ["lp-token-account" . as_bytes () , lp_token_mint . key () . as_ref () , user . key () . as_ref ()] , bump , token :: mint = lp_token_mint , token :: authority = user)]
         pub user_lp_token_account: Box<Account<'info, TokenAccount>>,
         pub rent: Sysvar<'info, Rent>,
         pub system_program: Program<'info, System>,
@@ -179,8 +232,9 @@ mod sea_amm {
 
     pub fn add_liquidity(
         ctx: Context<AddLiquidity>,
-        token_amount_a: u64,
-        token_amount_b: u64,
+        max_amount_a: u64,
+        max_amount_b: u64,
+        min_lp_out: u64,
     ) -> Result<()> {
         let mut programs = HashMap::new();
 
@@ -236,12 +290,14 @@ mod sea_amm {
             programs: &programs_map,
         };
 
-        let user_lp_token_account = Empty {
-            account: SeahorseAccount {
-                account: &ctx.accounts.user_lp_token_account,
-                programs: &programs_map,
-            },
-            bump: ctx.bumps.get("user_lp_token_account").map(|bump| *bump),
+        let locked_lp_token_account = SeahorseAccount {
+            account: &ctx.accounts.locked_lp_token_account,
+            programs: &programs_map,
+        };
+
+        let user_lp_token_account = SeahorseAccount {
+            account: &ctx.accounts.user_lp_token_account,
+            programs: &programs_map,
         };
 
         add_liquidity_handler(
@@ -254,38 +310,46 @@ mod sea_amm {
             pool_token_vault_a.clone(),
             pool_token_vault_b.clone(),
             lp_token_mint.clone(),
+            locked_lp_token_account.clone(),
             user_lp_token_account.clone(),
-            token_amount_a,
-            token_amount_b,
-        );
+            max_amount_a,
+            max_amount_b,
+            min_lp_out,
+        )?;
 
         dot::program::Pool::store(pool);
 
-        return Ok(());
+        Ok(())
     }
 
     #[derive(Accounts)]
-    pub struct CreatePool<'info> {
-        #[account(mut)]
-        pub authority: Signer<'info>,
-        # [account (init , space = std :: mem :: size_of :: < dot :: program :: Pool > () + 8 , payer = authority , seeds = ["pool" . as_bytes () . as_ref () , token_mint_a . key () . as_ref () , token_mint_b . key () . as_ref ()] , bump)]
-        pub pool: Box<Account<'info, dot::program::Pool>>,
+    # [instruction (max_amounts : Vec < u64 > , min_lp_out : u64)]
+    pub struct AddLiquidityMulti<'info> {
         #[account(mut)]
-        pub token_mint_a: Box<Account<'info, Mint>>,
+        pub user: Signer<'info>,
+        # [account (has_one = lp_token_mint @ SeaAmmError :: InvalidLpMint)]
+        pub multi_pool: Box<Account<'info, dot::program::MultiPool>>,
+        /// CHECK: signs for the pool's vaults and lp token mint
+        # [account (seeds = ["multi-pool-authority" . as_bytes () , multi_pool . key () . as_ref ()] , bump = multi_pool . authority_bump)]
+        pub multi_pool_authority: UncheckedAccount<'info>,
         #[account(mut)]
-        pub token_mint_b: Box<Account<'info, Mint>>,
-        # [account (init , payer = authority , seeds = ["token-vault-a" . as_bytes () . as_ref () , token_mint_a . key () . as_ref ()] , bump , token :: mint = token_mint_a , token :: authority = pool)]
-        pub token_vault_a: Box<Account<'info, TokenAccount>>,
-        # [account (init , payer = authority , seeds = ["token-vault-b" . as_bytes () . as_ref () , token_mint_b . key () . as_ref ()] , bump , token :: mint = token_mint_b , token :: authority = pool)]
-        pub token_vault_b: Box<Account<'info, TokenAccount>>,
-        # [account (init , payer = authority , seeds = ["lp-token-mint" . as_bytes () . as_ref () , token_mint_a . key () . as_ref () , token_mint_b . key () . as_ref ()] , bump , mint :: decimals = 6 , mint :: authority = pool)]
         pub lp_token_mint: Box<Account<'info, Mint>>,
+        # [account (mut , seeds = ["locked-lp-token-account" . as_bytes () , lp_token_mint . key () . as_ref ()] , bump = multi_pool . locked_lp_token_account_bump)]
+        pub locked_lp_token_account: Box<Account<'info, TokenAccount>>,
+        # [account (init_if_needed , payer = user , seeds = ["lp-token-account" . as_bytes () , lp_token_mint . key () . as_ref () , user . key () . as_ref ()] , bump , token :: mint = lp_token_mint , token :: authority = user)]
+        pub user_lp_token_account: Box<Account<'info, TokenAccount>>,
         pub rent: Sysvar<'info, Rent>,
         pub system_program: Program<'info, System>,
         pub token_program: Program<'info, Token>,
     }
 
-    pub fn create_pool(ctx: Context<CreatePool>) -> Result<()> {
+    /// Remaining accounts: the user's token account and the pool's vault of
+    /// each pool token in turn, in the pool's order.
+    pub fn add_liquidity_multi<'info>(
+        ctx: Context<'_, '_, '_, 'info, AddLiquidityMulti<'info>>,
+        max_amounts: Vec<u64>,
+        min_lp_out: u64,
+    ) -> Result<()> {
         let mut programs = HashMap::new();
 
         programs.insert(
@@ -299,91 +363,91 @@ mod sea_amm {
         );
 
         let programs_map = ProgramsMap(programs);
-        let authority = SeahorseSigner {
-            account: &ctx.accounts.authority,
+        let user_token_accounts =
+            load_remaining_accounts::<TokenAccount>(ctx.remaining_accounts.iter().step_by(2))?;
+        let pool_token_vaults = load_remaining_accounts::<TokenAccount>(
+            ctx.remaining_accounts.iter().skip(1).step_by(2),
+        )?;
+        let user = SeahorseSigner {
+            account: &ctx.accounts.user,
             programs: &programs_map,
         };
 
-        let pool = Empty {
-            account: dot::program::Pool::load(&mut ctx.accounts.pool, &programs_map),
-            bump: ctx.bumps.get("pool").map(|bump| *bump),
-        };
-
-        let token_mint_a = SeahorseAccount {
-            account: &ctx.accounts.token_mint_a,
+        let multi_pool = dot::program::MultiPool::load(&mut ctx.accounts.multi_pool, &programs_map);
+        let multi_pool_authority = &ctx.accounts.multi_pool_authority;
+        let lp_token_mint = SeahorseAccount {
+            account: &ctx.accounts.lp_token_mint,
             programs: &programs_map,
         };
 
-        let token_mint_b = SeahorseAccount {
-            account: &ctx.accounts.token_mint_b,
+        let locked_lp_token_account = SeahorseAccount {
+            account: &ctx.accounts.locked_lp_token_account,
             programs: &programs_map,
         };
 
-        let token_vault_a = Empty {
-            account: SeahorseAccount {
-                account: &ctx.accounts.token_vault_a,
-                programs: &programs_map,
-            },
-            bump: ctx.bumps.get("token_vault_a").map(|bump| *bump),
+        let user_lp_token_account = SeahorseAccount {
+            account: &ctx.accounts.user_lp_token_account,
+            programs: &programs_map,
         };
 
-        let token_vault_b = Empty {
-            account: SeahorseAccount {
-                account: &ctx.accounts.token_vault_b,
+        let user_token_accounts = user_token_accounts
+            .iter()
+            .map(|account| SeahorseAccount {
+                account,
                 programs: &programs_map,
-            },
-            bump: ctx.bumps.get("token_vault_b").map(|bump| *bump),
-        };
-
-        let lp_token_mint = Empty {
-            account: SeahorseAccount {
-                account: &ctx.accounts.lp_token_mint,
+            })
+            .collect();
+        let pool_token_vaults = pool_token_vaults
+            .iter()
+            .map(|account| SeahorseAccount {
+                account,
                 programs: &programs_map,
-            },
-            bump: ctx.bumps.get("lp_token_mint").map(|bump| *bump),
-        };
+            })
+            .collect();
 
-        create_pool_handler(
-            authority.clone(),
-            pool.clone(),
-            token_mint_a.clone(),
-            token_mint_b.clone(),
-            token_vault_a.clone(),
-            token_vault_b.clone(),
+        add_liquidity_multi_handler(
+            user.clone(),
+            multi_pool.clone(),
+            multi_pool_authority,
             lp_token_mint.clone(),
-        );
+            locked_lp_token_account.clone(),
+            user_lp_token_account.clone(),
+            user_token_accounts,
+            pool_token_vaults,
+            max_amounts,
+            min_lp_out,
+        )?;
 
-        dot::program::Pool::store(pool.account);
+        dot::program::MultiPool::store(multi_pool);
 
-        return Ok(());
+        Ok(())
     }
 
     #[derive(Accounts)]
-    pub struct RemoveLiquidity<'info> {
+    # [instruction (token_in_amount : u64 , minimum_lp_out : u64)]
+    pub struct AddLiquiditySingle<'info> {
         #[account(mut)]
         pub user: Signer<'info>,
-        #[account(mut)]
+        # [account (mut , has_one = lp_token_mint @ SeaAmmError :: InvalidLpMint)]
         pub pool: Box<Account<'info, dot::program::Pool>>,
+        # [account (mut , token :: mint = pool_source_vault . mint , token :: authority = user)]
+        pub user_source: Box<Account<'info, TokenAccount>>,
         #[account(mut)]
-        pub token_mint_a: Box<Account<'info, Mint>>,
-        #[account(mut)]
-        pub token_mint_b: Box<Account<'info, Mint>>,
-        #[account(mut)]
-        pub user_token_account_a: Box<Account<'info, TokenAccount>>,
-        #[account(mut)]
-        pub user_token_account_b: Box<Account<'info, TokenAccount>>,
-        #[account(mut)]
-        pub pool_token_vault_a: Box<Account<'info, TokenAccount>>,
+        pub pool_source_vault: Box<Account<'info, TokenAccount>>,
         #[account(mut)]
-        pub pool_token_vault_b: Box<Account<'info, TokenAccount>>,
+        pub pool_destination_vault: Box<Account<'info, TokenAccount>>,
         #[account(mut)]
         pub lp_token_mint: Box<Account<'info, Mint>>,
-        #[account(mut)]
+        # [account (mut , token :: mint = lp_token_mint , token :: authority = user)]
         pub user_lp_token_account: Box<Account<'info, TokenAccount>>,
         pub token_program: Program<'info, Token>,
     }
 
-    pub fn remove_liquidity(ctx: Context<RemoveLiquidity>) -> Result<()> {
+    pub fn add_liquidity_single(
+        ctx: Context<AddLiquiditySingle>,
+        token_in_amount: u64,
+        minimum_lp_out: u64,
+    ) -> Result<()> {
         let mut programs = HashMap::new();
 
         programs.insert(
@@ -398,16 +462,118 @@ mod sea_amm {
         };
 
         let pool = dot::program::Pool::load(&mut ctx.accounts.pool, &programs_map);
-        let token_mint_a = SeahorseAccount {
-            account: &ctx.accounts.token_mint_a,
+        let user_source = SeahorseAccount {
+            account: &ctx.accounts.user_source,
             programs: &programs_map,
         };
 
-        let token_mint_b = SeahorseAccount {
-            account: &ctx.accounts.token_mint_b,
+        let pool_source_vault = SeahorseAccount {
+            account: &ctx.accounts.pool_source_vault,
+            programs: &programs_map,
+        };
+
+        let pool_destination_vault = SeahorseAccount {
+            account: &ctx.accounts.pool_destination_vault,
+            programs: &programs_map,
+        };
+
+        let lp_token_mint = SeahorseAccount {
+            account: &ctx.accounts.lp_token_mint,
+            programs: &programs_map,
+        };
+
+        let user_lp_token_account = SeahorseAccount {
+            account: &ctx.accounts.user_lp_token_account,
+            programs: &programs_map,
+        };
+
+        add_liquidity_single_handler(
+            user.clone(),
+            pool.clone(),
+            user_source.clone(),
+            pool_source_vault.clone(),
+            pool_destination_vault.clone(),
+            lp_token_mint.clone(),
+            user_lp_token_account.clone(),
+            token_in_amount,
+            minimum_lp_out,
+        )?;
+
+        dot::program::Pool::store(pool);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct ClosePosition<'info> {
+        #[account(mut)]
+        pub owner: Signer<'info>,
+        # [account (mut , close = owner , has_one = owner @ SeaAmmError :: Unauthorized)]
+        pub position: Box<Account<'info, dot::program::Position>>,
+    }
+
+    pub fn close_position(ctx: Context<ClosePosition>) -> Result<()> {
+        let mut programs = HashMap::new();
+        let programs_map = ProgramsMap(programs);
+        let owner = SeahorseSigner {
+            account: &ctx.accounts.owner,
+            programs: &programs_map,
+        };
+
+        let position = dot::program::Position::load(&mut ctx.accounts.position, &programs_map);
+
+        close_position_handler(owner.clone(), position.clone())?;
+
+        dot::program::Position::store(position);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct CollectFees<'info> {
+        pub owner: Signer<'info>,
+        #[account(mut)]
+        pub concentrated_pool: Box<Account<'info, dot::program::ConcentratedPool>>,
+        # [account (mut , has_one = owner @ SeaAmmError :: Unauthorized , has_one = concentrated_pool @ SeaAmmError :: InvalidPool)]
+        pub position: Box<Account<'info, dot::program::Position>>,
+        # [account (mut , has_one = concentrated_pool @ SeaAmmError :: InvalidTickArray)]
+        pub tick_array_lower: Box<Account<'info, dot::program::TickArray>>,
+        # [account (mut , has_one = concentrated_pool @ SeaAmmError :: InvalidTickArray)]
+        pub tick_array_upper: Box<Account<'info, dot::program::TickArray>>,
+        # [account (mut , token :: mint = concentrated_pool . token_mint_a , token :: authority = owner)]
+        pub user_token_account_a: Box<Account<'info, TokenAccount>>,
+        # [account (mut , token :: mint = concentrated_pool . token_mint_b , token :: authority = owner)]
+        pub user_token_account_b: Box<Account<'info, TokenAccount>>,
+        # [account (mut , address = concentrated_pool . token_vault_a @ SeaAmmError :: InvalidVault)]
+        pub pool_token_vault_a: Box<Account<'info, TokenAccount>>,
+        # [account (mut , address = concentrated_pool . token_vault_b @ SeaAmmError :: InvalidVault)]
+        pub pool_token_vault_b: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    pub fn collect_fees(ctx: Context<CollectFees>) -> Result<()> {
+        let mut programs = HashMap::new();
+
+        programs.insert(
+            "token_program",
+            ctx.accounts.token_program.to_account_info(),
+        );
+
+        let programs_map = ProgramsMap(programs);
+        let owner = SeahorseSigner {
+            account: &ctx.accounts.owner,
             programs: &programs_map,
         };
 
+        let concentrated_pool = dot::program::ConcentratedPool::load(
+            &mut ctx.accounts.concentrated_pool,
+            &programs_map,
+        );
+        let position = dot::program::Position::load(&mut ctx.accounts.position, &programs_map);
+        let tick_array_lower =
+            dot::program::TickArray::load(&mut ctx.accounts.tick_array_lower, &programs_map);
+        let tick_array_upper =
+            dot::program::TickArray::load(&mut ctx.accounts.tick_array_upper, &programs_map);
         let user_token_account_a = SeahorseAccount {
             account: &ctx.accounts.user_token_account_a,
             programs: &programs_map,
@@ -428,79 +594,150 @@ mod sea_amm {
             programs: &programs_map,
         };
 
-        let lp_token_mint = SeahorseAccount {
-            account: &ctx.accounts.lp_token_mint,
+        collect_fees_handler(
+            owner.clone(),
+            concentrated_pool.clone(),
+            position.clone(),
+            tick_array_lower.clone(),
+            tick_array_upper.clone(),
+            user_token_account_a.clone(),
+            user_token_account_b.clone(),
+            pool_token_vault_a.clone(),
+            pool_token_vault_b.clone(),
+        )?;
+
+        dot::program::ConcentratedPool::store(concentrated_pool);
+
+        dot::program::Position::store(position);
+
+        dot::program::TickArray::store(tick_array_lower);
+
+        dot::program::TickArray::store(tick_array_upper);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct CollectProtocolFees<'info> {
+        pub authority: Signer<'info>,
+        # [account (mut , has_one = authority @ SeaAmmError :: Unauthorized)]
+        pub pool: Box<Account<'info, dot::program::Pool>>,
+        # [account (mut , address = pool . token_vault_a @ SeaAmmError :: InvalidVault)]
+        pub pool_token_vault_a: Box<Account<'info, TokenAccount>>,
+        # [account (mut , address = pool . token_vault_b @ SeaAmmError :: InvalidVault)]
+        pub pool_token_vault_b: Box<Account<'info, TokenAccount>>,
+        # [account (mut , token :: mint = pool . token_mint_a)]
+        pub authority_token_account_a: Box<Account<'info, TokenAccount>>,
+        # [account (mut , token :: mint = pool . token_mint_b)]
+        pub authority_token_account_b: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    pub fn collect_protocol_fees(ctx: Context<CollectProtocolFees>) -> Result<()> {
+        let mut programs = HashMap::new();
+
+        programs.insert(
+            "token_program",
+            ctx.accounts.token_program.to_account_info(),
+        );
+
+        let programs_map = ProgramsMap(programs);
+        let authority = SeahorseSigner {
+            account: &ctx.accounts.authority,
             programs: &programs_map,
         };
 
-        let user_lp_token_account = SeahorseAccount {
-            account: &ctx.accounts.user_lp_token_account,
+        let pool = dot::program::Pool::load(&mut ctx.accounts.pool, &programs_map);
+        let pool_token_vault_a = SeahorseAccount {
+            account: &ctx.accounts.pool_token_vault_a,
             programs: &programs_map,
         };
 
-        remove_liquidity_handler(
-            user.clone(),
+        let pool_token_vault_b = SeahorseAccount {
+            account: &ctx.accounts.pool_token_vault_b,
+            programs: &programs_map,
+        };
+
+        let authority_token_account_a = SeahorseAccount {
+            account: &ctx.accounts.authority_token_account_a,
+            programs: &programs_map,
+        };
+
+        let authority_token_account_b = SeahorseAccount {
+            account: &ctx.accounts.authority_token_account_b,
+            programs: &programs_map,
+        };
+
+        collect_protocol_fees_handler(
+            authority.clone(),
             pool.clone(),
-            token_mint_a.clone(),
-            token_mint_b.clone(),
-            user_token_account_a.clone(),
-            user_token_account_b.clone(),
             pool_token_vault_a.clone(),
             pool_token_vault_b.clone(),
-            lp_token_mint.clone(),
-            user_lp_token_account.clone(),
-        );
+            authority_token_account_a.clone(),
+            authority_token_account_b.clone(),
+        )?;
 
         dot::program::Pool::store(pool);
 
-        return Ok(());
+        Ok(())
     }
 
     #[derive(Accounts)]
-    # [instruction (token_in_amount : u64)]
-    pub struct Swap<'info> {
-        #[account(mut)]
-        pub user: Signer<'info>,
-        #[account(mut)]
-        pub pool: Box<Account<'info, dot::program::Pool>>,
-        #[account(mut)]
-        pub token_in_mint: Box<Account<'info, Mint>>,
-        #[account(mut)]
-        pub token_in_vault: Box<Account<'info, TokenAccount>>,
+    # [instruction (fee_numerator : u64 , fee_denominator : u64)]
+    pub struct CreateConcentratedPool<'info> {
         #[account(mut)]
+        pub authority: Signer<'info>,
+        # [account (seeds = ["config" . as_bytes ()] , bump = config . bump)]
+        pub config: Box<Account<'info, dot::program::Config>>,
+        /// CHECK: receives the pool creation fee, checked against the config
+        # [account (mut , address = config . admin @ SeaAmmError :: InvalidAdmin)]
+        pub admin: UncheckedAccount<'info>,
+        # [account (init , space = std :: mem :: size_of :: < dot :: program :: ConcentratedPool > () + 8 , payer = authority , seeds = ["concentrated-pool" . as_bytes () , token_mint_a . key () . as_ref () , token_mint_b . key () . as_ref () , fee_numerator . to_le_bytes () . as_ref () , fee_denominator . to_le_bytes () . as_ref ()] , bump)]
+        pub concentrated_pool: Box<Account<'info, dot::program::ConcentratedPool>>,
         pub token_mint_a: Box<Account<'info, Mint>>,
-        #[account(mut)]
         pub token_mint_b: Box<Account<'info, Mint>>,
-        #[account(mut)]
+        # [account (init , payer = authority , seeds = ["token-vault-a" . as_bytes () , concentrated_pool . key () . as_ref ()] , bump , token :: mint = token_mint_a , token :: authority = concentrated_pool)]
         pub token_vault_a: Box<Account<'info, TokenAccount>>,
-        #[account(mut)]
+        # [account (init , payer = authority , seeds = ["token-vault-b" . as_bytes () , concentrated_pool . key () . as_ref ()] , bump , token :: mint = token_mint_b , token :: authority = concentrated_pool)]
         pub token_vault_b: Box<Account<'info, TokenAccount>>,
+        pub rent: Sysvar<'info, Rent>,
+        pub system_program: Program<'info, System>,
         pub token_program: Program<'info, Token>,
     }
 
-    pub fn swap(ctx: Context<Swap>, token_in_amount: u64) -> Result<()> {
+    pub fn create_concentrated_pool(
+        ctx: Context<CreateConcentratedPool>,
+        fee_numerator: u64,
+        fee_denominator: u64,
+        tick_spacing: u16,
+        initial_sqrt_price: u128,
+    ) -> Result<()> {
         let mut programs = HashMap::new();
 
+        programs.insert(
+            "system_program",
+            ctx.accounts.system_program.to_account_info(),
+        );
+
         programs.insert(
             "token_program",
             ctx.accounts.token_program.to_account_info(),
         );
 
         let programs_map = ProgramsMap(programs);
-        let user = SeahorseSigner {
-            account: &ctx.accounts.user,
-            programs: &programs_map,
-        };
-
-        let pool = dot::program::Pool::load(&mut ctx.accounts.pool, &programs_map);
-        let token_in_mint = SeahorseAccount {
-            account: &ctx.accounts.token_in_mint,
+        let authority = SeahorseSigner {
+            account: &ctx.accounts.authority,
             programs: &programs_map,
         };
 
-        let token_in_vault = SeahorseAccount {
-            account: &ctx.accounts.token_in_vault,
-            programs: &programs_map,
+        let config = dot::program::Config::load(&mut ctx.accounts.config, &programs_map);
+        let admin = &ctx.accounts.admin;
+        let concentrated_pool = Empty {
+            account: dot::program::ConcentratedPool::load(
+                &mut ctx.accounts.concentrated_pool,
+                &programs_map,
+            ),
+            bump: ctx.bumps.get("concentrated_pool").copied(),
         };
 
         let token_mint_a = SeahorseAccount {
@@ -513,30 +750,1363 @@ mod sea_amm {
             programs: &programs_map,
         };
 
-        let token_vault_a = SeahorseAccount {
-            account: &ctx.accounts.token_vault_a,
-            programs: &programs_map,
+        let token_vault_a = Empty {
+            account: SeahorseAccount {
+                account: &ctx.accounts.token_vault_a,
+                programs: &programs_map,
+            },
+            bump: ctx.bumps.get("token_vault_a").copied(),
         };
 
-        let token_vault_b = SeahorseAccount {
-            account: &ctx.accounts.token_vault_b,
-            programs: &programs_map,
+        let token_vault_b = Empty {
+            account: SeahorseAccount {
+                account: &ctx.accounts.token_vault_b,
+                programs: &programs_map,
+            },
+            bump: ctx.bumps.get("token_vault_b").copied(),
+        };
+
+        create_concentrated_pool_handler(
+            authority.clone(),
+            config.clone(),
+            admin,
+            concentrated_pool.clone(),
+            token_mint_a.clone(),
+            token_mint_b.clone(),
+            token_vault_a.clone(),
+            token_vault_b.clone(),
+            fee_numerator,
+            fee_denominator,
+            tick_spacing,
+            initial_sqrt_price,
+        )?;
+
+        dot::program::ConcentratedPool::store(concentrated_pool.account);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    # [instruction (fee_numerator : u64 , fee_denominator : u64)]
+    pub struct CreateMultiPool<'info> {
+        #[account(mut)]
+        pub authority: Signer<'info>,
+        # [account (seeds = ["config" . as_bytes ()] , bump = config . bump)]
+        pub config: Box<Account<'info, dot::program::Config>>,
+        /// CHECK: receives the pool creation fee, checked against the config
+        # [account (mut , address = config . admin @ SeaAmmError :: InvalidAdmin)]
+        pub admin: UncheckedAccount<'info>,
+        # [account (init , space = std :: mem :: size_of :: < dot :: program :: MultiPool > () + 2 * dot :: program :: MAX_MULTI_POOL_TOKENS * std :: mem :: size_of :: < Pubkey > () + 8 , payer = authority)]
+        pub multi_pool: Box<Account<'info, dot::program::MultiPool>>,
+        /// CHECK: signs for the pool's vaults and lp token mint
+        # [account (seeds = ["multi-pool-authority" . as_bytes () , multi_pool . key () . as_ref ()] , bump)]
+        pub multi_pool_authority: UncheckedAccount<'info>,
+        # [account (init , payer = authority , seeds = ["lp-token-mint" . as_bytes () , multi_pool . key () . as_ref ()] , bump , mint :: decimals = 6 , mint :: authority = multi_pool_authority)]
+        pub lp_token_mint: Box<Account<'info, Mint>>,
+        # [account (init , payer = authority , seeds = ["locked-lp-token-account" . as_bytes () , lp_token_mint . key () . as_ref ()] , bump , token :: mint = lp_token_mint , token :: authority = multi_pool_authority)]
+        pub locked_lp_token_account: Box<Account<'info, TokenAccount>>,
+        pub rent: Sysvar<'info, Rent>,
+        pub system_program: Program<'info, System>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    /// Remaining accounts: the mint and the vault of each pool token in turn,
+    /// with the mints in ascending order.
+    pub fn create_multi_pool<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreateMultiPool<'info>>,
+        fee_numerator: u64,
+        fee_denominator: u64,
+    ) -> Result<()> {
+        let mut programs = HashMap::new();
+
+        programs.insert(
+            "system_program",
+            ctx.accounts.system_program.to_account_info(),
+        );
+
+        programs.insert(
+            "token_program",
+            ctx.accounts.token_program.to_account_info(),
+        );
+
+        let programs_map = ProgramsMap(programs);
+        let token_mints =
+            load_remaining_accounts::<Mint>(ctx.remaining_accounts.iter().step_by(2))?;
+        let token_vaults = load_remaining_accounts::<TokenAccount>(
+            ctx.remaining_accounts.iter().skip(1).step_by(2),
+        )?;
+        let authority = SeahorseSigner {
+            account: &ctx.accounts.authority,
+            programs: &programs_map,
+        };
+
+        let config = dot::program::Config::load(&mut ctx.accounts.config, &programs_map);
+        let admin = &ctx.accounts.admin;
+        let multi_pool = Empty {
+            account: dot::program::MultiPool::load(&mut ctx.accounts.multi_pool, &programs_map),
+            bump: ctx.bumps.get("multi_pool").copied(),
+        };
+
+        let multi_pool_authority = &ctx.accounts.multi_pool_authority;
+        let multi_pool_authority_bump = *ctx.bumps.get("multi_pool_authority").unwrap();
+        let lp_token_mint = Empty {
+            account: SeahorseAccount {
+                account: &ctx.accounts.lp_token_mint,
+                programs: &programs_map,
+            },
+            bump: ctx.bumps.get("lp_token_mint").copied(),
+        };
+
+        let locked_lp_token_account = Empty {
+            account: SeahorseAccount {
+                account: &ctx.accounts.locked_lp_token_account,
+                programs: &programs_map,
+            },
+            bump: ctx.bumps.get("locked_lp_token_account").copied(),
+        };
+
+        let token_mints = token_mints
+            .iter()
+            .map(|account| SeahorseAccount {
+                account,
+                programs: &programs_map,
+            })
+            .collect();
+        let token_vaults = token_vaults
+            .iter()
+            .map(|account| SeahorseAccount {
+                account,
+                programs: &programs_map,
+            })
+            .collect();
+
+        create_multi_pool_handler(
+            authority.clone(),
+            config.clone(),
+            admin,
+            multi_pool.clone(),
+            multi_pool_authority,
+            multi_pool_authority_bump,
+            lp_token_mint.clone(),
+            locked_lp_token_account.clone(),
+            token_mints,
+            token_vaults,
+            fee_numerator,
+            fee_denominator,
+        )?;
+
+        dot::program::MultiPool::store(multi_pool.account);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    # [instruction (fee_numerator : u64 , fee_denominator : u64)]
+    pub struct CreatePool<'info> {
+        #[account(mut)]
+        pub authority: Signer<'info>,
+        # [account (seeds = ["config" . as_bytes ()] , bump = config . bump)]
+        pub config: Box<Account<'info, dot::program::Config>>,
+        /// CHECK: receives the pool creation fee, checked against the config
+        # [account (mut , address = config . admin @ SeaAmmError :: InvalidAdmin)]
+        pub admin: UncheckedAccount<'info>,
+        # [account (init , space = std :: mem :: size_of :: < dot :: program :: Pool > () + 8 , payer = authority , seeds = ["pool" . as_bytes () , token_mint_a . key () . as_ref () , token_mint_b . key () . as_ref () , fee_numerator . to_le_bytes () . as_ref () , fee_denominator . to_le_bytes () . as_ref ()] , bump)]
+        pub pool: Box<Account<'info, dot::program::Pool>>,
+        #[account(mut)]
+        pub token_mint_a: Box<Account<'info, Mint>>,
+        #[account(mut)]
+        pub token_mint_b: Box<Account<'info, Mint>>,
+        # [account (init , payer = authority , seeds = ["token-vault-a" . as_bytes () , pool . key () . as_ref ()] , bump , token :: mint = token_mint_a , token :: authority = pool)]
+        pub token_vault_a: Box<Account<'info, TokenAccount>>,
+        # [account (init , payer = authority , seeds = ["token-vault-b" . as_bytes () , pool . key () . as_ref ()] , bump , token :: mint = token_mint_b , token :: authority = pool)]
+        pub token_vault_b: Box<Account<'info, TokenAccount>>,
+        # [account (init , payer = authority , seeds = ["lp-token-mint" . as_bytes () , pool . key () . as_ref ()] , bump , mint :: decimals = 6 , mint :: authority = pool)]
+        pub lp_token_mint: Box<Account<'info, Mint>>,
+        # [account (init , payer = authority , seeds = ["locked-lp-token-account" . as_bytes () , lp_token_mint . key () . as_ref ()] , bump , token :: mint = lp_token_mint , token :: authority = pool)]
+        pub locked_lp_token_account: Box<Account<'info, TokenAccount>>,
+        pub rent: Sysvar<'info, Rent>,
+        pub system_program: Program<'info, System>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    pub fn create_pool(
+        ctx: Context<CreatePool>,
+        fee_numerator: u64,
+        fee_denominator: u64,
+        curve_type: dot::program::CurveType,
+        amp: u64,
+        weight_a: u64,
+        weight_b: u64,
+    ) -> Result<()> {
+        let mut programs = HashMap::new();
+
+        programs.insert(
+            "system_program",
+            ctx.accounts.system_program.to_account_info(),
+        );
+
+        programs.insert(
+            "token_program",
+            ctx.accounts.token_program.to_account_info(),
+        );
+
+        let programs_map = ProgramsMap(programs);
+        let authority = SeahorseSigner {
+            account: &ctx.accounts.authority,
+            programs: &programs_map,
+        };
+
+        let config = dot::program::Config::load(&mut ctx.accounts.config, &programs_map);
+        let admin = &ctx.accounts.admin;
+        let pool = Empty {
+            account: dot::program::Pool::load(&mut ctx.accounts.pool, &programs_map),
+            bump: ctx.bumps.get("pool").copied(),
+        };
+
+        let token_mint_a = SeahorseAccount {
+            account: &ctx.accounts.token_mint_a,
+            programs: &programs_map,
+        };
+
+        let token_mint_b = SeahorseAccount {
+            account: &ctx.accounts.token_mint_b,
+            programs: &programs_map,
+        };
+
+        let token_vault_a = Empty {
+            account: SeahorseAccount {
+                account: &ctx.accounts.token_vault_a,
+                programs: &programs_map,
+            },
+            bump: ctx.bumps.get("token_vault_a").copied(),
+        };
+
+        let token_vault_b = Empty {
+            account: SeahorseAccount {
+                account: &ctx.accounts.token_vault_b,
+                programs: &programs_map,
+            },
+            bump: ctx.bumps.get("token_vault_b").copied(),
+        };
+
+        let lp_token_mint = Empty {
+            account: SeahorseAccount {
+                account: &ctx.accounts.lp_token_mint,
+                programs: &programs_map,
+            },
+            bump: ctx.bumps.get("lp_token_mint").copied(),
+        };
+
+        let locked_lp_token_account = Empty {
+            account: SeahorseAccount {
+                account: &ctx.accounts.locked_lp_token_account,
+                programs: &programs_map,
+            },
+            bump: ctx.bumps.get("locked_lp_token_account").copied(),
+        };
+
+        create_pool_handler(
+            authority.clone(),
+            config.clone(),
+            admin,
+            pool.clone(),
+            token_mint_a.clone(),
+            token_mint_b.clone(),
+            token_vault_a.clone(),
+            token_vault_b.clone(),
+            lp_token_mint.clone(),
+            locked_lp_token_account.clone(),
+            fee_numerator,
+            fee_denominator,
+            curve_type,
+            amp,
+            weight_a,
+            weight_b,
+        )?;
+
+        dot::program::Pool::store(pool.account);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    # [instruction (liquidity_amount : u128 , minimum_amount_a : u64 , minimum_amount_b : u64)]
+    pub struct DecreaseLiquidity<'info> {
+        pub owner: Signer<'info>,
+        #[account(mut)]
+        pub concentrated_pool: Box<Account<'info, dot::program::ConcentratedPool>>,
+        # [account (mut , has_one = owner @ SeaAmmError :: Unauthorized , has_one = concentrated_pool @ SeaAmmError :: InvalidPool)]
+        pub position: Box<Account<'info, dot::program::Position>>,
+        # [account (mut , has_one = concentrated_pool @ SeaAmmError :: InvalidTickArray)]
+        pub tick_array_lower: Box<Account<'info, dot::program::TickArray>>,
+        # [account (mut , has_one = concentrated_pool @ SeaAmmError :: InvalidTickArray)]
+        pub tick_array_upper: Box<Account<'info, dot::program::TickArray>>,
+        # [account (mut , token :: mint = concentrated_pool . token_mint_a , token :: authority = owner)]
+        pub user_token_account_a: Box<Account<'info, TokenAccount>>,
+        # [account (mut , token :: mint = concentrated_pool . token_mint_b , token :: authority = owner)]
+        pub user_token_account_b: Box<Account<'info, TokenAccount>>,
+        # [account (mut , address = concentrated_pool . token_vault_a @ SeaAmmError :: InvalidVault)]
+        pub pool_token_vault_a: Box<Account<'info, TokenAccount>>,
+        # [account (mut , address = concentrated_pool . token_vault_b @ SeaAmmError :: InvalidVault)]
+        pub pool_token_vault_b: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    pub fn decrease_liquidity(
+        ctx: Context<DecreaseLiquidity>,
+        liquidity_amount: u128,
+        minimum_amount_a: u64,
+        minimum_amount_b: u64,
+    ) -> Result<()> {
+        let mut programs = HashMap::new();
+
+        programs.insert(
+            "token_program",
+            ctx.accounts.token_program.to_account_info(),
+        );
+
+        let programs_map = ProgramsMap(programs);
+        let owner = SeahorseSigner {
+            account: &ctx.accounts.owner,
+            programs: &programs_map,
+        };
+
+        let concentrated_pool = dot::program::ConcentratedPool::load(
+            &mut ctx.accounts.concentrated_pool,
+            &programs_map,
+        );
+        let position = dot::program::Position::load(&mut ctx.accounts.position, &programs_map);
+        let tick_array_lower =
+            dot::program::TickArray::load(&mut ctx.accounts.tick_array_lower, &programs_map);
+        let tick_array_upper =
+            dot::program::TickArray::load(&mut ctx.accounts.tick_array_upper, &programs_map);
+        let user_token_account_a = SeahorseAccount {
+            account: &ctx.accounts.user_token_account_a,
+            programs: &programs_map,
+        };
+
+        let user_token_account_b = SeahorseAccount {
+            account: &ctx.accounts.user_token_account_b,
+            programs: &programs_map,
+        };
+
+        let pool_token_vault_a = SeahorseAccount {
+            account: &ctx.accounts.pool_token_vault_a,
+            programs: &programs_map,
+        };
+
+        let pool_token_vault_b = SeahorseAccount {
+            account: &ctx.accounts.pool_token_vault_b,
+            programs: &programs_map,
+        };
+
+        decrease_liquidity_handler(
+            owner.clone(),
+            concentrated_pool.clone(),
+            position.clone(),
+            tick_array_lower.clone(),
+            tick_array_upper.clone(),
+            user_token_account_a.clone(),
+            user_token_account_b.clone(),
+            pool_token_vault_a.clone(),
+            pool_token_vault_b.clone(),
+            liquidity_amount,
+            minimum_amount_a,
+            minimum_amount_b,
+        )?;
+
+        dot::program::ConcentratedPool::store(concentrated_pool);
+
+        dot::program::Position::store(position);
+
+        dot::program::TickArray::store(tick_array_lower);
+
+        dot::program::TickArray::store(tick_array_upper);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    # [instruction (liquidity_amount : u128 , max_amount_a : u64 , max_amount_b : u64)]
+    pub struct IncreaseLiquidity<'info> {
+        pub owner: Signer<'info>,
+        #[account(mut)]
+        pub concentrated_pool: Box<Account<'info, dot::program::ConcentratedPool>>,
+        # [account (mut , has_one = owner @ SeaAmmError :: Unauthorized , has_one = concentrated_pool @ SeaAmmError :: InvalidPool)]
+        pub position: Box<Account<'info, dot::program::Position>>,
+        # [account (mut , has_one = concentrated_pool @ SeaAmmError :: InvalidTickArray)]
+        pub tick_array_lower: Box<Account<'info, dot::program::TickArray>>,
+        # [account (mut , has_one = concentrated_pool @ SeaAmmError :: InvalidTickArray)]
+        pub tick_array_upper: Box<Account<'info, dot::program::TickArray>>,
+        # [account (mut , token :: mint = concentrated_pool . token_mint_a , token :: authority = owner)]
+        pub user_token_account_a: Box<Account<'info, TokenAccount>>,
+        # [account (mut , token :: mint = concentrated_pool . token_mint_b , token :: authority = owner)]
+        pub user_token_account_b: Box<Account<'info, TokenAccount>>,
+        # [account (mut , address = concentrated_pool . token_vault_a @ SeaAmmError :: InvalidVault)]
+        pub pool_token_vault_a: Box<Account<'info, TokenAccount>>,
+        # [account (mut , address = concentrated_pool . token_vault_b @ SeaAmmError :: InvalidVault)]
+        pub pool_token_vault_b: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    pub fn increase_liquidity(
+        ctx: Context<IncreaseLiquidity>,
+        liquidity_amount: u128,
+        max_amount_a: u64,
+        max_amount_b: u64,
+    ) -> Result<()> {
+        let mut programs = HashMap::new();
+
+        programs.insert(
+            "token_program",
+            ctx.accounts.token_program.to_account_info(),
+        );
+
+        let programs_map = ProgramsMap(programs);
+        let owner = SeahorseSigner {
+            account: &ctx.accounts.owner,
+            programs: &programs_map,
+        };
+
+        let concentrated_pool = dot::program::ConcentratedPool::load(
+            &mut ctx.accounts.concentrated_pool,
+            &programs_map,
+        );
+        let position = dot::program::Position::load(&mut ctx.accounts.position, &programs_map);
+        let tick_array_lower =
+            dot::program::TickArray::load(&mut ctx.accounts.tick_array_lower, &programs_map);
+        let tick_array_upper =
+            dot::program::TickArray::load(&mut ctx.accounts.tick_array_upper, &programs_map);
+        let user_token_account_a = SeahorseAccount {
+            account: &ctx.accounts.user_token_account_a,
+            programs: &programs_map,
+        };
+
+        let user_token_account_b = SeahorseAccount {
+            account: &ctx.accounts.user_token_account_b,
+            programs: &programs_map,
+        };
+
+        let pool_token_vault_a = SeahorseAccount {
+            account: &ctx.accounts.pool_token_vault_a,
+            programs: &programs_map,
+        };
+
+        let pool_token_vault_b = SeahorseAccount {
+            account: &ctx.accounts.pool_token_vault_b,
+            programs: &programs_map,
+        };
+
+        increase_liquidity_handler(
+            owner.clone(),
+            concentrated_pool.clone(),
+            position.clone(),
+            tick_array_lower.clone(),
+            tick_array_upper.clone(),
+            user_token_account_a.clone(),
+            user_token_account_b.clone(),
+            pool_token_vault_a.clone(),
+            pool_token_vault_b.clone(),
+            liquidity_amount,
+            max_amount_a,
+            max_amount_b,
+        )?;
+
+        dot::program::ConcentratedPool::store(concentrated_pool);
+
+        dot::program::Position::store(position);
+
+        dot::program::TickArray::store(tick_array_lower);
+
+        dot::program::TickArray::store(tick_array_upper);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    # [instruction (fee_tiers : Vec < dot :: program :: FeeTier > , protocol_fee_numerator : u64 , protocol_fee_denominator : u64 , pool_creation_fee : u64)]
+    pub struct InitializeConfig<'info> {
+        #[account(mut)]
+        pub admin: Signer<'info>,
+        # [account (init , space = std :: mem :: size_of :: < dot :: program :: Config > () + dot :: program :: MAX_FEE_TIERS * std :: mem :: size_of :: < dot :: program :: FeeTier > () + 8 , payer = admin , seeds = ["config" . as_bytes ()] , bump)]
+        pub config: Box<Account<'info, dot::program::Config>>,
+        pub upgrade_authority: Signer<'info>,
+        # [account (constraint = program . programdata_address () ? == Some (program_data . key ()) @ SeaAmmError :: Unauthorized)]
+        pub program: Program<'info, crate::program::SeaAmm>,
+        # [account (constraint = program_data . upgrade_authority_address == Some (upgrade_authority . key ()) @ SeaAmmError :: Unauthorized)]
+        pub program_data: Account<'info, ProgramData>,
+        pub rent: Sysvar<'info, Rent>,
+        pub system_program: Program<'info, System>,
+    }
+
+    pub fn initialize_config(
+        ctx: Context<InitializeConfig>,
+        fee_tiers: Vec<dot::program::FeeTier>,
+        protocol_fee_numerator: u64,
+        protocol_fee_denominator: u64,
+        pool_creation_fee: u64,
+    ) -> Result<()> {
+        let mut programs = HashMap::new();
+
+        programs.insert(
+            "system_program",
+            ctx.accounts.system_program.to_account_info(),
+        );
+
+        let programs_map = ProgramsMap(programs);
+        let admin = SeahorseSigner {
+            account: &ctx.accounts.admin,
+            programs: &programs_map,
+        };
+
+        let config = Empty {
+            account: dot::program::Config::load(&mut ctx.accounts.config, &programs_map),
+            bump: ctx.bumps.get("config").copied(),
+        };
+
+        initialize_config_handler(
+            admin.clone(),
+            config.clone(),
+            fee_tiers,
+            protocol_fee_numerator,
+            protocol_fee_denominator,
+            pool_creation_fee,
+        )?;
+
+        dot::program::Config::store(config.account);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    # [instruction (start_tick_index : i32)]
+    pub struct InitializeTickArray<'info> {
+        #[account(mut)]
+        pub payer: Signer<'info>,
+        pub concentrated_pool: Box<Account<'info, dot::program::ConcentratedPool>>,
+        # [account (init , space = std :: mem :: size_of :: < dot :: program :: TickArray > () + dot :: program :: TICK_ARRAY_SIZE * std :: mem :: size_of :: < dot :: program :: Tick > () + 8 , payer = payer , seeds = ["tick-array" . as_bytes () , concentrated_pool . key () . as_ref () , start_tick_index . to_le_bytes () . as_ref ()] , bump)]
+        pub tick_array: Box<Account<'info, dot::program::TickArray>>,
+        pub rent: Sysvar<'info, Rent>,
+        pub system_program: Program<'info, System>,
+    }
+
+    pub fn initialize_tick_array(
+        ctx: Context<InitializeTickArray>,
+        start_tick_index: i32,
+    ) -> Result<()> {
+        let mut programs = HashMap::new();
+
+        programs.insert(
+            "system_program",
+            ctx.accounts.system_program.to_account_info(),
+        );
+
+        let programs_map = ProgramsMap(programs);
+        let payer = SeahorseSigner {
+            account: &ctx.accounts.payer,
+            programs: &programs_map,
+        };
+
+        let concentrated_pool = dot::program::ConcentratedPool::load(
+            &mut ctx.accounts.concentrated_pool,
+            &programs_map,
+        );
+        let tick_array = Empty {
+            account: dot::program::TickArray::load(&mut ctx.accounts.tick_array, &programs_map),
+            bump: ctx.bumps.get("tick_array").copied(),
+        };
+
+        initialize_tick_array_handler(
+            payer.clone(),
+            concentrated_pool.clone(),
+            tick_array.clone(),
+            start_tick_index,
+        )?;
+
+        dot::program::TickArray::store(tick_array.account);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    # [instruction (tick_lower : i32 , tick_upper : i32)]
+    pub struct OpenPosition<'info> {
+        #[account(mut)]
+        pub owner: Signer<'info>,
+        pub concentrated_pool: Box<Account<'info, dot::program::ConcentratedPool>>,
+        # [account (init , space = std :: mem :: size_of :: < dot :: program :: Position > () + 8 , payer = owner , seeds = ["position" . as_bytes () , concentrated_pool . key () . as_ref () , owner . key () . as_ref () , tick_lower . to_le_bytes () . as_ref () , tick_upper . to_le_bytes () . as_ref ()] , bump)]
+        pub position: Box<Account<'info, dot::program::Position>>,
+        pub rent: Sysvar<'info, Rent>,
+        pub system_program: Program<'info, System>,
+    }
+
+    pub fn open_position(
+        ctx: Context<OpenPosition>,
+        tick_lower: i32,
+        tick_upper: i32,
+    ) -> Result<()> {
+        let mut programs = HashMap::new();
+
+        programs.insert(
+            "system_program",
+            ctx.accounts.system_program.to_account_info(),
+        );
+
+        let programs_map = ProgramsMap(programs);
+        let owner = SeahorseSigner {
+            account: &ctx.accounts.owner,
+            programs: &programs_map,
+        };
+
+        let concentrated_pool = dot::program::ConcentratedPool::load(
+            &mut ctx.accounts.concentrated_pool,
+            &programs_map,
+        );
+        let position = Empty {
+            account: dot::program::Position::load(&mut ctx.accounts.position, &programs_map),
+            bump: ctx.bumps.get("position").copied(),
+        };
+
+        open_position_handler(
+            owner.clone(),
+            concentrated_pool.clone(),
+            position.clone(),
+            tick_lower,
+            tick_upper,
+        )?;
+
+        dot::program::Position::store(position.account);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    # [instruction (new_authority : Pubkey)]
+    pub struct ProposeAuthority<'info> {
+        pub authority: Signer<'info>,
+        # [account (mut , has_one = authority @ SeaAmmError :: Unauthorized)]
+        pub pool: Box<Account<'info, dot::program::Pool>>,
+    }
+
+    pub fn propose_authority(ctx: Context<ProposeAuthority>, new_authority: Pubkey) -> Result<()> {
+        let mut programs = HashMap::new();
+        let programs_map = ProgramsMap(programs);
+        let authority = SeahorseSigner {
+            account: &ctx.accounts.authority,
+            programs: &programs_map,
+        };
+
+        let pool = dot::program::Pool::load(&mut ctx.accounts.pool, &programs_map);
+
+        propose_authority_handler(authority.clone(), pool.clone(), new_authority)?;
+
+        dot::program::Pool::store(pool);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    # [instruction (target_amp : u64 , ramp_end_timestamp : i64)]
+    pub struct RampAmp<'info> {
+        pub authority: Signer<'info>,
+        # [account (mut , has_one = authority @ SeaAmmError :: Unauthorized)]
+        pub pool: Box<Account<'info, dot::program::Pool>>,
+    }
+
+    pub fn ramp_amp(ctx: Context<RampAmp>, target_amp: u64, ramp_end_timestamp: i64) -> Result<()> {
+        let mut programs = HashMap::new();
+        let programs_map = ProgramsMap(programs);
+        let authority = SeahorseSigner {
+            account: &ctx.accounts.authority,
+            programs: &programs_map,
+        };
+
+        let pool = dot::program::Pool::load(&mut ctx.accounts.pool, &programs_map);
+
+        ramp_amp_handler(
+            authority.clone(),
+            pool.clone(),
+            target_amp,
+            ramp_end_timestamp,
+        )?;
+
+        dot::program::Pool::store(pool);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    # [instruction (lp_amount : u64 , minimum_amount_a : u64 , minimum_amount_b : u64)]
+    pub struct RemoveLiquidity<'info> {
+        #[account(mut)]
+        pub user: Signer<'info>,
+        # [account (mut , has_one = token_mint_a @ SeaAmmError :: InvalidPool , has_one = token_mint_b @ SeaAmmError :: InvalidPool , has_one = lp_token_mint @ SeaAmmError :: InvalidLpMint)]
+        pub pool: Box<Account<'info, dot::program::Pool>>,
+        #[account(mut)]
+        pub token_mint_a: Box<Account<'info, Mint>>,
+        #[account(mut)]
+        pub token_mint_b: Box<Account<'info, Mint>>,
+        # [account (mut , token :: mint = token_mint_a , token :: authority = user)]
+        pub user_token_account_a: Box<Account<'info, TokenAccount>>,
+        # [account (mut , token :: mint = token_mint_b , token :: authority = user)]
+        pub user_token_account_b: Box<Account<'info, TokenAccount>>,
+        # [account (mut , address = pool . token_vault_a @ SeaAmmError :: InvalidVault)]
+        pub pool_token_vault_a: Box<Account<'info, TokenAccount>>,
+        # [account (mut , address = pool . token_vault_b @ SeaAmmError :: InvalidVault)]
+        pub pool_token_vault_b: Box<Account<'info, TokenAccount>>,
+        #[account(mut)]
+        pub lp_token_mint: Box<Account<'info, Mint>>,
+        # [account (mut , token :: mint = lp_token_mint , token :: authority = user)]
+        pub user_lp_token_account: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    pub fn remove_liquidity(
+        ctx: Context<RemoveLiquidity>,
+        lp_amount: u64,
+        minimum_amount_a: u64,
+        minimum_amount_b: u64,
+    ) -> Result<()> {
+        let mut programs = HashMap::new();
+
+        programs.insert(
+            "token_program",
+            ctx.accounts.token_program.to_account_info(),
+        );
+
+        let programs_map = ProgramsMap(programs);
+        let user = SeahorseSigner {
+            account: &ctx.accounts.user,
+            programs: &programs_map,
+        };
+
+        let pool = dot::program::Pool::load(&mut ctx.accounts.pool, &programs_map);
+        let token_mint_a = SeahorseAccount {
+            account: &ctx.accounts.token_mint_a,
+            programs: &programs_map,
+        };
+
+        let token_mint_b = SeahorseAccount {
+            account: &ctx.accounts.token_mint_b,
+            programs: &programs_map,
+        };
+
+        let user_token_account_a = SeahorseAccount {
+            account: &ctx.accounts.user_token_account_a,
+            programs: &programs_map,
+        };
+
+        let user_token_account_b = SeahorseAccount {
+            account: &ctx.accounts.user_token_account_b,
+            programs: &programs_map,
+        };
+
+        let pool_token_vault_a = SeahorseAccount {
+            account: &ctx.accounts.pool_token_vault_a,
+            programs: &programs_map,
+        };
+
+        let pool_token_vault_b = SeahorseAccount {
+            account: &ctx.accounts.pool_token_vault_b,
+            programs: &programs_map,
+        };
+
+        let lp_token_mint = SeahorseAccount {
+            account: &ctx.accounts.lp_token_mint,
+            programs: &programs_map,
+        };
+
+        let user_lp_token_account = SeahorseAccount {
+            account: &ctx.accounts.user_lp_token_account,
+            programs: &programs_map,
+        };
+
+        remove_liquidity_handler(
+            user.clone(),
+            pool.clone(),
+            token_mint_a.clone(),
+            token_mint_b.clone(),
+            user_token_account_a.clone(),
+            user_token_account_b.clone(),
+            pool_token_vault_a.clone(),
+            pool_token_vault_b.clone(),
+            lp_token_mint.clone(),
+            user_lp_token_account.clone(),
+            lp_amount,
+            minimum_amount_a,
+            minimum_amount_b,
+        )?;
+
+        dot::program::Pool::store(pool);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    # [instruction (lp_amount : u64 , minimum_amounts : Vec < u64 >)]
+    pub struct RemoveLiquidityMulti<'info> {
+        #[account(mut)]
+        pub user: Signer<'info>,
+        # [account (has_one = lp_token_mint @ SeaAmmError :: InvalidLpMint)]
+        pub multi_pool: Box<Account<'info, dot::program::MultiPool>>,
+        /// CHECK: signs for the pool's vaults and lp token mint
+        # [account (seeds = ["multi-pool-authority" . as_bytes () , multi_pool . key () . as_ref ()] , bump = multi_pool . authority_bump)]
+        pub multi_pool_authority: UncheckedAccount<'info>,
+        #[account(mut)]
+        pub lp_token_mint: Box<Account<'info, Mint>>,
+        # [account (mut , token :: mint = lp_token_mint , token :: authority = user)]
+        pub user_lp_token_account: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    /// Remaining accounts: the user's token account and the pool's vault of
+    /// each pool token in turn, in the pool's order.
+    pub fn remove_liquidity_multi<'info>(
+        ctx: Context<'_, '_, '_, 'info, RemoveLiquidityMulti<'info>>,
+        lp_amount: u64,
+        minimum_amounts: Vec<u64>,
+    ) -> Result<()> {
+        let mut programs = HashMap::new();
+
+        programs.insert(
+            "token_program",
+            ctx.accounts.token_program.to_account_info(),
+        );
+
+        let programs_map = ProgramsMap(programs);
+        let user_token_accounts =
+            load_remaining_accounts::<TokenAccount>(ctx.remaining_accounts.iter().step_by(2))?;
+        let pool_token_vaults = load_remaining_accounts::<TokenAccount>(
+            ctx.remaining_accounts.iter().skip(1).step_by(2),
+        )?;
+        let user = SeahorseSigner {
+            account: &ctx.accounts.user,
+            programs: &programs_map,
+        };
+
+        let multi_pool = dot::program::MultiPool::load(&mut ctx.accounts.multi_pool, &programs_map);
+        let multi_pool_authority = &ctx.accounts.multi_pool_authority;
+        let lp_token_mint = SeahorseAccount {
+            account: &ctx.accounts.lp_token_mint,
+            programs: &programs_map,
+        };
+
+        let user_lp_token_account = SeahorseAccount {
+            account: &ctx.accounts.user_lp_token_account,
+            programs: &programs_map,
+        };
+
+        let user_token_accounts = user_token_accounts
+            .iter()
+            .map(|account| SeahorseAccount {
+                account,
+                programs: &programs_map,
+            })
+            .collect();
+        let pool_token_vaults = pool_token_vaults
+            .iter()
+            .map(|account| SeahorseAccount {
+                account,
+                programs: &programs_map,
+            })
+            .collect();
+
+        remove_liquidity_multi_handler(
+            user.clone(),
+            multi_pool.clone(),
+            multi_pool_authority,
+            lp_token_mint.clone(),
+            user_lp_token_account.clone(),
+            user_token_accounts,
+            pool_token_vaults,
+            lp_amount,
+            minimum_amounts,
+        )?;
+
+        dot::program::MultiPool::store(multi_pool);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct RenounceAuthority<'info> {
+        pub authority: Signer<'info>,
+        # [account (mut , has_one = authority @ SeaAmmError :: Unauthorized)]
+        pub pool: Box<Account<'info, dot::program::Pool>>,
+    }
+
+    pub fn renounce_authority(ctx: Context<RenounceAuthority>) -> Result<()> {
+        let mut programs = HashMap::new();
+        let programs_map = ProgramsMap(programs);
+        let authority = SeahorseSigner {
+            account: &ctx.accounts.authority,
+            programs: &programs_map,
+        };
+
+        let pool = dot::program::Pool::load(&mut ctx.accounts.pool, &programs_map);
+
+        renounce_authority_handler(authority.clone(), pool.clone())?;
+
+        dot::program::Pool::store(pool);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct SetConcentratedPoolStatus<'info> {
+        pub authority: Signer<'info>,
+        # [account (mut , has_one = authority @ SeaAmmError :: Unauthorized)]
+        pub concentrated_pool: Box<Account<'info, dot::program::ConcentratedPool>>,
+    }
+
+    pub fn set_concentrated_pool_status(
+        ctx: Context<SetConcentratedPoolStatus>,
+        status: dot::program::PoolStatus,
+    ) -> Result<()> {
+        let mut programs = HashMap::new();
+        let programs_map = ProgramsMap(programs);
+        let authority = SeahorseSigner {
+            account: &ctx.accounts.authority,
+            programs: &programs_map,
+        };
+
+        let concentrated_pool = dot::program::ConcentratedPool::load(
+            &mut ctx.accounts.concentrated_pool,
+            &programs_map,
+        );
+
+        set_concentrated_pool_status_handler(authority.clone(), concentrated_pool.clone(), status)?;
+
+        dot::program::ConcentratedPool::store(concentrated_pool);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct SetMultiPoolStatus<'info> {
+        pub authority: Signer<'info>,
+        # [account (mut , has_one = authority @ SeaAmmError :: Unauthorized)]
+        pub multi_pool: Box<Account<'info, dot::program::MultiPool>>,
+    }
+
+    pub fn set_multi_pool_status(
+        ctx: Context<SetMultiPoolStatus>,
+        status: dot::program::PoolStatus,
+    ) -> Result<()> {
+        let mut programs = HashMap::new();
+        let programs_map = ProgramsMap(programs);
+        let authority = SeahorseSigner {
+            account: &ctx.accounts.authority,
+            programs: &programs_map,
+        };
+
+        let multi_pool = dot::program::MultiPool::load(&mut ctx.accounts.multi_pool, &programs_map);
+
+        set_multi_pool_status_handler(authority.clone(), multi_pool.clone(), status)?;
+
+        dot::program::MultiPool::store(multi_pool);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct SetPoolStatus<'info> {
+        pub authority: Signer<'info>,
+        # [account (mut , has_one = authority @ SeaAmmError :: Unauthorized)]
+        pub pool: Box<Account<'info, dot::program::Pool>>,
+    }
+
+    pub fn set_pool_status(
+        ctx: Context<SetPoolStatus>,
+        status: dot::program::PoolStatus,
+    ) -> Result<()> {
+        let mut programs = HashMap::new();
+        let programs_map = ProgramsMap(programs);
+        let authority = SeahorseSigner {
+            account: &ctx.accounts.authority,
+            programs: &programs_map,
+        };
+
+        let pool = dot::program::Pool::load(&mut ctx.accounts.pool, &programs_map);
+
+        set_pool_status_handler(authority.clone(), pool.clone(), status)?;
+
+        dot::program::Pool::store(pool);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    pub struct StopRampAmp<'info> {
+        pub authority: Signer<'info>,
+        # [account (mut , has_one = authority @ SeaAmmError :: Unauthorized)]
+        pub pool: Box<Account<'info, dot::program::Pool>>,
+    }
+
+    pub fn stop_ramp_amp(ctx: Context<StopRampAmp>) -> Result<()> {
+        let mut programs = HashMap::new();
+        let programs_map = ProgramsMap(programs);
+        let authority = SeahorseSigner {
+            account: &ctx.accounts.authority,
+            programs: &programs_map,
+        };
+
+        let pool = dot::program::Pool::load(&mut ctx.accounts.pool, &programs_map);
+
+        stop_ramp_amp_handler(authority.clone(), pool.clone())?;
+
+        dot::program::Pool::store(pool);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    # [instruction (token_in_amount : u64 , minimum_amount_out : u64)]
+    pub struct Swap<'info> {
+        #[account(mut)]
+        pub user: Signer<'info>,
+        #[account(mut)]
+        pub pool: Box<Account<'info, dot::program::Pool>>,
+        # [account (mut , token :: mint = pool_source_vault . mint , token :: authority = user)]
+        pub user_source: Box<Account<'info, TokenAccount>>,
+        # [account (mut , token :: mint = pool_destination_vault . mint , token :: authority = user)]
+        pub user_destination: Box<Account<'info, TokenAccount>>,
+        #[account(mut)]
+        pub pool_source_vault: Box<Account<'info, TokenAccount>>,
+        #[account(mut)]
+        pub pool_destination_vault: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    pub fn swap(ctx: Context<Swap>, token_in_amount: u64, minimum_amount_out: u64) -> Result<()> {
+        let mut programs = HashMap::new();
+
+        programs.insert(
+            "token_program",
+            ctx.accounts.token_program.to_account_info(),
+        );
+
+        let programs_map = ProgramsMap(programs);
+        let user = SeahorseSigner {
+            account: &ctx.accounts.user,
+            programs: &programs_map,
+        };
+
+        let pool = dot::program::Pool::load(&mut ctx.accounts.pool, &programs_map);
+        let user_source = SeahorseAccount {
+            account: &ctx.accounts.user_source,
+            programs: &programs_map,
+        };
+
+        let user_destination = SeahorseAccount {
+            account: &ctx.accounts.user_destination,
+            programs: &programs_map,
+        };
+
+        let pool_source_vault = SeahorseAccount {
+            account: &ctx.accounts.pool_source_vault,
+            programs: &programs_map,
+        };
+
+        let pool_destination_vault = SeahorseAccount {
+            account: &ctx.accounts.pool_destination_vault,
+            programs: &programs_map,
         };
 
         swap_handler(
             user.clone(),
             pool.clone(),
-            token_in_mint.clone(),
-            token_in_vault.clone(),
+            user_source.clone(),
+            user_destination.clone(),
+            pool_source_vault.clone(),
+            pool_destination_vault.clone(),
             token_in_amount,
-            token_mint_a.clone(),
-            token_mint_b.clone(),
-            token_vault_a.clone(),
-            token_vault_b.clone(),
+            minimum_amount_out,
+        )?;
+
+        dot::program::Pool::store(pool);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    # [instruction (token_in_amount : u64 , minimum_amount_out : u64)]
+    pub struct SwapConcentrated<'info> {
+        #[account(mut)]
+        pub user: Signer<'info>,
+        #[account(mut)]
+        pub concentrated_pool: Box<Account<'info, dot::program::ConcentratedPool>>,
+        # [account (mut , token :: mint = pool_source_vault . mint , token :: authority = user)]
+        pub user_source: Box<Account<'info, TokenAccount>>,
+        # [account (mut , token :: mint = pool_destination_vault . mint , token :: authority = user)]
+        pub user_destination: Box<Account<'info, TokenAccount>>,
+        #[account(mut)]
+        pub pool_source_vault: Box<Account<'info, TokenAccount>>,
+        #[account(mut)]
+        pub pool_destination_vault: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    pub fn swap_concentrated<'info>(
+        ctx: Context<'_, '_, '_, 'info, SwapConcentrated<'info>>,
+        token_in_amount: u64,
+        minimum_amount_out: u64,
+    ) -> Result<()> {
+        let mut programs = HashMap::new();
+
+        programs.insert(
+            "token_program",
+            ctx.accounts.token_program.to_account_info(),
+        );
+
+        let programs_map = ProgramsMap(programs);
+        let mut tick_array_accounts =
+            load_remaining_accounts::<dot::program::TickArray>(ctx.remaining_accounts.iter())?;
+        let user = SeahorseSigner {
+            account: &ctx.accounts.user,
+            programs: &programs_map,
+        };
+
+        let concentrated_pool = dot::program::ConcentratedPool::load(
+            &mut ctx.accounts.concentrated_pool,
+            &programs_map,
+        );
+        let user_source = SeahorseAccount {
+            account: &ctx.accounts.user_source,
+            programs: &programs_map,
+        };
+
+        let user_destination = SeahorseAccount {
+            account: &ctx.accounts.user_destination,
+            programs: &programs_map,
+        };
+
+        let pool_source_vault = SeahorseAccount {
+            account: &ctx.accounts.pool_source_vault,
+            programs: &programs_map,
+        };
+
+        let pool_destination_vault = SeahorseAccount {
+            account: &ctx.accounts.pool_destination_vault,
+            programs: &programs_map,
+        };
+
+        let tick_arrays: Vec<_> = tick_array_accounts
+            .iter_mut()
+            .map(|account| dot::program::TickArray::load(account, &programs_map))
+            .collect();
+
+        swap_concentrated_handler(
+            user.clone(),
+            concentrated_pool.clone(),
+            user_source.clone(),
+            user_destination.clone(),
+            pool_source_vault.clone(),
+            pool_destination_vault.clone(),
+            tick_arrays.clone(),
+            token_in_amount,
+            minimum_amount_out,
+        )?;
+
+        dot::program::ConcentratedPool::store(concentrated_pool);
+
+        for tick_array in tick_arrays {
+            dot::program::TickArray::store(tick_array);
+        }
+
+        // anchor only writes back the accounts of the instruction
+        for tick_array in tick_array_accounts.iter() {
+            tick_array.exit(&crate::ID)?;
+        }
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    # [instruction (amount_out : u64 , maximum_amount_in : u64)]
+    pub struct SwapExactOut<'info> {
+        #[account(mut)]
+        pub user: Signer<'info>,
+        #[account(mut)]
+        pub pool: Box<Account<'info, dot::program::Pool>>,
+        # [account (mut , token :: mint = pool_source_vault . mint , token :: authority = user)]
+        pub user_source: Box<Account<'info, TokenAccount>>,
+        # [account (mut , token :: mint = pool_destination_vault . mint , token :: authority = user)]
+        pub user_destination: Box<Account<'info, TokenAccount>>,
+        #[account(mut)]
+        pub pool_source_vault: Box<Account<'info, TokenAccount>>,
+        #[account(mut)]
+        pub pool_destination_vault: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    pub fn swap_exact_out(
+        ctx: Context<SwapExactOut>,
+        amount_out: u64,
+        maximum_amount_in: u64,
+    ) -> Result<()> {
+        let mut programs = HashMap::new();
+
+        programs.insert(
+            "token_program",
+            ctx.accounts.token_program.to_account_info(),
         );
 
+        let programs_map = ProgramsMap(programs);
+        let user = SeahorseSigner {
+            account: &ctx.accounts.user,
+            programs: &programs_map,
+        };
+
+        let pool = dot::program::Pool::load(&mut ctx.accounts.pool, &programs_map);
+        let user_source = SeahorseAccount {
+            account: &ctx.accounts.user_source,
+            programs: &programs_map,
+        };
+
+        let user_destination = SeahorseAccount {
+            account: &ctx.accounts.user_destination,
+            programs: &programs_map,
+        };
+
+        let pool_source_vault = SeahorseAccount {
+            account: &ctx.accounts.pool_source_vault,
+            programs: &programs_map,
+        };
+
+        let pool_destination_vault = SeahorseAccount {
+            account: &ctx.accounts.pool_destination_vault,
+            programs: &programs_map,
+        };
+
+        swap_exact_out_handler(
+            user.clone(),
+            pool.clone(),
+            user_source.clone(),
+            user_destination.clone(),
+            pool_source_vault.clone(),
+            pool_destination_vault.clone(),
+            amount_out,
+            maximum_amount_in,
+        )?;
+
         dot::program::Pool::store(pool);
 
-        return Ok(());
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    # [instruction (token_in_amount : u64 , minimum_amount_out : u64)]
+    pub struct SwapMulti<'info> {
+        #[account(mut)]
+        pub user: Signer<'info>,
+        pub multi_pool: Box<Account<'info, dot::program::MultiPool>>,
+        /// CHECK: signs for the pool's vaults and lp token mint
+        # [account (seeds = ["multi-pool-authority" . as_bytes () , multi_pool . key () . as_ref ()] , bump = multi_pool . authority_bump)]
+        pub multi_pool_authority: UncheckedAccount<'info>,
+        # [account (mut , token :: mint = pool_source_vault . mint , token :: authority = user)]
+        pub user_source: Box<Account<'info, TokenAccount>>,
+        # [account (mut , token :: mint = pool_destination_vault . mint , token :: authority = user)]
+        pub user_destination: Box<Account<'info, TokenAccount>>,
+        #[account(mut)]
+        pub pool_source_vault: Box<Account<'info, TokenAccount>>,
+        #[account(mut)]
+        pub pool_destination_vault: Box<Account<'info, TokenAccount>>,
+        pub token_program: Program<'info, Token>,
+    }
+
+    pub fn swap_multi(
+        ctx: Context<SwapMulti>,
+        token_in_amount: u64,
+        minimum_amount_out: u64,
+    ) -> Result<()> {
+        let mut programs = HashMap::new();
+
+        programs.insert(
+            "token_program",
+            ctx.accounts.token_program.to_account_info(),
+        );
+
+        let programs_map = ProgramsMap(programs);
+        let user = SeahorseSigner {
+            account: &ctx.accounts.user,
+            programs: &programs_map,
+        };
+
+        let multi_pool = dot::program::MultiPool::load(&mut ctx.accounts.multi_pool, &programs_map);
+        let multi_pool_authority = &ctx.accounts.multi_pool_authority;
+        let user_source = SeahorseAccount {
+            account: &ctx.accounts.user_source,
+            programs: &programs_map,
+        };
+
+        let user_destination = SeahorseAccount {
+            account: &ctx.accounts.user_destination,
+            programs: &programs_map,
+        };
+
+        let pool_source_vault = SeahorseAccount {
+            account: &ctx.accounts.pool_source_vault,
+            programs: &programs_map,
+        };
+
+        let pool_destination_vault = SeahorseAccount {
+            account: &ctx.accounts.pool_destination_vault,
+            programs: &programs_map,
+        };
+
+        swap_multi_handler(
+            user.clone(),
+            multi_pool.clone(),
+            multi_pool_authority,
+            user_source.clone(),
+            user_destination.clone(),
+            pool_source_vault.clone(),
+            pool_destination_vault.clone(),
+            token_in_amount,
+            minimum_amount_out,
+        )?;
+
+        dot::program::MultiPool::store(multi_pool);
+
+        Ok(())
+    }
+
+    #[derive(Accounts)]
+    # [instruction (fee_tiers : Vec < dot :: program :: FeeTier > , protocol_fee_numerator : u64 , protocol_fee_denominator : u64 , pool_creation_fee : u64)]
+    pub struct UpdateConfig<'info> {
+        pub admin: Signer<'info>,
+        # [account (mut , seeds = ["config" . as_bytes ()] , bump = config . bump , has_one = admin @ SeaAmmError :: Unauthorized)]
+        pub config: Box<Account<'info, dot::program::Config>>,
+    }
+
+    pub fn update_config(
+        ctx: Context<UpdateConfig>,
+        fee_tiers: Vec<dot::program::FeeTier>,
+        protocol_fee_numerator: u64,
+        protocol_fee_denominator: u64,
+        pool_creation_fee: u64,
+    ) -> Result<()> {
+        let mut programs = HashMap::new();
+        let programs_map = ProgramsMap(programs);
+        let admin = SeahorseSigner {
+            account: &ctx.accounts.admin,
+            programs: &programs_map,
+        };
+
+        let config = dot::program::Config::load(&mut ctx.accounts.config, &programs_map);
+
+        update_config_handler(
+            admin.clone(),
+            config.clone(),
+            fee_tiers,
+            protocol_fee_numerator,
+            protocol_fee_denominator,
+            pool_creation_fee,
+        )?;
+
+        dot::program::Config::store(config);
+
+        Ok(())
     }
 }
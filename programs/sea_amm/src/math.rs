@@ -0,0 +1,612 @@
+//! Curve math used by the instruction handlers in `dot::program`.
+//!
+//! Every product is taken in u128 (or `U256` where that is not enough) with
+//! checked operations, and results are narrowed back to u64 only at the end,
+//! so realistic 9-decimal balances don't overflow.
+
+use crate::error::{MathResult, SeaAmmError};
+use anchor_lang::prelude::*;
+
+pub use self::big_int::U256;
+
+// kept out of the anchor prelude's scope, whose `Result` alias clashes with
+// the one `construct_uint!` expects
+#[allow(clippy::assign_op_pattern, clippy::manual_div_ceil)]
+mod big_int {
+    use uint::construct_uint;
+
+    construct_uint! {
+        /// 256 bit unsigned integer, for the few formulas whose intermediates
+        /// don't fit in u128.
+        pub struct U256(4);
+    }
+}
+
+/// Returns the amount of the output token paid out for `amount_in` of the
+/// input token, using the constant product formula `dy = y * dx / (x + dx)`.
+///
+/// `fee_numerator / fee_denominator` of the input is taken as the swap fee
+/// before pricing. The fee stays in the input vault, so it accrues to LP
+/// token holders.
+pub fn get_amount_out(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> MathResult<u64> {
+    let fee_multiplier = checked_sub(fee_denominator as u128, fee_numerator as u128)?;
+    let amount_in_after_fee = checked_div(
+        checked_mul(amount_in as u128, fee_multiplier)?,
+        fee_denominator as u128,
+    )?;
+
+    to_u64(checked_div(
+        checked_mul(reserve_out as u128, amount_in_after_fee)?,
+        checked_add(reserve_in as u128, amount_in_after_fee)?,
+    )?)
+}
+
+/// Returns the amount of the input token needed to receive exactly
+/// `amount_out` of the output token, using `dx = x * dy / (y - dy)`.
+///
+/// Both the curve price and the fee gross-up are rounded up, so the pool
+/// never pays out more than the curve allows. `amount_out` must be less than
+/// `reserve_out`.
+pub fn get_amount_in(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> MathResult<u64> {
+    let reserve_out_after = checked_sub(reserve_out as u128, amount_out as u128)?;
+    let amount_in_after_fee = checked_div_ceil(
+        checked_mul(reserve_in as u128, amount_out as u128)?,
+        reserve_out_after,
+    )?;
+    let fee_multiplier = checked_sub(fee_denominator as u128, fee_numerator as u128)?;
+
+    to_u64(checked_div_ceil(
+        checked_mul(amount_in_after_fee, fee_denominator as u128)?,
+        fee_multiplier,
+    )?)
+}
+
+/// Returns the protocol's share of the swap fee on `amount_in`, which is
+/// `protocol_fee_numerator / protocol_fee_denominator` of the fee, rounded
+/// down in favor of LP token holders.
+pub fn get_protocol_fee(
+    amount_in: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+    protocol_fee_numerator: u64,
+    protocol_fee_denominator: u64,
+) -> MathResult<u64> {
+    let fee = checked_div(
+        checked_mul(amount_in as u128, fee_numerator as u128)?,
+        fee_denominator as u128,
+    )?;
+
+    to_u64(checked_div(
+        checked_mul(fee, protocol_fee_numerator as u128)?,
+        protocol_fee_denominator as u128,
+    )?)
+}
+
+/// Returns the amount of token b worth `amount_a` at the pool ratio
+/// `reserve_b / reserve_a`, rounded up so the pool is never short changed.
+pub fn quote(amount_a: u64, reserve_a: u64, reserve_b: u64) -> MathResult<u64> {
+    to_u64(checked_div_ceil(
+        checked_mul(amount_a as u128, reserve_b as u128)?,
+        reserve_a as u128,
+    )?)
+}
+
+/// Returns the largest deposit of token a and token b, each at most
+/// `max_amount_a` and `max_amount_b`, that matches the pool ratio.
+pub fn get_deposit_amounts(
+    max_amount_a: u64,
+    max_amount_b: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+) -> MathResult<(u64, u64)> {
+    let amount_b = quote(max_amount_a, reserve_a, reserve_b)?;
+
+    if amount_b <= max_amount_b {
+        return Ok((max_amount_a, amount_b));
+    }
+
+    let amount_a = quote(max_amount_b, reserve_b, reserve_a)?;
+
+    Ok((amount_a.min(max_amount_a), max_amount_b))
+}
+
+/// LP tokens locked forever on the first deposit. Keeping this floor under the
+/// LP supply stops the first depositor from inflating the value of a single
+/// LP token to round later depositors' shares down to zero.
+pub const MINIMUM_LIQUIDITY: u64 = 1_000;
+
+/// Returns the LP tokens minted for the first deposit into an empty pool,
+/// which is the geometric mean `sqrt(amount_a * amount_b)`. This includes the
+/// `MINIMUM_LIQUIDITY` that gets locked.
+pub fn get_initial_lp_amount(amount_a: u64, amount_b: u64) -> MathResult<u64> {
+    to_u64(sqrt(checked_mul(amount_a as u128, amount_b as u128)?))
+}
+
+/// Returns the LP tokens minted for depositing `amount_a` and `amount_b`
+/// into a pool holding `reserve_a` and `reserve_b`, proportional to the
+/// smaller of the two shares.
+pub fn get_lp_amount(
+    amount_a: u64,
+    amount_b: u64,
+    reserve_a: u64,
+    reserve_b: u64,
+    lp_supply: u64,
+) -> MathResult<u64> {
+    let lp_amount_a = checked_div(
+        checked_mul(amount_a as u128, lp_supply as u128)?,
+        reserve_a as u128,
+    )?;
+    let lp_amount_b = checked_div(
+        checked_mul(amount_b as u128, lp_supply as u128)?,
+        reserve_b as u128,
+    )?;
+
+    to_u64(lp_amount_a.min(lp_amount_b))
+}
+
+/// Returns the deposit of every token of a multi token pool, each at most
+/// its `max_amounts` entry, that matches the pool's balances, and the LP
+/// tokens it mints. The LP amount is rounded down and the deposit rounded
+/// up, both in favor of the pool.
+pub fn get_multi_deposit_amounts(
+    max_amounts: &[u64],
+    reserves: &[u64],
+    lp_supply: u64,
+) -> MathResult<(Vec<u64>, u64)> {
+    let mut lp_amount = u64::MAX as u128;
+    for (max_amount, reserve) in max_amounts.iter().zip(reserves) {
+        lp_amount = lp_amount.min(checked_div(
+            checked_mul(*max_amount as u128, lp_supply as u128)?,
+            *reserve as u128,
+        )?);
+    }
+
+    let amounts = reserves
+        .iter()
+        .map(|reserve| {
+            to_u64(checked_div_ceil(
+                checked_mul(lp_amount, *reserve as u128)?,
+                lp_supply as u128,
+            )?)
+        })
+        .collect::<MathResult<Vec<u64>>>()?;
+
+    Ok((amounts, to_u64(lp_amount)?))
+}
+
+/// Returns how much of a single sided deposit of `amount_in` has to be
+/// swapped into the other token, so that the rest of the deposit and the swap
+/// output match the pool ratio after the swap.
+///
+/// With the fee multiplier `r = 1 - fee`, this is the positive root of
+/// `r * s^2 + x * (1 + r) * s - x * a = 0`, scaled by `fee_denominator` to
+/// keep it in integers.
+pub fn get_single_sided_swap_amount(
+    amount_in: u64,
+    reserve_in: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> MathResult<u64> {
+    let fee_multiplier = U256::from(checked_sub(fee_denominator as u128, fee_numerator as u128)?);
+    let fee_denominator = U256::from(fee_denominator);
+    let reserve_in = U256::from(reserve_in);
+
+    // s = (sqrt(b^2 + c) - b) / 2m
+    // b = x * (d + m)
+    // c = 4 * m * d * x * a
+    let b = checked_mul_u256(reserve_in, fee_denominator + fee_multiplier)?;
+    let c = checked_mul_u256(
+        checked_mul_u256(U256::from(4) * fee_multiplier, fee_denominator)?,
+        checked_mul_u256(reserve_in, U256::from(amount_in))?,
+    )?;
+    let root = checked_add_u256(checked_mul_u256(b, b)?, c)?.integer_sqrt();
+    let swap_amount = checked_sub_u256(root, b)?
+        .checked_div(U256::from(2) * fee_multiplier)
+        .ok_or(SeaAmmError::MathOverflow)?;
+
+    if swap_amount > U256::from(u64::MAX) {
+        return Err(SeaAmmError::MathOverflow);
+    }
+
+    Ok(swap_amount.as_u64())
+}
+
+/// Returns the LP tokens minted for a single sided deposit of `amount_in`.
+///
+/// The optimal part of the deposit is swapped against the pool's curve first,
+/// and the rest is deposited together with the swap output at the reserves
+/// after the swap.
+pub fn get_single_sided_lp_amount(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    lp_supply: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> MathResult<u64> {
+    let swap_amount =
+        get_single_sided_swap_amount(amount_in, reserve_in, fee_numerator, fee_denominator)?;
+    let swap_amount_out = get_amount_out(
+        swap_amount,
+        reserve_in,
+        reserve_out,
+        fee_numerator,
+        fee_denominator,
+    )?;
+
+    get_lp_amount(
+        to_u64(checked_sub(amount_in as u128, swap_amount as u128)?)?,
+        swap_amount_out,
+        to_u64(checked_add(reserve_in as u128, swap_amount as u128)?)?,
+        to_u64(checked_sub(reserve_out as u128, swap_amount_out as u128)?)?,
+        lp_supply,
+    )
+}
+
+/// Returns the share of `reserve` paid out for burning `lp_amount` of an LP
+/// supply of `lp_supply`.
+pub fn get_withdraw_amount(lp_amount: u64, reserve: u64, lp_supply: u64) -> MathResult<u64> {
+    to_u64(checked_div(
+        checked_mul(reserve as u128, lp_amount as u128)?,
+        lp_supply as u128,
+    )?)
+}
+
+/// Returns the integer square root of `value`, rounded down.
+pub fn sqrt(value: u128) -> u128 {
+    if value < 2 {
+        return value;
+    }
+
+    // Newton's method, starting from a power of two above the root so the
+    // iterates decrease monotonically towards it
+    let mut x0 = 1u128 << (128 - value.leading_zeros()).div_ceil(2);
+    let mut x1 = (x0 + value / x0) / 2;
+
+    while x1 < x0 {
+        x0 = x1;
+        x1 = (x0 + value / x0) / 2;
+    }
+
+    x0
+}
+
+pub(crate) fn checked_add(a: u128, b: u128) -> MathResult<u128> {
+    a.checked_add(b).ok_or(SeaAmmError::MathOverflow)
+}
+
+pub(crate) fn checked_sub(a: u128, b: u128) -> MathResult<u128> {
+    a.checked_sub(b).ok_or(SeaAmmError::MathOverflow)
+}
+
+pub(crate) fn checked_mul(a: u128, b: u128) -> MathResult<u128> {
+    a.checked_mul(b).ok_or(SeaAmmError::MathOverflow)
+}
+
+pub(crate) fn checked_div(a: u128, b: u128) -> MathResult<u128> {
+    a.checked_div(b).ok_or(SeaAmmError::MathOverflow)
+}
+
+pub(crate) fn checked_div_ceil(a: u128, b: u128) -> MathResult<u128> {
+    let quotient = checked_div(a, b)?;
+
+    if quotient * b < a {
+        return checked_add(quotient, 1);
+    }
+
+    Ok(quotient)
+}
+
+pub(crate) fn checked_add_u256(a: U256, b: U256) -> MathResult<U256> {
+    a.checked_add(b).ok_or(SeaAmmError::MathOverflow)
+}
+
+pub(crate) fn checked_mul_u256(a: U256, b: U256) -> MathResult<U256> {
+    a.checked_mul(b).ok_or(SeaAmmError::MathOverflow)
+}
+
+pub(crate) fn checked_sub_u256(a: U256, b: U256) -> MathResult<U256> {
+    a.checked_sub(b).ok_or(SeaAmmError::MathOverflow)
+}
+
+pub(crate) fn checked_div_u256(a: U256, b: U256) -> MathResult<U256> {
+    a.checked_div(b).ok_or(SeaAmmError::MathOverflow)
+}
+
+pub(crate) fn to_u64(a: u128) -> MathResult<u64> {
+    u64::try_from(a).map_err(|_| SeaAmmError::MathOverflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn amount_out_without_fee() {
+        assert_eq!(
+            get_amount_out(1_000, 10_000, 10_000, 0, 1_000).unwrap(),
+            909
+        );
+    }
+
+    #[test]
+    fn amount_out_with_fee() {
+        // 0.3% of 1_000 is taken as fee, leaving 997 to be priced
+        assert_eq!(
+            get_amount_out(1_000, 10_000, 10_000, 3, 1_000).unwrap(),
+            906
+        );
+    }
+
+    #[test]
+    fn amount_out_with_large_reserves() {
+        // 1 billion tokens with 9 decimals on each side
+        let reserve = 1_000_000_000_000_000_000;
+        let amount_out = get_amount_out(reserve, reserve, reserve, 0, 1_000).unwrap();
+        assert_eq!(amount_out, reserve / 2);
+    }
+
+    #[test]
+    fn amount_in_rounds_up() {
+        // 10_000 * 909 / 9_091 = 999.9, rounded up
+        assert_eq!(get_amount_in(909, 10_000, 10_000, 0, 1_000).unwrap(), 1_000);
+        assert_eq!(get_amount_in(906, 10_000, 10_000, 3, 1_000).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn amount_in_covers_amount_out() {
+        for amount_out in [1, 7, 999, 12_345, 499_999, 999_999] {
+            let amount_in = get_amount_in(amount_out, 1_000_000, 1_000_000, 3, 1_000).unwrap();
+            let amount_out_for_amount_in =
+                get_amount_out(amount_in, 1_000_000, 1_000_000, 3, 1_000).unwrap();
+            assert!(amount_out_for_amount_in >= amount_out);
+        }
+    }
+
+    #[test]
+    fn amount_in_overflow_is_an_error() {
+        assert!(get_amount_in(u64::MAX - 1, u64::MAX, u64::MAX, 0, 1_000).is_err());
+        assert!(get_amount_in(10, 10, 10, 0, 1_000).is_err());
+    }
+
+    #[test]
+    fn protocol_fee_is_a_share_of_the_swap_fee() {
+        // 0.3% fee on 1_000_000 is 3_000, of which the protocol takes a sixth
+        assert_eq!(get_protocol_fee(1_000_000, 3, 1_000, 1, 6).unwrap(), 500);
+        assert_eq!(get_protocol_fee(1_000_000, 3, 1_000, 0, 6).unwrap(), 0);
+        assert_eq!(
+            get_protocol_fee(u64::MAX, 3, 1_000, 1, 1).unwrap(),
+            u64::MAX / 1_000 * 3 + 1
+        );
+    }
+
+    #[test]
+    fn protocol_fee_never_eats_into_reserves() {
+        let (reserve_in, reserve_out) = (1_000_000_000u64, 2_000_000_000u64);
+        for amount_in in [1, 333, 1_000_000, 999_999_999] {
+            let amount_out = get_amount_out(amount_in, reserve_in, reserve_out, 3, 1_000).unwrap();
+            let protocol_fee = get_protocol_fee(amount_in, 3, 1_000, 1, 1).unwrap();
+            // even with the whole fee going to the protocol, k doesn't drop
+            let k_before = reserve_in as u128 * reserve_out as u128;
+            let k_after = (reserve_in + amount_in - protocol_fee) as u128
+                * (reserve_out - amount_out) as u128;
+            assert!(k_after >= k_before);
+        }
+    }
+
+    #[test]
+    fn quote_rounds_up() {
+        assert_eq!(quote(1_000, 3_000, 9_000).unwrap(), 3_000);
+        assert_eq!(quote(1_000, 3_000, 10_000).unwrap(), 3_334);
+        assert_eq!(
+            quote(u64::MAX, u64::MAX, u64::MAX - 1).unwrap(),
+            u64::MAX - 1
+        );
+    }
+
+    #[test]
+    fn deposit_amounts_limited_by_token_b() {
+        // 1_000 a needs 3_000 b, but only 1_500 b is allowed
+        let deposit_amounts = get_deposit_amounts(1_000, 1_500, 3_000, 9_000).unwrap();
+        assert_eq!(deposit_amounts, (500, 1_500));
+    }
+
+    #[test]
+    fn deposit_amounts_limited_by_token_a() {
+        let deposit_amounts = get_deposit_amounts(1_000, 5_000, 3_000, 9_000).unwrap();
+        assert_eq!(deposit_amounts, (1_000, 3_000));
+    }
+
+    #[test]
+    fn deposit_amounts_never_exceed_max_amounts() {
+        for (reserve_a, reserve_b) in [(3, 7), (1_000_003, 999_983), (7, 1_000_000_007)] {
+            for (max_a, max_b) in [(1, 1), (10, 13), (999_999, 1), (123_456, 654_321)] {
+                let (amount_a, amount_b) =
+                    get_deposit_amounts(max_a, max_b, reserve_a, reserve_b).unwrap();
+                assert!(amount_a <= max_a && amount_b <= max_b);
+            }
+        }
+    }
+
+    #[test]
+    fn sqrt_small_values() {
+        let roots = [0, 1, 1, 1, 2, 2, 2, 2, 2, 3, 3];
+        for (value, root) in roots.iter().enumerate() {
+            assert_eq!(sqrt(value as u128), *root);
+        }
+    }
+
+    #[test]
+    fn sqrt_rounds_down() {
+        for root in [2u128, 1_000, 65_535, 4_294_967_295, 1 << 63] {
+            assert_eq!(sqrt(root * root), root);
+            assert_eq!(sqrt(root * root - 1), root - 1);
+            assert_eq!(sqrt(root * root + 1), root);
+        }
+    }
+
+    #[test]
+    fn sqrt_max_value() {
+        assert_eq!(sqrt(u128::MAX), u64::MAX as u128);
+    }
+
+    #[test]
+    fn initial_lp_amount_is_geometric_mean() {
+        assert_eq!(get_initial_lp_amount(4_000, 9_000).unwrap(), 6_000);
+        assert_eq!(get_initial_lp_amount(u64::MAX, u64::MAX).unwrap(), u64::MAX);
+        assert_eq!(get_initial_lp_amount(1_000_000, 0).unwrap(), 0);
+    }
+
+    #[test]
+    fn lp_amount_with_large_supply() {
+        let lp_supply = 1_000_000_000_000_000_000;
+        let lp_amount = get_lp_amount(500, 1_000, 1_000, 1_000, lp_supply).unwrap();
+        assert_eq!(lp_amount, lp_supply / 2);
+    }
+
+    #[test]
+    fn later_deposits_get_their_share_of_the_pool() {
+        let mut reserve_a = 4_000_000;
+        let mut reserve_b = 9_000_000;
+        let mut lp_supply = get_initial_lp_amount(reserve_a, reserve_b).unwrap();
+
+        for (max_amount_a, max_amount_b) in [(1_000_000, 5_000_000), (777, 1_000), (3_000_000, 100)]
+        {
+            // priced against the reserves before this deposit lands
+            let (amount_a, amount_b) =
+                get_deposit_amounts(max_amount_a, max_amount_b, reserve_a, reserve_b).unwrap();
+            let lp_amount =
+                get_lp_amount(amount_a, amount_b, reserve_a, reserve_b, lp_supply).unwrap();
+            reserve_a += amount_a;
+            reserve_b += amount_b;
+            lp_supply += lp_amount;
+
+            // redeeming straight away returns the deposit, less rounding
+            let token_amount_a = get_withdraw_amount(lp_amount, reserve_a, lp_supply).unwrap();
+            let token_amount_b = get_withdraw_amount(lp_amount, reserve_b, lp_supply).unwrap();
+            assert!(token_amount_a <= amount_a && token_amount_b <= amount_b);
+            assert!(amount_a - token_amount_a <= 2 && amount_b - token_amount_b <= 3);
+        }
+    }
+
+    #[test]
+    fn single_sided_swap_amount_without_fee() {
+        // without a fee, s^2 + 2xs - xa = 0, so s = x * (sqrt(1 + a / x) - 1)
+        assert_eq!(
+            get_single_sided_swap_amount(3_000, 1_000, 0, 1_000).unwrap(),
+            1_000
+        );
+        assert_eq!(
+            get_single_sided_swap_amount(8_000, 1_000, 0, 1_000).unwrap(),
+            2_000
+        );
+    }
+
+    #[test]
+    fn single_sided_swap_leaves_deposit_in_pool_ratio() {
+        let (reserve_in, reserve_out) = (1_000_000_000_000_000_000, 3_000_000_000_000_000_000);
+        for amount_in in [1_000_000_000, 1_000_000_000_000, 500_000_000_000_000_000] {
+            let swap_amount =
+                get_single_sided_swap_amount(amount_in, reserve_in, 3, 1_000).unwrap();
+            let swap_amount_out =
+                get_amount_out(swap_amount, reserve_in, reserve_out, 3, 1_000).unwrap();
+
+            // deposit_in / reserve_in equals deposit_out / reserve_out after
+            // the swap, up to rounding
+            let deposit_in = (amount_in - swap_amount) as f64;
+            let deposit_out = swap_amount_out as f64;
+            let ratio_in = deposit_in / (reserve_in + swap_amount) as f64;
+            let ratio_out = deposit_out / (reserve_out - swap_amount_out) as f64;
+            assert!((ratio_in / ratio_out - 1.0).abs() < 1e-6);
+        }
+    }
+
+    #[test]
+    fn single_sided_lp_amount_is_less_than_balanced_deposit() {
+        let lp_supply = 1_000_000;
+        let single_sided =
+            get_single_sided_lp_amount(2_000, 1_000_000, 1_000_000, lp_supply, 3, 1_000).unwrap();
+        let balanced = get_lp_amount(1_000, 1_000, 1_000_000, 1_000_000, lp_supply).unwrap();
+        assert!(single_sided < balanced);
+        assert!(single_sided > balanced * 99 / 100);
+    }
+
+    #[test]
+    fn single_sided_overflow_is_an_error() {
+        // the reserve after the swap no longer fits in a u64
+        let max = u64::MAX;
+        assert!(get_single_sided_lp_amount(max, max, max, max, 3, 1_000).is_err());
+    }
+
+    #[test]
+    fn multi_deposit_is_limited_by_the_scarcest_token() {
+        let (amounts, lp_amount) = get_multi_deposit_amounts(
+            &[10_000_000, 100_000_000, 100_000_000],
+            &[100_000_000, 200_000_000, 300_000_000],
+            1_000_000,
+        )
+        .unwrap();
+        assert_eq!(lp_amount, 100_000);
+        assert_eq!(amounts, vec![10_000_000, 20_000_000, 30_000_000]);
+
+        // rounding takes a little more of every token, never more than the max
+        let (amounts, lp_amount) =
+            get_multi_deposit_amounts(&[1_000, 1_000, 1_000], &[3_333, 7_777, 9_999], 1_234)
+                .unwrap();
+        assert_eq!(lp_amount, 123);
+        for (amount, reserve) in amounts.iter().zip([3_333u64, 7_777, 9_999]) {
+            assert!(*amount as u128 * 1_234 >= lp_amount as u128 * reserve as u128);
+            assert!(*amount <= 1_000);
+        }
+    }
+
+    #[test]
+    fn withdraw_amount_with_large_reserve() {
+        let reserve = 1_000_000_000_000_000_000;
+        assert_eq!(
+            get_withdraw_amount(250, reserve, 1_000).unwrap(),
+            reserve / 4
+        );
+    }
+
+    #[test]
+    fn fees_grow_lp_redemption_value() {
+        let lp_supply: u64 = 1_000_000;
+        let mut reserve_a: u64 = 1_000_000;
+        let mut reserve_b: u64 = 1_000_000;
+        let k_before = reserve_a * reserve_b;
+
+        // swap a -> b and straight back again
+        for _ in 0..10 {
+            let amount_b = get_amount_out(50_000, reserve_a, reserve_b, 3, 1_000).unwrap();
+            reserve_a += 50_000;
+            reserve_b -= amount_b;
+
+            let amount_a = get_amount_out(amount_b, reserve_b, reserve_a, 3, 1_000).unwrap();
+            reserve_b += amount_b;
+            reserve_a -= amount_a;
+        }
+
+        assert!(reserve_a * reserve_b > k_before);
+
+        // redeeming 10% of the LP supply returns more than 10% of the
+        // initial deposit on at least one side and never less on either
+        let redeem = lp_supply / 10;
+        let token_amount_a = get_withdraw_amount(redeem, reserve_a, lp_supply).unwrap();
+        let token_amount_b = get_withdraw_amount(redeem, reserve_b, lp_supply).unwrap();
+        assert!(token_amount_a >= 100_000 && token_amount_b >= 100_000);
+        assert!(token_amount_a + token_amount_b > 200_000);
+    }
+}
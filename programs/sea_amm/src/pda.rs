@@ -0,0 +1,260 @@
+//! Addresses of the config and of the accounts that make up a pool.
+//!
+//! A pair of mints has exactly one pool per fee tier: `create_pool` only
+//! accepts the mints in canonical order, `token_mint_a < token_mint_b` by
+//! their bytes. Clients can pass the two mints in any order to
+//! `PoolAddresses::new` to get the ordering and every derived address for
+//! that pool.
+//!
+//! A multi token pool is an ordinary account, so `MultiPoolAddresses::new`
+//! derives its accounts from the pool's own address.
+//!
+//! Concentrated pools follow the same rules as pools, under their own seeds,
+//! a position is derived from its pool, owner and ticks, and a tick array
+//! from its pool and first tick.
+
+use anchor_lang::prelude::*;
+
+/// Returns the address of the singleton config account.
+pub fn config_address() -> Pubkey {
+    Pubkey::find_program_address(&[b"config"], &crate::ID).0
+}
+
+/// Returns the two mints in the order `create_pool` expects them.
+pub fn sort_mints(mint_x: Pubkey, mint_y: Pubkey) -> (Pubkey, Pubkey) {
+    if mint_x < mint_y {
+        (mint_x, mint_y)
+    } else {
+        (mint_y, mint_x)
+    }
+}
+
+/// Program derived addresses of a pool and its accounts, for the mints in
+/// canonical order and the pool's fee tier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PoolAddresses {
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub pool: Pubkey,
+    pub token_vault_a: Pubkey,
+    pub token_vault_b: Pubkey,
+    pub lp_token_mint: Pubkey,
+    pub locked_lp_token_account: Pubkey,
+}
+
+impl PoolAddresses {
+    pub fn new(mint_x: Pubkey, mint_y: Pubkey, fee_numerator: u64, fee_denominator: u64) -> Self {
+        let (token_mint_a, token_mint_b) = sort_mints(mint_x, mint_y);
+        let find = |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, &crate::ID).0;
+
+        let pool = find(&[
+            b"pool",
+            token_mint_a.as_ref(),
+            token_mint_b.as_ref(),
+            &fee_numerator.to_le_bytes(),
+            &fee_denominator.to_le_bytes(),
+        ]);
+        let lp_token_mint = find(&[b"lp-token-mint", pool.as_ref()]);
+
+        PoolAddresses {
+            token_mint_a,
+            token_mint_b,
+            pool,
+            token_vault_a: find(&[b"token-vault-a", pool.as_ref()]),
+            token_vault_b: find(&[b"token-vault-b", pool.as_ref()]),
+            lp_token_mint,
+            locked_lp_token_account: find(&[b"locked-lp-token-account", lp_token_mint.as_ref()]),
+        }
+    }
+
+    /// Returns the lp token account `add_liquidity` creates for `user`.
+    pub fn user_lp_token_account(&self, user: &Pubkey) -> Pubkey {
+        lp_token_account(&self.lp_token_mint, user)
+    }
+}
+
+/// Program derived addresses of the accounts of a multi token pool.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct MultiPoolAddresses {
+    pub multi_pool: Pubkey,
+    /// Owns the pool's vaults and mints its lp tokens. The vaults passed to
+    /// `create_multi_pool` must be owned by it.
+    pub multi_pool_authority: Pubkey,
+    pub lp_token_mint: Pubkey,
+    pub locked_lp_token_account: Pubkey,
+}
+
+impl MultiPoolAddresses {
+    pub fn new(multi_pool: Pubkey) -> Self {
+        let find = |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, &crate::ID).0;
+        let lp_token_mint = find(&[b"lp-token-mint", multi_pool.as_ref()]);
+
+        MultiPoolAddresses {
+            multi_pool,
+            multi_pool_authority: find(&[b"multi-pool-authority", multi_pool.as_ref()]),
+            lp_token_mint,
+            locked_lp_token_account: find(&[b"locked-lp-token-account", lp_token_mint.as_ref()]),
+        }
+    }
+
+    /// Returns the lp token account `add_liquidity_multi` creates for `user`.
+    pub fn user_lp_token_account(&self, user: &Pubkey) -> Pubkey {
+        lp_token_account(&self.lp_token_mint, user)
+    }
+}
+
+/// Program derived addresses of a concentrated pool and its vaults, for the
+/// mints in canonical order and the pool's fee tier.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ConcentratedPoolAddresses {
+    pub token_mint_a: Pubkey,
+    pub token_mint_b: Pubkey,
+    pub concentrated_pool: Pubkey,
+    pub token_vault_a: Pubkey,
+    pub token_vault_b: Pubkey,
+}
+
+impl ConcentratedPoolAddresses {
+    pub fn new(mint_x: Pubkey, mint_y: Pubkey, fee_numerator: u64, fee_denominator: u64) -> Self {
+        let (token_mint_a, token_mint_b) = sort_mints(mint_x, mint_y);
+        let find = |seeds: &[&[u8]]| Pubkey::find_program_address(seeds, &crate::ID).0;
+
+        let concentrated_pool = find(&[
+            b"concentrated-pool",
+            token_mint_a.as_ref(),
+            token_mint_b.as_ref(),
+            &fee_numerator.to_le_bytes(),
+            &fee_denominator.to_le_bytes(),
+        ]);
+
+        ConcentratedPoolAddresses {
+            token_mint_a,
+            token_mint_b,
+            concentrated_pool,
+            token_vault_a: find(&[b"token-vault-a", concentrated_pool.as_ref()]),
+            token_vault_b: find(&[b"token-vault-b", concentrated_pool.as_ref()]),
+        }
+    }
+
+    /// Returns the position `open_position` creates for `owner` between
+    /// `tick_lower` and `tick_upper`.
+    pub fn position(&self, owner: &Pubkey, tick_lower: i32, tick_upper: i32) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                b"position",
+                self.concentrated_pool.as_ref(),
+                owner.as_ref(),
+                &tick_lower.to_le_bytes(),
+                &tick_upper.to_le_bytes(),
+            ],
+            &crate::ID,
+        )
+        .0
+    }
+
+    /// Returns the tick array `initialize_tick_array` creates from
+    /// `start_tick_index`.
+    pub fn tick_array(&self, start_tick_index: i32) -> Pubkey {
+        Pubkey::find_program_address(
+            &[
+                b"tick-array",
+                self.concentrated_pool.as_ref(),
+                &start_tick_index.to_le_bytes(),
+            ],
+            &crate::ID,
+        )
+        .0
+    }
+}
+
+fn lp_token_account(lp_token_mint: &Pubkey, user: &Pubkey) -> Pubkey {
+    Pubkey::find_program_address(
+        &[b"lp-token-account", lp_token_mint.as_ref(), user.as_ref()],
+        &crate::ID,
+    )
+    .0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mint_order_does_not_change_the_pool() {
+        let (mint_x, mint_y) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let addresses = PoolAddresses::new(mint_x, mint_y, 3, 1_000);
+        assert_eq!(addresses, PoolAddresses::new(mint_y, mint_x, 3, 1_000));
+        assert!(addresses.token_mint_a < addresses.token_mint_b);
+    }
+
+    #[test]
+    fn pools_sharing_a_mint_have_their_own_vaults() {
+        let mint = Pubkey::new_unique();
+        let addresses = PoolAddresses::new(mint, Pubkey::new_unique(), 3, 1_000);
+        let other_addresses = PoolAddresses::new(mint, Pubkey::new_unique(), 3, 1_000);
+        assert_ne!(addresses.token_vault_a, other_addresses.token_vault_a);
+        assert_ne!(addresses.token_vault_b, other_addresses.token_vault_b);
+    }
+
+    #[test]
+    fn fee_tiers_of_a_pair_are_separate_pools() {
+        let (mint_x, mint_y) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let addresses = PoolAddresses::new(mint_x, mint_y, 3, 1_000);
+        let other_addresses = PoolAddresses::new(mint_x, mint_y, 1, 10_000);
+        assert_ne!(addresses.pool, other_addresses.pool);
+        assert_ne!(addresses.lp_token_mint, other_addresses.lp_token_mint);
+    }
+
+    #[test]
+    fn multi_pool_lp_accounts_are_its_own() {
+        let addresses = MultiPoolAddresses::new(Pubkey::new_unique());
+        let other_addresses = MultiPoolAddresses::new(Pubkey::new_unique());
+        assert_ne!(addresses.lp_token_mint, other_addresses.lp_token_mint);
+        let user = Pubkey::new_unique();
+        assert_ne!(
+            addresses.user_lp_token_account(&user),
+            other_addresses.user_lp_token_account(&user)
+        );
+    }
+
+    #[test]
+    fn concentrated_pools_are_apart_from_pools() {
+        let (mint_x, mint_y) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let addresses = PoolAddresses::new(mint_x, mint_y, 3, 1_000);
+        let concentrated_addresses = ConcentratedPoolAddresses::new(mint_y, mint_x, 3, 1_000);
+        assert_eq!(concentrated_addresses.token_mint_a, addresses.token_mint_a);
+        assert_ne!(concentrated_addresses.concentrated_pool, addresses.pool);
+        assert_ne!(
+            concentrated_addresses.token_vault_a,
+            addresses.token_vault_a
+        );
+
+        let owner = Pubkey::new_unique();
+        assert_ne!(
+            concentrated_addresses.position(&owner, -60, 60),
+            concentrated_addresses.position(&owner, -120, 60)
+        );
+        assert_ne!(
+            concentrated_addresses.tick_array(0),
+            concentrated_addresses.tick_array(-960)
+        );
+    }
+
+    #[test]
+    fn pool_address_matches_create_pool_seeds() {
+        let (mint_x, mint_y) = (Pubkey::new_unique(), Pubkey::new_unique());
+        let addresses = PoolAddresses::new(mint_y, mint_x, 3, 1_000);
+        let (pool, _) = Pubkey::find_program_address(
+            &[
+                b"pool",
+                mint_x.as_ref(),
+                mint_y.as_ref(),
+                &3u64.to_le_bytes(),
+                &1_000u64.to_le_bytes(),
+            ],
+            &crate::ID,
+        );
+        // new_unique hands out increasing keys
+        assert_eq!(addresses.pool, pool);
+    }
+}
@@ -0,0 +1,629 @@
+//! StableSwap curve math, for pools of pegged tokens.
+//!
+//! The invariant `D` of a two token pool with balances `x` and `y` and
+//! amplification `A` satisfies
+//!
+//! `4A(x + y) + D = 4AD + D^3 / (4xy)`
+//!
+//! which behaves like `x + y = D` near the peg and like `xy = (D/2)^2` far
+//! from it. Neither `D` nor a balance given the other has a closed form, so
+//! both are found with Newton's method, in `U256`.
+//!
+//! The curve assumes one unit of each token is worth the same, so balances of
+//! tokens with fewer decimals are scaled up to the larger number of decimals
+//! first, and amounts paid out are scaled back down, rounded against the
+//! trader.
+
+use crate::error::{MathResult, SeaAmmError};
+use crate::math::{
+    self, checked_add_u256, checked_div, checked_div_u256, checked_mul, checked_mul_u256,
+    checked_sub, checked_sub_u256, U256,
+};
+use anchor_lang::prelude::*;
+
+/// Smallest amplification a stable pool can have.
+pub const MIN_AMP: u64 = 1;
+/// Largest amplification a stable pool can have.
+pub const MAX_AMP: u64 = 1_000_000;
+/// Shortest time a change of amplification can be ramped over, and between
+/// the starts of two ramps, in seconds.
+pub const MIN_RAMP_DURATION: i64 = 24 * 60 * 60;
+/// Largest factor one ramp can change the amplification by, either way.
+pub const MAX_AMP_CHANGE: u64 = 10;
+
+const N_COINS: u64 = 2;
+const MAX_ITERATIONS: usize = 255;
+
+/// A reserve of a stable pool, with the multiplier that scales amounts of its
+/// token up to the precision the curve is computed in.
+#[derive(Clone, Copy, Debug)]
+pub struct Reserve {
+    pub amount: u64,
+    pub precision_multiplier: u64,
+}
+
+impl Reserve {
+    pub fn new(amount: u64, precision_multiplier: u64) -> Self {
+        Self {
+            amount,
+            precision_multiplier,
+        }
+    }
+
+    fn balance(&self) -> U256 {
+        self.upscale(self.amount)
+    }
+
+    fn upscale(&self, amount: u64) -> U256 {
+        U256::from(amount) * U256::from(self.precision_multiplier)
+    }
+
+    fn downscale(&self, amount: U256) -> MathResult<U256> {
+        checked_div_u256(amount, U256::from(self.precision_multiplier))
+    }
+
+    fn downscale_ceil(&self, amount: U256) -> MathResult<U256> {
+        let multiplier = U256::from(self.precision_multiplier);
+        checked_div_u256(checked_add_u256(amount, multiplier - 1)?, multiplier)
+    }
+}
+
+/// Returns the precision multipliers of two tokens with `decimals_a` and
+/// `decimals_b` decimals, which scale both to the larger of the two.
+pub fn get_precision_multipliers(decimals_a: u8, decimals_b: u8) -> MathResult<(u64, u64)> {
+    let decimals = decimals_a.max(decimals_b);
+    let multiplier = |token_decimals: u8| {
+        10u64
+            .checked_pow((decimals - token_decimals) as u32)
+            .ok_or(SeaAmmError::MathOverflow)
+    };
+
+    Ok((multiplier(decimals_a)?, multiplier(decimals_b)?))
+}
+
+/// Returns the invariant `D` of a pool with `amp` and scaled balances
+/// `balance_a` and `balance_b`, which is `balance_a + balance_b` for a
+/// balanced pool.
+pub fn compute_d(amp: u64, balance_a: U256, balance_b: U256) -> MathResult<U256> {
+    let sum = checked_add_u256(balance_a, balance_b)?;
+    if sum.is_zero() {
+        return Ok(U256::zero());
+    }
+
+    let n = U256::from(N_COINS);
+    let amp_times_n = checked_mul_u256(U256::from(amp), n * n)?;
+    let mut d = sum;
+
+    for _ in 0..MAX_ITERATIONS {
+        // d_p = d^3 / (4 * balance_a * balance_b)
+        let mut d_p = d;
+        d_p = checked_div_u256(checked_mul_u256(d_p, d)?, checked_mul_u256(balance_a, n)?)?;
+        d_p = checked_div_u256(checked_mul_u256(d_p, d)?, checked_mul_u256(balance_b, n)?)?;
+
+        // d = (4A * sum + 2 * d_p) * d / ((4A - 1) * d + 3 * d_p)
+        let d_prev = d;
+        let numerator = checked_mul_u256(
+            checked_add_u256(checked_mul_u256(amp_times_n, sum)?, d_p * n)?,
+            d,
+        )?;
+        let denominator = checked_add_u256(
+            checked_mul_u256(checked_sub_u256(amp_times_n, U256::one())?, d)?,
+            checked_mul_u256(d_p, n + 1)?,
+        )?;
+        d = checked_div_u256(numerator, denominator)?;
+
+        if abs_diff(d, d_prev) <= U256::one() {
+            return Ok(d);
+        }
+    }
+
+    Err(SeaAmmError::CurveNotConverged)
+}
+
+/// Returns the balance of one token that keeps the invariant at `d` when the
+/// other token's balance is `balance`.
+pub fn compute_y(amp: u64, balance: U256, d: U256) -> MathResult<U256> {
+    let n = U256::from(N_COINS);
+    let amp_times_n = checked_mul_u256(U256::from(amp), n * n)?;
+
+    // c = d^3 / (4 * balance * 4A), b = balance + d / 4A
+    let mut c = d;
+    c = checked_div_u256(checked_mul_u256(c, d)?, checked_mul_u256(balance, n)?)?;
+    c = checked_div_u256(checked_mul_u256(c, d)?, checked_mul_u256(amp_times_n, n)?)?;
+    let b = checked_add_u256(balance, checked_div_u256(d, amp_times_n)?)?;
+    let mut y = d;
+
+    for _ in 0..MAX_ITERATIONS {
+        // y = (y^2 + c) / (2y + b - d)
+        let y_prev = y;
+        y = checked_div_u256(
+            checked_add_u256(checked_mul_u256(y, y)?, c)?,
+            checked_sub_u256(checked_add_u256(y * n, b)?, d)?,
+        )?;
+
+        if abs_diff(y, y_prev) <= U256::one() {
+            return Ok(y);
+        }
+    }
+
+    Err(SeaAmmError::CurveNotConverged)
+}
+
+/// Returns the amplification at `now` of a ramp from `initial_amp` at
+/// `ramp_start_timestamp` to `target_amp` at `ramp_end_timestamp`, which
+/// moves linearly in between so prices never jump.
+pub fn get_amp(
+    initial_amp: u64,
+    target_amp: u64,
+    ramp_start_timestamp: i64,
+    ramp_end_timestamp: i64,
+    now: i64,
+) -> u64 {
+    if now >= ramp_end_timestamp {
+        return target_amp;
+    }
+    if now <= ramp_start_timestamp {
+        return initial_amp;
+    }
+
+    let elapsed = (now - ramp_start_timestamp) as u128;
+    let duration = (ramp_end_timestamp - ramp_start_timestamp) as u128;
+    // the change is at most MAX_AMP and elapsed < duration, so nothing overflows
+    if target_amp >= initial_amp {
+        initial_amp + ((target_amp - initial_amp) as u128 * elapsed / duration) as u64
+    } else {
+        initial_amp - ((initial_amp - target_amp) as u128 * elapsed / duration) as u64
+    }
+}
+
+/// Checks a ramp from `amp` now to `target_amp` at `ramp_end_timestamp`. The
+/// last ramp must have started at least `MIN_RAMP_DURATION` ago, so ramps
+/// can't be chained to change the amplification faster.
+pub fn check_ramp(
+    amp: u64,
+    target_amp: u64,
+    last_ramp_start_timestamp: i64,
+    now: i64,
+    ramp_end_timestamp: i64,
+) -> MathResult<()> {
+    if now < last_ramp_start_timestamp.saturating_add(MIN_RAMP_DURATION) {
+        return Err(SeaAmmError::RampTooSoon);
+    }
+
+    if !(MIN_AMP..=MAX_AMP).contains(&target_amp) {
+        return Err(SeaAmmError::InvalidAmp);
+    }
+
+    if !((target_amp <= amp.saturating_mul(MAX_AMP_CHANGE))
+        && (amp <= target_amp.saturating_mul(MAX_AMP_CHANGE)))
+    {
+        return Err(SeaAmmError::AmpChangeTooLarge);
+    }
+
+    if ramp_end_timestamp < now.saturating_add(MIN_RAMP_DURATION) {
+        return Err(SeaAmmError::InvalidRampTime);
+    }
+
+    Ok(())
+}
+
+/// Returns the amount of the output token paid out for `amount_in` of the
+/// input token. The fee is taken from the input before pricing, as on the
+/// constant product curve.
+pub fn get_amount_out(
+    amount_in: u64,
+    reserve_in: Reserve,
+    reserve_out: Reserve,
+    amp: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> MathResult<u64> {
+    let fee_multiplier = checked_sub(fee_denominator as u128, fee_numerator as u128)?;
+    let amount_in_after_fee = math::to_u64(checked_div(
+        checked_mul(amount_in as u128, fee_multiplier)?,
+        fee_denominator as u128,
+    )?)?;
+
+    let d = compute_d(amp, reserve_in.balance(), reserve_out.balance())?;
+    let new_balance_in = checked_add_u256(
+        reserve_in.balance(),
+        reserve_in.upscale(amount_in_after_fee),
+    )?;
+    let new_balance_out = compute_y(amp, new_balance_in, d)?;
+
+    // one unit less than exact, so rounding never pays out of the invariant
+    let amount_out = reserve_out
+        .balance()
+        .saturating_sub(new_balance_out)
+        .saturating_sub(U256::one());
+
+    to_u64(reserve_out.downscale(amount_out)?)
+}
+
+/// Returns the amount of the input token needed to get exactly `amount_out`
+/// of the output token, fee included and rounded up.
+pub fn get_amount_in(
+    amount_out: u64,
+    reserve_in: Reserve,
+    reserve_out: Reserve,
+    amp: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> MathResult<u64> {
+    let d = compute_d(amp, reserve_in.balance(), reserve_out.balance())?;
+    let new_balance_out = checked_sub_u256(reserve_out.balance(), reserve_out.upscale(amount_out))?;
+    let new_balance_in = compute_y(amp, new_balance_out, d)?;
+    let amount_in_after_fee = reserve_in.downscale_ceil(checked_add_u256(
+        checked_sub_u256(new_balance_in, reserve_in.balance())?,
+        U256::one(),
+    )?)?;
+
+    let fee_multiplier = U256::from(checked_sub(fee_denominator as u128, fee_numerator as u128)?);
+    let amount_in = checked_div_u256(
+        checked_add_u256(
+            checked_mul_u256(amount_in_after_fee, U256::from(fee_denominator))?,
+            fee_multiplier - 1,
+        )?,
+        fee_multiplier,
+    )?;
+
+    to_u64(amount_in)
+}
+
+/// Returns the LP tokens minted for the first deposit into a stable pool,
+/// which is the invariant `D` of the deposit in the precision of the token
+/// with more decimals.
+pub fn get_initial_lp_amount(amp: u64, deposit_a: Reserve, deposit_b: Reserve) -> MathResult<u64> {
+    to_u64(compute_d(amp, deposit_a.balance(), deposit_b.balance())?)
+}
+
+/// Returns the part of a single sided deposit of `amount_in` that a
+/// proportional deposit would have put in the other token, rounded up.
+pub fn get_single_sided_swap_amount(
+    amount_in: u64,
+    reserve_in: Reserve,
+    reserve_out: Reserve,
+) -> MathResult<u64> {
+    let total = checked_add_u256(reserve_in.balance(), reserve_out.balance())?;
+    let swap_amount = checked_div_u256(
+        checked_add_u256(
+            checked_mul_u256(reserve_in.upscale(amount_in), reserve_out.balance())?,
+            total - 1,
+        )?,
+        total,
+    )?;
+
+    to_u64(reserve_in.downscale_ceil(swap_amount)?)
+}
+
+/// Returns the LP tokens minted for a deposit of `amount_in` of one token only.
+///
+/// The deposit moves the pool away from its current balance, so like a swap
+/// it pays half the swap fee on how far each balance ends up from the
+/// balance a proportional deposit would have left. LP tokens are minted for
+/// the growth of `D` after that fee.
+pub fn get_single_sided_lp_amount(
+    amount_in: u64,
+    reserve_in: Reserve,
+    reserve_out: Reserve,
+    lp_supply: u64,
+    amp: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> MathResult<u64> {
+    let balance_in = reserve_in.balance();
+    let balance_out = reserve_out.balance();
+    let d_0 = compute_d(amp, balance_in, balance_out)?;
+    let new_balance_in = checked_add_u256(balance_in, reserve_in.upscale(amount_in))?;
+    let d_1 = compute_d(amp, new_balance_in, balance_out)?;
+
+    let charge_fee = |balance: U256, new_balance: U256| -> MathResult<U256> {
+        let ideal_balance = checked_div_u256(checked_mul_u256(d_1, balance)?, d_0)?;
+        let difference = abs_diff(ideal_balance, new_balance);
+        let fee = checked_div_u256(
+            checked_mul_u256(difference, U256::from(fee_numerator))?,
+            U256::from(fee_denominator) * 2,
+        )?;
+
+        checked_sub_u256(new_balance, fee)
+    };
+
+    let d_2 = compute_d(
+        amp,
+        charge_fee(balance_in, new_balance_in)?,
+        charge_fee(balance_out, balance_out)?,
+    )?;
+
+    to_u64(checked_div_u256(
+        checked_mul_u256(U256::from(lp_supply), d_2.saturating_sub(d_0))?,
+        d_0,
+    )?)
+}
+
+fn abs_diff(a: U256, b: U256) -> U256 {
+    if a > b {
+        a - b
+    } else {
+        b - a
+    }
+}
+
+fn to_u64(a: U256) -> MathResult<u64> {
+    if a > U256::from(u64::MAX) {
+        return Err(SeaAmmError::MathOverflow);
+    }
+
+    Ok(a.as_u64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // deterministic xorshift, so failures reproduce
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn between(&mut self, low: u64, high: u64) -> u64 {
+            low + self.next() % (high - low + 1)
+        }
+    }
+
+    fn d(amp: u64, reserve_a: u64, reserve_b: u64) -> U256 {
+        compute_d(amp, U256::from(reserve_a), U256::from(reserve_b)).unwrap()
+    }
+
+    // a reserve of a token with as many decimals as the other one
+    fn reserve(amount: u64) -> Reserve {
+        Reserve::new(amount, 1)
+    }
+
+    #[test]
+    fn amp_ramps_linearly() {
+        assert_eq!(get_amp(100, 200, 1_000, 2_000, 500), 100);
+        assert_eq!(get_amp(100, 200, 1_000, 2_000, 1_250), 125);
+        assert_eq!(get_amp(100, 200, 1_000, 2_000, 3_000), 200);
+        assert_eq!(get_amp(200, 100, 1_000, 2_000, 1_750), 125);
+        // a pool that never ramped
+        assert_eq!(get_amp(100, 100, 0, 0, 1_000), 100);
+    }
+
+    #[test]
+    fn ramps_are_bounded() {
+        // a pool that never ramped
+        let now = MIN_RAMP_DURATION;
+        let end = now + MIN_RAMP_DURATION;
+        assert!(check_ramp(100, 1_000, 0, now, end).is_ok());
+        assert!(check_ramp(100, 10, 0, now, end).is_ok());
+        assert!(check_ramp(100, 1_001, 0, now, end).is_err());
+        assert!(check_ramp(100, 9, 0, now, end).is_err());
+        assert!(check_ramp(100, 200, 0, now, end - 1).is_err());
+        assert!(check_ramp(MAX_AMP, MAX_AMP + 1, 0, now, end).is_err());
+    }
+
+    #[test]
+    fn ramps_wait_for_the_last_one() {
+        let start = 1_000;
+        let now = start + MIN_RAMP_DURATION;
+        assert!(check_ramp(100, 1_000, start, now, now + MIN_RAMP_DURATION).is_ok());
+        assert!(check_ramp(100, 1_000, start, now - 1, now + MIN_RAMP_DURATION).is_err());
+    }
+
+    #[test]
+    fn d_of_a_balanced_pool_is_the_sum() {
+        for amp in [1, 100, MAX_AMP] {
+            assert_eq!(
+                d(amp, 1_000_000_000, 1_000_000_000),
+                U256::from(2_000_000_000u64)
+            );
+        }
+        assert_eq!(d(100, 0, 0), U256::zero());
+    }
+
+    #[test]
+    fn y_solves_the_invariant() {
+        let y = compute_y(
+            100,
+            U256::from(3_000_000_000u64),
+            d(100, 3_000_000_000, 1_000_000_000),
+        )
+        .unwrap();
+        assert!(abs_diff(y, U256::from(1_000_000_000u64)) <= U256::one());
+    }
+
+    #[test]
+    fn stable_swap_beats_constant_product_near_the_peg() {
+        let reserve_amount = 1_000_000_000_000;
+        let amount_in = 10_000_000_000;
+        let stable = get_amount_out(
+            amount_in,
+            reserve(reserve_amount),
+            reserve(reserve_amount),
+            100,
+            3,
+            1_000,
+        )
+        .unwrap();
+        let constant_product =
+            crate::math::get_amount_out(amount_in, reserve_amount, reserve_amount, 3, 1_000)
+                .unwrap();
+        assert!(stable > constant_product);
+        // close to 1:1 less the fee
+        assert!(stable > amount_in * 996 / 1_000);
+    }
+
+    #[test]
+    fn amount_in_covers_amount_out() {
+        let (reserve_in, reserve_out) = (reserve(5_000_000_000), reserve(7_000_000_000));
+        let amount_in =
+            get_amount_in(1_000_000_000, reserve_in, reserve_out, 50, 3, 1_000).unwrap();
+        let amount_out = get_amount_out(amount_in, reserve_in, reserve_out, 50, 3, 1_000).unwrap();
+        assert!(amount_out >= 1_000_000_000 - 2);
+    }
+
+    #[test]
+    fn swaps_never_decrease_the_invariant() {
+        let mut rng = Rng(0x5eed);
+        for _ in 0..500 {
+            let amp = rng.between(MIN_AMP, 10_000);
+            let reserve_in = rng.between(1_000, 1_000_000_000_000_000);
+            let reserve_out = rng.between(1_000, 1_000_000_000_000_000);
+            let amount_in = rng.between(1, reserve_in);
+            let fee_numerator = rng.between(0, 100);
+
+            let d_before = d(amp, reserve_in, reserve_out);
+            let amount_out = get_amount_out(
+                amount_in,
+                reserve(reserve_in),
+                reserve(reserve_out),
+                amp,
+                fee_numerator,
+                10_000,
+            )
+            .unwrap();
+            let d_after = d(amp, reserve_in + amount_in, reserve_out - amount_out);
+            assert!(d_after >= d_before);
+        }
+    }
+
+    #[test]
+    fn exact_out_swaps_never_decrease_the_invariant() {
+        let mut rng = Rng(0xfee);
+        for _ in 0..500 {
+            let amp = rng.between(MIN_AMP, 10_000);
+            let reserve_in = rng.between(1_000, 1_000_000_000_000_000);
+            let reserve_out = rng.between(1_000, 1_000_000_000_000_000);
+            let amount_out = rng.between(1, reserve_out / 2);
+            let fee_numerator = rng.between(0, 100);
+
+            let d_before = d(amp, reserve_in, reserve_out);
+            let Ok(amount_in) = get_amount_in(
+                amount_out,
+                reserve(reserve_in),
+                reserve(reserve_out),
+                amp,
+                fee_numerator,
+                10_000,
+            ) else {
+                continue;
+            };
+            let Some(new_reserve_in) = reserve_in.checked_add(amount_in) else {
+                continue;
+            };
+            let d_after = d(amp, new_reserve_in, reserve_out - amount_out);
+            assert!(d_after >= d_before);
+        }
+    }
+
+    #[test]
+    fn single_sided_deposits_never_dilute_lp_tokens() {
+        let mut rng = Rng(0xd1);
+        for _ in 0..500 {
+            let amp = rng.between(MIN_AMP, 10_000);
+            let reserve_in = rng.between(1_000_000, 1_000_000_000_000_000);
+            let reserve_out = rng.between(1_000_000, 1_000_000_000_000_000);
+            let lp_supply = rng.between(1_000_000, 1_000_000_000_000_000);
+            let amount_in = rng.between(1, reserve_in);
+
+            let lp_amount = get_single_sided_lp_amount(
+                amount_in,
+                reserve(reserve_in),
+                reserve(reserve_out),
+                lp_supply,
+                amp,
+                3,
+                1_000,
+            )
+            .unwrap();
+
+            // D per lp token doesn't drop
+            let d_before = d(amp, reserve_in, reserve_out);
+            let d_after = d(amp, reserve_in + amount_in, reserve_out);
+            assert!(
+                d_after * U256::from(lp_supply) >= d_before * U256::from(lp_supply + lp_amount)
+            );
+        }
+    }
+
+    #[test]
+    fn precision_multipliers_scale_to_the_larger_decimals() {
+        assert_eq!(get_precision_multipliers(6, 9).unwrap(), (1_000, 1));
+        assert_eq!(get_precision_multipliers(9, 6).unwrap(), (1, 1_000));
+        assert_eq!(get_precision_multipliers(6, 6).unwrap(), (1, 1));
+        assert_eq!(
+            get_precision_multipliers(0, 19).unwrap(),
+            (10_000_000_000_000_000_000, 1)
+        );
+        assert!(get_precision_multipliers(0, 20).is_err());
+    }
+
+    #[test]
+    fn swaps_between_tokens_of_different_decimals_stay_near_the_peg() {
+        // a million of a 6 decimal token against a million of a 9 decimal one
+        let reserve_a = Reserve::new(1_000_000_000_000, 1_000);
+        let reserve_b = Reserve::new(1_000_000_000_000_000, 1);
+
+        // 1_000 a buys close to 1_000 b less the fee, and back
+        let amount_out =
+            get_amount_out(1_000_000_000, reserve_a, reserve_b, 100, 3, 1_000).unwrap();
+        assert!(amount_out < 1_000_000_000_000);
+        assert!(amount_out > 996_000_000_000);
+        let amount_out =
+            get_amount_out(1_000_000_000_000, reserve_b, reserve_a, 100, 3, 1_000).unwrap();
+        assert!(amount_out < 1_000_000_000);
+        assert!(amount_out > 996_000_000);
+
+        let amount_in = get_amount_in(1_000_000_000, reserve_b, reserve_a, 100, 3, 1_000).unwrap();
+        assert!(amount_in > 1_000_000_000_000);
+        assert!(amount_in < 1_004_000_000_000);
+
+        // the first deposit mints D in the precision of the 9 decimal token
+        let lp_amount = get_initial_lp_amount(100, reserve_a, reserve_b).unwrap();
+        assert_eq!(lp_amount, 2_000_000_000_000_000);
+    }
+
+    #[test]
+    fn swaps_between_tokens_of_different_decimals_never_decrease_the_invariant() {
+        let mut rng = Rng(0xdec);
+        for _ in 0..500 {
+            let amp = rng.between(MIN_AMP, 10_000);
+            let (multiplier_in, multiplier_out) = if rng.next().is_multiple_of(2) {
+                (1_000, 1)
+            } else {
+                (1, 1_000)
+            };
+            let reserve_in = rng.between(1_000, 1_000_000_000_000_000 / multiplier_in);
+            let reserve_out = rng.between(1_000, 1_000_000_000_000_000 / multiplier_out);
+            let amount_in = rng.between(1, reserve_in);
+
+            let amount_out = get_amount_out(
+                amount_in,
+                Reserve::new(reserve_in, multiplier_in),
+                Reserve::new(reserve_out, multiplier_out),
+                amp,
+                3,
+                1_000,
+            )
+            .unwrap();
+
+            let d_before = d(
+                amp,
+                reserve_in * multiplier_in,
+                reserve_out * multiplier_out,
+            );
+            let d_after = d(
+                amp,
+                (reserve_in + amount_in) * multiplier_in,
+                (reserve_out - amount_out) * multiplier_out,
+            );
+            assert!(d_after >= d_before);
+        }
+    }
+}
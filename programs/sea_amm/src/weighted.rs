@@ -0,0 +1,480 @@
+//! Weighted product curve math, for pools whose tokens have unequal weights.
+//!
+//! A pool with balances `x` and `y` and normalized weights `w_x + w_y = 1`
+//! keeps `x^w_x * y^w_y` constant through swaps, so it always holds `w_x` of
+//! its value in `x`. A 50/50 pool is the constant product curve. Powers with
+//! fractional exponents are taken in 18 decimal fixed point as
+//! `exp(e * ln(b))`, and rounded against the trader by the error bound of
+//! that approximation.
+
+use crate::error::{MathResult, SeaAmmError};
+use crate::math::{checked_add, checked_div, checked_div_ceil, checked_mul, checked_sub, to_u64};
+use anchor_lang::prelude::*;
+
+/// Fixed point one.
+pub const WAD: u128 = 1_000_000_000_000_000_000;
+/// Sum of the two weights, so each weight is a percentage.
+pub const TOTAL_WEIGHT: u64 = 100;
+/// Smallest weight either token can have. The other token's weight is then at
+/// most `TOTAL_WEIGHT - MIN_WEIGHT`, which bounds the exponents of the curve.
+pub const MIN_WEIGHT: u64 = 1;
+/// Largest share of a reserve one swap or deposit can move, in percent. Keeps
+/// the powers in the range where they are accurate.
+pub const MAX_TRADE_PERCENT: u64 = 30;
+
+const LN_2: i128 = 693_147_180_559_945_309;
+// relative error bound of `pow`, well above what ln and exp lose
+const MAX_POW_RELATIVE_ERROR: u128 = 10_000;
+
+/// Returns whether `weight_a` and `weight_b` are a valid pair of weights.
+pub fn check_weights(weight_a: u64, weight_b: u64) -> MathResult<bool> {
+    Ok(
+        checked_add(weight_a as u128, weight_b as u128)? == TOTAL_WEIGHT as u128
+            && weight_a >= MIN_WEIGHT
+            && weight_b >= MIN_WEIGHT,
+    )
+}
+
+/// Returns the amount of the output token paid out for `amount_in` of the
+/// input token, `y * (1 - (x / (x + dx))^(w_x / w_y))`, with the fee taken
+/// from the input before pricing.
+pub fn get_amount_out(
+    amount_in: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    weight_in: u64,
+    weight_out: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> MathResult<u64> {
+    check_trade_size(amount_in, reserve_in)?;
+
+    let fee_multiplier = checked_sub(fee_denominator as u128, fee_numerator as u128)?;
+    let amount_in_after_fee = checked_div(
+        checked_mul(amount_in as u128, fee_multiplier)?,
+        fee_denominator as u128,
+    )?;
+
+    let base = checked_div_ceil(
+        checked_mul(reserve_in as u128, WAD)?,
+        checked_add(reserve_in as u128, amount_in_after_fee)?,
+    )?;
+    let exponent = checked_div(checked_mul(weight_in as u128, WAD)?, weight_out as u128)?;
+    let power = pow_up(base, exponent)?.min(WAD);
+
+    to_u64(checked_div(
+        checked_mul(reserve_out as u128, WAD - power)?,
+        WAD,
+    )?)
+}
+
+/// Returns the amount of the input token needed to get exactly `amount_out`
+/// of the output token, `x * ((y / (y - dy))^(w_y / w_x) - 1)`, fee included
+/// and rounded up.
+pub fn get_amount_in(
+    amount_out: u64,
+    reserve_in: u64,
+    reserve_out: u64,
+    weight_in: u64,
+    weight_out: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> MathResult<u64> {
+    check_trade_size(amount_out, reserve_out)?;
+
+    let base = checked_div_ceil(
+        checked_mul(reserve_out as u128, WAD)?,
+        checked_sub(reserve_out as u128, amount_out as u128)?,
+    )?;
+    let exponent = checked_div_ceil(checked_mul(weight_out as u128, WAD)?, weight_in as u128)?;
+    let power = pow_up(base, exponent)?;
+    let amount_in_after_fee = checked_div_ceil(
+        checked_mul(reserve_in as u128, checked_sub(power, WAD)?)?,
+        WAD,
+    )?;
+
+    let fee_multiplier = checked_sub(fee_denominator as u128, fee_numerator as u128)?;
+
+    to_u64(checked_div_ceil(
+        checked_mul(amount_in_after_fee, fee_denominator as u128)?,
+        fee_multiplier,
+    )?)
+}
+
+/// Returns the LP tokens minted for the first deposit into a weighted pool,
+/// the weighted geometric mean `a^w_a * b^w_b`. For equal weights that is
+/// `sqrt(a * b)`, as on the constant product curve.
+pub fn get_initial_lp_amount(
+    amount_a: u64,
+    amount_b: u64,
+    weight_a: u64,
+    weight_b: u64,
+) -> MathResult<u64> {
+    let total_weight = checked_add(weight_a as u128, weight_b as u128)? as i128;
+    let ln_a = ln(checked_mul(amount_a as u128, WAD)?)?;
+    let ln_b = ln(checked_mul(amount_b as u128, WAD)?)?;
+    let weighted_ln = checked_add_i128(
+        checked_mul_i128(ln_a, weight_a as i128)?,
+        checked_mul_i128(ln_b, weight_b as i128)?,
+    )? / total_weight;
+    let mean = exp(weighted_ln)?;
+
+    to_u64(round_down(mean)? / WAD)
+}
+
+/// Returns the geometric mean of `amounts`, the LP tokens minted for the
+/// first deposit into a multi token pool, which weighs its tokens equally.
+pub fn get_geometric_mean(amounts: &[u64]) -> MathResult<u64> {
+    let mut ln_sum: i128 = 0;
+    for amount in amounts {
+        ln_sum = checked_add_i128(ln_sum, ln(checked_mul(*amount as u128, WAD)?)?)?;
+    }
+    let mean = exp(ln_sum / amounts.len() as i128)?;
+
+    to_u64(round_down(mean)? / WAD)
+}
+
+/// Returns the part of a single sided deposit of `amount_in` that a
+/// proportional deposit would have put in the other token, rounded up.
+pub fn get_single_sided_swap_amount(
+    amount_in: u64,
+    weight_in: u64,
+    weight_out: u64,
+) -> MathResult<u64> {
+    to_u64(checked_div_ceil(
+        checked_mul(amount_in as u128, weight_out as u128)?,
+        checked_add(weight_in as u128, weight_out as u128)?,
+    )?)
+}
+
+/// Returns the LP tokens minted for a deposit of `amount_in` of one token
+/// only, `supply * ((1 + dx / x)^w_x - 1)`.
+///
+/// Only the part of the deposit that a proportional deposit would have put
+/// in the other token, `1 - w_x` of it, is in effect swapped, so the swap
+/// fee is charged on that part only.
+pub fn get_single_sided_lp_amount(
+    amount_in: u64,
+    reserve_in: u64,
+    lp_supply: u64,
+    weight_in: u64,
+    weight_out: u64,
+    fee_numerator: u64,
+    fee_denominator: u64,
+) -> MathResult<u64> {
+    check_trade_size(amount_in, reserve_in)?;
+
+    let total_weight = checked_add(weight_in as u128, weight_out as u128)?;
+    let swapped_amount = get_single_sided_swap_amount(amount_in, weight_in, weight_out)?;
+    let fee = checked_div_ceil(
+        checked_mul(swapped_amount as u128, fee_numerator as u128)?,
+        fee_denominator as u128,
+    )?;
+    let amount_in_after_fee = checked_sub(amount_in as u128, fee)?;
+
+    let base = checked_div(
+        checked_mul(checked_add(reserve_in as u128, amount_in_after_fee)?, WAD)?,
+        reserve_in as u128,
+    )?;
+    let exponent = checked_div(checked_mul(weight_in as u128, WAD)?, total_weight)?;
+    let power = pow_down(base, exponent)?;
+
+    to_u64(checked_div(
+        checked_mul(lp_supply as u128, power.saturating_sub(WAD))?,
+        WAD,
+    )?)
+}
+
+fn check_trade_size(amount: u64, reserve: u64) -> MathResult<()> {
+    if checked_mul(amount as u128, 100)? > checked_mul(reserve as u128, MAX_TRADE_PERCENT as u128)?
+    {
+        return Err(SeaAmmError::TradeTooLarge);
+    }
+
+    Ok(())
+}
+
+/// Returns `base^exponent`, both in fixed point, rounded up.
+fn pow_up(base: u128, exponent: u128) -> MathResult<u128> {
+    let power = pow(base, exponent)?;
+
+    checked_add(
+        checked_add(power, checked_mul(power, MAX_POW_RELATIVE_ERROR)? / WAD)?,
+        1,
+    )
+}
+
+/// Returns `base^exponent`, both in fixed point, rounded down.
+fn pow_down(base: u128, exponent: u128) -> MathResult<u128> {
+    round_down(pow(base, exponent)?)
+}
+
+fn round_down(value: u128) -> MathResult<u128> {
+    Ok(value
+        .saturating_sub(checked_mul(value, MAX_POW_RELATIVE_ERROR)? / WAD)
+        .saturating_sub(1))
+}
+
+fn pow(base: u128, exponent: u128) -> MathResult<u128> {
+    if base == WAD || exponent == 0 {
+        return Ok(WAD);
+    }
+
+    let exponent = i128::try_from(exponent).map_err(|_| SeaAmmError::MathOverflow)?;
+
+    exp(checked_mul_i128(ln(base)?, exponent)? / WAD as i128)
+}
+
+/// Returns `ln(x)` of a positive fixed point `x`.
+fn ln(x: u128) -> MathResult<i128> {
+    if x == 0 {
+        return Err(SeaAmmError::MathOverflow);
+    }
+
+    // x = m * 2^k with m in [1, 2)
+    let mut k: i128 = 0;
+    let mut m = x;
+    while m >= 2 * WAD {
+        m >>= 1;
+        k += 1;
+    }
+    while m < WAD {
+        m <<= 1;
+        k -= 1;
+    }
+
+    // ln(m) = 2 * atanh(z) = 2 * (z + z^3 / 3 + z^5 / 5 + ...), z = (m - 1) / (m + 1)
+    let z = ((m - WAD) * WAD / (m + WAD)) as i128;
+    let z_squared = z * z / WAD as i128;
+    let mut term = z;
+    let mut sum = 0;
+    let mut n = 1;
+    while term != 0 {
+        sum += term / n;
+        term = term * z_squared / WAD as i128;
+        n += 2;
+    }
+
+    Ok(k * LN_2 + 2 * sum)
+}
+
+/// Returns `e^x` of a fixed point `x`.
+fn exp(x: i128) -> MathResult<u128> {
+    // e^x = e^r * 2^k with r in [0, ln 2)
+    let k = x.div_euclid(LN_2);
+    let r = x.rem_euclid(LN_2);
+
+    let mut term = WAD as i128;
+    let mut sum = term;
+    let mut n = 1;
+    while term != 0 {
+        term = term * r / WAD as i128 / n;
+        sum += term;
+        n += 1;
+    }
+
+    let sum = sum as u128;
+    if k >= 0 {
+        // e^r < 2, so the result fits if 2 * 2^k does
+        if k >= sum.leading_zeros() as i128 {
+            return Err(SeaAmmError::MathOverflow);
+        }
+
+        Ok(sum << k)
+    } else if k > -128 {
+        Ok(sum >> -k)
+    } else {
+        Ok(0)
+    }
+}
+
+fn checked_add_i128(a: i128, b: i128) -> MathResult<i128> {
+    a.checked_add(b).ok_or(SeaAmmError::MathOverflow)
+}
+
+fn checked_mul_i128(a: i128, b: i128) -> MathResult<i128> {
+    a.checked_mul(b).ok_or(SeaAmmError::MathOverflow)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // xorshift with a fixed seed per test, so failures reproduce
+    struct Rng(u64);
+
+    impl Rng {
+        fn between(&mut self, low: u64, high: u64) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            low + self.0 % (high - low + 1)
+        }
+    }
+
+    // ln of the invariant, scaled by the total weight
+    fn ln_invariant(x: u64, y: u64, weight_x: u64, weight_y: u64) -> i128 {
+        ln(x as u128 * WAD).unwrap() * weight_x as i128
+            + ln(y as u128 * WAD).unwrap() * weight_y as i128
+    }
+
+    #[test]
+    fn ln_and_exp_are_accurate() {
+        assert_eq!(ln(WAD).unwrap(), 0);
+        assert!((ln(2 * WAD).unwrap() - LN_2).abs() <= 1);
+        assert!((exp(LN_2).unwrap() as i128 - 2 * WAD as i128).abs() <= 10);
+        // 1.5^2.5 = 2.755675960631075...
+        let power = pow(3 * WAD / 2, 5 * WAD / 2).unwrap();
+        assert!(power.abs_diff(2_755_675_960_631_075_000) < 1_000_000);
+    }
+
+    #[test]
+    fn equal_weights_match_constant_product() {
+        let (reserve_in, reserve_out) = (1_000_000_000_000, 4_000_000_000_000);
+        let weighted =
+            get_amount_out(10_000_000_000, reserve_in, reserve_out, 50, 50, 3, 1_000).unwrap();
+        let constant_product =
+            crate::math::get_amount_out(10_000_000_000, reserve_in, reserve_out, 3, 1_000).unwrap();
+        assert!(weighted <= constant_product);
+        assert!(weighted >= constant_product - constant_product / 1_000_000_000);
+
+        assert_eq!(
+            get_initial_lp_amount(4_000_000, 9_000_000, 1, 1).unwrap(),
+            crate::math::get_initial_lp_amount(4_000_000, 9_000_000).unwrap() - 1
+        );
+    }
+
+    #[test]
+    fn geometric_mean_of_three_amounts() {
+        // (1 * 8 * 27)^(1/3) = 6, less the rounding margin
+        assert_eq!(
+            get_geometric_mean(&[1_000_000, 8_000_000, 27_000_000]).unwrap(),
+            5_999_999
+        );
+        assert!(get_geometric_mean(&[1_000_000, 0, 27_000_000]).is_err());
+    }
+
+    #[test]
+    fn price_follows_the_weights() {
+        // 80/20 with 80% of the value in token a prices the tokens equally
+        let amount_out =
+            get_amount_out(1_000_000, 800_000_000_000, 200_000_000_000, 80, 20, 0, 1).unwrap();
+        // less a few units of slippage
+        assert!(amount_out < 1_000_000 && amount_out > 999_990);
+    }
+
+    #[test]
+    fn trades_are_bounded() {
+        assert!(get_amount_out(301, 1_000, 1_000, 80, 20, 3, 1_000).is_err());
+        assert!(get_amount_in(301, 1_000, 1_000, 80, 20, 3, 1_000).is_err());
+    }
+
+    #[test]
+    fn weights_are_bounded() {
+        assert!(check_weights(80, 20).unwrap());
+        assert!(check_weights(99, 1).unwrap());
+        assert!(check_weights(1, 99).unwrap());
+        assert!(!check_weights(100, 0).unwrap());
+        assert!(!check_weights(0, 100).unwrap());
+        assert!(!check_weights(0, 0).unwrap());
+
+        // the weights are percentages, so they have to add up to 100
+        assert!(!check_weights(100, 1).unwrap());
+        assert!(!check_weights(8, 2).unwrap());
+        assert!(!check_weights(98, 1).unwrap());
+        assert!(!check_weights(u64::MAX, u64::MAX).unwrap());
+        assert!(!check_weights(u64::MAX - 99, 100).unwrap());
+    }
+
+    #[test]
+    fn swaps_never_decrease_the_invariant() {
+        let mut rng = Rng(0xba1);
+        for _ in 0..500 {
+            let weight_in = rng.between(1, 99);
+            let weight_out = 100 - weight_in;
+            let reserve_in = rng.between(1_000, 1_000_000_000_000_000);
+            let reserve_out = rng.between(1_000, 1_000_000_000_000_000);
+            let amount_in = rng.between(1, reserve_in * 3 / 10);
+            let fee_numerator = rng.between(0, 100);
+
+            let amount_out = get_amount_out(
+                amount_in,
+                reserve_in,
+                reserve_out,
+                weight_in,
+                weight_out,
+                fee_numerator,
+                10_000,
+            )
+            .unwrap();
+            assert!(
+                ln_invariant(
+                    reserve_in + amount_in,
+                    reserve_out - amount_out,
+                    weight_in,
+                    weight_out
+                ) >= ln_invariant(reserve_in, reserve_out, weight_in, weight_out)
+            );
+        }
+    }
+
+    #[test]
+    fn exact_out_swaps_never_decrease_the_invariant() {
+        let mut rng = Rng(0xa2c);
+        for _ in 0..500 {
+            let weight_in = rng.between(1, 99);
+            let weight_out = 100 - weight_in;
+            let reserve_in = rng.between(1_000, 1_000_000_000_000_000);
+            let reserve_out = rng.between(1_000, 1_000_000_000_000_000);
+            let amount_out = rng.between(1, reserve_out * 3 / 10);
+            let fee_numerator = rng.between(0, 100);
+
+            let Ok(amount_in) = get_amount_in(
+                amount_out,
+                reserve_in,
+                reserve_out,
+                weight_in,
+                weight_out,
+                fee_numerator,
+                10_000,
+            ) else {
+                continue;
+            };
+            assert!(
+                ln_invariant(
+                    reserve_in + amount_in,
+                    reserve_out - amount_out,
+                    weight_in,
+                    weight_out
+                ) >= ln_invariant(reserve_in, reserve_out, weight_in, weight_out)
+            );
+        }
+    }
+
+    #[test]
+    fn single_sided_deposits_never_dilute_lp_tokens() {
+        let mut rng = Rng(0x10d);
+        for _ in 0..500 {
+            let weight_in = rng.between(1, 99);
+            let weight_out = 100 - weight_in;
+            let reserve_in = rng.between(1_000_000, 1_000_000_000_000_000);
+            let reserve_out = rng.between(1_000_000, 1_000_000_000_000_000);
+            let lp_supply = rng.between(1_000_000, 1_000_000_000_000_000);
+            let amount_in = rng.between(1, reserve_in * 3 / 10);
+
+            let lp_amount = get_single_sided_lp_amount(
+                amount_in, reserve_in, lp_supply, weight_in, weight_out, 3, 1_000,
+            )
+            .unwrap();
+
+            // the invariant per lp token doesn't drop, compared in ln
+            let ln_supply = |supply: u64| ln(supply as u128 * WAD).unwrap() * 100;
+            assert!(
+                ln_invariant(reserve_in + amount_in, reserve_out, weight_in, weight_out)
+                    - ln_supply(lp_supply + lp_amount)
+                    >= ln_invariant(reserve_in, reserve_out, weight_in, weight_out)
+                        - ln_supply(lp_supply)
+            );
+        }
+    }
+}
@@ -1,6 +1,13 @@
 import * as anchor from "@project-serum/anchor";
 import { Program } from "@project-serum/anchor";
-import { createAccount, createMint, getMint, mintTo } from "@solana/spl-token";
+import {
+	createAccount,
+	createMint,
+	getAccount,
+	getMint,
+	mintTo,
+} from "@solana/spl-token";
+import { expect } from "chai";
 import { SeaAmm } from "../target/types/sea_amm";
 
 // what is dy given dx?
@@ -22,6 +29,35 @@ describe("sea_amm", () => {
 	const user = anchor.web3.Keypair.generate();
 	const userPk = user.publicKey;
 
+	const admin = anchor.web3.Keypair.generate();
+	const adminPk = admin.publicKey;
+	const [configPk] = anchor.web3.PublicKey.findProgramAddressSync(
+		[Buffer.from("config")],
+		programIdPk
+	);
+	// only the upgrade authority of the program can create the config
+	const [programDataPk] = anchor.web3.PublicKey.findProgramAddressSync(
+		[programIdPk.toBuffer()],
+		new anchor.web3.PublicKey("BPFLoaderUpgradeab1e11111111111111111111111")
+	);
+
+	// 0.3% swap fee, a sixth of which goes to the protocol
+	const feeNumerator = new anchor.BN(3);
+	const feeDenominator = new anchor.BN(1000);
+	const protocolFeeNumerator = new anchor.BN(1);
+	const protocolFeeDenominator = new anchor.BN(6);
+
+	// pools can be created at 0.3% or 0.05% for 0.01 sol
+	const feeTiers = [
+		{ feeNumerator, feeDenominator },
+		{ feeNumerator: new anchor.BN(5), feeDenominator: new anchor.BN(10_000) },
+	];
+	const poolCreationFee = new anchor.BN(anchor.web3.LAMPORTS_PER_SOL / 100);
+
+	const constantProduct = { constantProduct: {} } as any;
+	const stableSwap = { stableSwap: {} } as any;
+	const weighted = { weighted: {} } as any;
+
 	let poolPk: anchor.web3.PublicKey;
 	let tokenMintAPk: anchor.web3.PublicKey;
 	let tokenMintBPk: anchor.web3.PublicKey;
@@ -29,9 +65,260 @@ describe("sea_amm", () => {
 	let userTokenAccountAPk: anchor.web3.PublicKey;
 	let userTokenAccountBPk: anchor.web3.PublicKey;
 	let userLPTokenAccountPk: anchor.web3.PublicKey;
+	let lockedLPTokenAccountPk: anchor.web3.PublicKey;
 	let poolTokenVaultAPk: anchor.web3.PublicKey;
 	let poolTokenVaultBPk: anchor.web3.PublicKey;
 
+	const expectError = async (tx: Promise<string>, code: string) => {
+		try {
+			await tx;
+		} catch (err) {
+			expect(err).to.be.instanceOf(anchor.AnchorError);
+			expect(err.error.errorCode.code).to.equal(code);
+			return;
+		}
+		expect.fail(`transaction should have failed with ${code}`);
+	};
+
+	// the status tests every kind of pool shares, given how to set its status
+	// (as the authority unless another signer is passed), swap on it, deposit
+	// into it and each way of withdrawing from it
+	const itChecksPoolStatus = (calls: {
+		setStatus: (
+			status: object,
+			signer?: anchor.web3.Keypair
+		) => Promise<string>;
+		swap: () => Promise<string>;
+		deposit: () => Promise<string>;
+		withdrawals: (() => Promise<string>)[];
+	}) => {
+		const { setStatus, swap, deposit, withdrawals } = calls;
+
+		it("set status fails for another signer", async () => {
+			await expectError(setStatus({ frozen: {} }, user), "Unauthorized");
+		});
+
+		it("swaps paused still allows deposits and withdrawals", async () => {
+			await setStatus({ swapsPaused: {} });
+			await expectError(swap(), "SwapsPaused");
+			await deposit();
+			for (const withdraw of withdrawals) {
+				await withdraw();
+			}
+		});
+
+		it("deposits paused still allows swaps and withdrawals", async () => {
+			await setStatus({ depositsPaused: {} });
+			await expectError(deposit(), "DepositsPaused");
+			await swap();
+			for (const withdraw of withdrawals) {
+				await withdraw();
+			}
+		});
+
+		it("frozen stops withdrawals too", async () => {
+			await setStatus({ frozen: {} });
+			await expectError(swap(), "PoolFrozen");
+			await expectError(deposit(), "PoolFrozen");
+			for (const withdraw of withdrawals) {
+				await expectError(withdraw(), "PoolFrozen");
+			}
+		});
+
+		after(async () => {
+			await setStatus({ active: {} });
+		});
+	};
+
+	const initializeConfigAccounts = (
+		upgradeAuthorityPk: anchor.web3.PublicKey
+	) => ({
+		admin: adminPk,
+		config: configPk,
+		upgradeAuthority: upgradeAuthorityPk,
+		program: programIdPk,
+		programData: programDataPk,
+	});
+
+	const addLiquidityAccounts = () => ({
+		user: userPk,
+		pool: poolPk,
+		tokenMintA: tokenMintAPk,
+		tokenMintB: tokenMintBPk,
+		userTokenAccountA: userTokenAccountAPk,
+		userTokenAccountB: userTokenAccountBPk,
+		poolTokenVaultA: poolTokenVaultAPk,
+		poolTokenVaultB: poolTokenVaultBPk,
+		lpTokenMint: lpTokenMintPk,
+		lockedLpTokenAccount: lockedLPTokenAccountPk,
+		userLpTokenAccount: userLPTokenAccountPk,
+	});
+
+	const removeLiquidityAccounts = () => ({
+		user: userPk,
+		pool: poolPk,
+		tokenMintA: tokenMintAPk,
+		tokenMintB: tokenMintBPk,
+		userTokenAccountA: userTokenAccountAPk,
+		userTokenAccountB: userTokenAccountBPk,
+		poolTokenVaultA: poolTokenVaultAPk,
+		poolTokenVaultB: poolTokenVaultBPk,
+		lpTokenMint: lpTokenMintPk,
+		userLpTokenAccount: userLPTokenAccountPk,
+	});
+
+	// swaps token a for token b
+	const swapAccounts = () => ({
+		user: userPk,
+		pool: poolPk,
+		userSource: userTokenAccountAPk,
+		userDestination: userTokenAccountBPk,
+		poolSourceVault: poolTokenVaultAPk,
+		poolDestinationVault: poolTokenVaultBPk,
+	});
+
+	// vault balances less the protocol fees held in them
+	const getReserves = async () => {
+		const pool = await program.account.pool.fetch(poolPk);
+		const vaultA = await getAccount(connection, poolTokenVaultAPk);
+		const vaultB = await getAccount(connection, poolTokenVaultBPk);
+		return [
+			new anchor.BN(vaultA.amount.toString()).sub(pool.protocolFeesA),
+			new anchor.BN(vaultB.amount.toString()).sub(pool.protocolFeesB),
+		];
+	};
+
+	// accounts create_pool derives for the mints in the given order and fee
+	const createPoolAccounts = (
+		tokenMintAPk: anchor.web3.PublicKey,
+		tokenMintBPk: anchor.web3.PublicKey,
+		poolFeeNumerator = feeNumerator,
+		poolFeeDenominator = feeDenominator
+	) => {
+		const findPda = (seeds: Buffer[]) =>
+			anchor.web3.PublicKey.findProgramAddressSync(seeds, programIdPk)[0];
+		const poolPk = findPda([
+			Buffer.from("pool"),
+			tokenMintAPk.toBuffer(),
+			tokenMintBPk.toBuffer(),
+			poolFeeNumerator.toArrayLike(Buffer, "le", 8),
+			poolFeeDenominator.toArrayLike(Buffer, "le", 8),
+		]);
+		const lpTokenMintPk = findPda([Buffer.from("lp-token-mint"), poolPk.toBuffer()]);
+		return {
+			config: configPk,
+			admin: adminPk,
+			pool: poolPk,
+			tokenMintA: tokenMintAPk,
+			tokenMintB: tokenMintBPk,
+			tokenVaultA: findPda([Buffer.from("token-vault-a"), poolPk.toBuffer()]),
+			tokenVaultB: findPda([Buffer.from("token-vault-b"), poolPk.toBuffer()]),
+			lpTokenMint: lpTokenMintPk,
+			lockedLpTokenAccount: findPda([
+				Buffer.from("locked-lp-token-account"),
+				lpTokenMintPk.toBuffer(),
+			]),
+		};
+	};
+
+	// token mint a must sort before token mint b, so a pair has one pool
+	const sortMints = (
+		mintXPk: anchor.web3.PublicKey,
+		mintYPk: anchor.web3.PublicKey
+	) =>
+		Buffer.compare(mintXPk.toBuffer(), mintYPk.toBuffer()) < 0
+			? [mintXPk, mintYPk]
+			: [mintYPk, mintXPk];
+
+	const getTokenAmount = async (tokenAccountPk: anchor.web3.PublicKey) => {
+		const tokenAccount = await getAccount(connection, tokenAccountPk);
+		return new anchor.BN(tokenAccount.amount.toString());
+	};
+
+	// a fresh pair of mints funded in the user's accounts, for a pool of its
+	// own, with the accounts to deposit and to swap x for y
+	const createUserPair = async (decimals = [6, 6]) => {
+		const [mintXPk, mintYPk] = sortMints(
+			await createMint(
+				connection,
+				authority,
+				authorityPk,
+				authorityPk,
+				decimals[0]
+			),
+			await createMint(
+				connection,
+				authority,
+				authorityPk,
+				authorityPk,
+				decimals[1]
+			)
+		);
+		const accounts = createPoolAccounts(mintXPk, mintYPk);
+		const userTokenAccountXPk = await createAccount(
+			connection,
+			authority,
+			mintXPk,
+			userPk
+		);
+		const userTokenAccountYPk = await createAccount(
+			connection,
+			authority,
+			mintYPk,
+			userPk
+		);
+		for (const [mintPk, tokenAccountPk] of [
+			[mintXPk, userTokenAccountXPk],
+			[mintYPk, userTokenAccountYPk],
+		]) {
+			// a thousand whole tokens of each
+			const mint = await getMint(connection, mintPk);
+			await mintTo(
+				connection,
+				authority,
+				mintPk,
+				tokenAccountPk,
+				authority,
+				1_000 * 10 ** mint.decimals
+			);
+		}
+		const [userLpTokenAccountPk] = anchor.web3.PublicKey.findProgramAddressSync(
+			[
+				Buffer.from("lp-token-account"),
+				accounts.lpTokenMint.toBuffer(),
+				userPk.toBuffer(),
+			],
+			programIdPk
+		);
+
+		return {
+			accounts,
+			userTokenAccountXPk,
+			userTokenAccountYPk,
+			addLiquidityAccounts: {
+				user: userPk,
+				pool: accounts.pool,
+				tokenMintA: mintXPk,
+				tokenMintB: mintYPk,
+				userTokenAccountA: userTokenAccountXPk,
+				userTokenAccountB: userTokenAccountYPk,
+				poolTokenVaultA: accounts.tokenVaultA,
+				poolTokenVaultB: accounts.tokenVaultB,
+				lpTokenMint: accounts.lpTokenMint,
+				lockedLpTokenAccount: accounts.lockedLpTokenAccount,
+				userLpTokenAccount: userLpTokenAccountPk,
+			},
+			swapAccounts: {
+				user: userPk,
+				pool: accounts.pool,
+				userSource: userTokenAccountXPk,
+				userDestination: userTokenAccountYPk,
+				poolSourceVault: accounts.tokenVaultA,
+				poolDestinationVault: accounts.tokenVaultB,
+			},
+		};
+	};
+
 	before(async () => {
 		// request airdrops
 		const authorityAirdropSign = await connection.requestAirdrop(
@@ -44,22 +331,16 @@ describe("sea_amm", () => {
 			anchor.web3.LAMPORTS_PER_SOL
 		);
 		await connection.confirmTransaction(userAirdropSign);
-
-		// create tokens
-		tokenMintAPk = await createMint(
-			connection,
-			authority,
-			authorityPk,
-			authorityPk,
-			6
+		const adminAirdropSign = await connection.requestAirdrop(
+			adminPk,
+			anchor.web3.LAMPORTS_PER_SOL
 		);
+		await connection.confirmTransaction(adminAirdropSign);
 
-		tokenMintBPk = await createMint(
-			connection,
-			authority,
-			authorityPk,
-			authorityPk,
-			6
+		// create tokens, in the order create_pool expects them
+		[tokenMintAPk, tokenMintBPk] = sortMints(
+			await createMint(connection, authority, authorityPk, authorityPk, 6),
+			await createMint(connection, authority, authorityPk, authorityPk, 6)
 		);
 
 		// create token accounts for user
@@ -103,26 +384,13 @@ describe("sea_amm", () => {
 		console.log("Mint B supply", mintAccountB.supply.toString());
 
 		// get pda accounts
-		[poolPk] = anchor.web3.PublicKey.findProgramAddressSync(
-			[Buffer.from("pool"), tokenMintAPk.toBuffer(), tokenMintBPk.toBuffer()],
-			programIdPk
-		);
-		[lpTokenMintPk] = anchor.web3.PublicKey.findProgramAddressSync(
-			[
-				Buffer.from("lp-token-mint"),
-				tokenMintAPk.toBuffer(),
-				tokenMintBPk.toBuffer(),
-			],
-			programIdPk
-		);
-		[poolTokenVaultAPk] = anchor.web3.PublicKey.findProgramAddressSync(
-			[Buffer.from("token-vault-a"), tokenMintAPk.toBuffer()],
-			programIdPk
-		);
-		[poolTokenVaultBPk] = anchor.web3.PublicKey.findProgramAddressSync(
-			[Buffer.from("token-vault-b"), tokenMintBPk.toBuffer()],
-			programIdPk
-		);
+		({
+			pool: poolPk,
+			tokenVaultA: poolTokenVaultAPk,
+			tokenVaultB: poolTokenVaultBPk,
+			lpTokenMint: lpTokenMintPk,
+			lockedLpTokenAccount: lockedLPTokenAccountPk,
+		} = createPoolAccounts(tokenMintAPk, tokenMintBPk));
 		[userLPTokenAccountPk] = anchor.web3.PublicKey.findProgramAddressSync(
 			[
 				Buffer.from("lp-token-account"),
@@ -133,20 +401,2221 @@ describe("sea_amm", () => {
 		);
 	});
 
+	it("initialize config fails without the upgrade authority", async () => {
+		await expectError(
+			program.methods
+				.initializeConfig(
+					feeTiers,
+					protocolFeeNumerator,
+					protocolFeeDenominator,
+					poolCreationFee
+				)
+				.accounts(initializeConfigAccounts(adminPk))
+				.signers([admin])
+				.rpc(),
+			"Unauthorized"
+		);
+	});
+
+	it("initialize config fails with a protocol fee above the swap fee", async () => {
+		await expectError(
+			program.methods
+				.initializeConfig(
+					feeTiers,
+					protocolFeeDenominator.addn(1),
+					protocolFeeDenominator,
+					poolCreationFee
+				)
+				.accounts(initializeConfigAccounts(provider.wallet.publicKey))
+				.signers([admin])
+				.rpc(),
+			"InvalidProtocolFee"
+		);
+	});
+
+	it("initialize config", async () => {
+		await program.methods
+			.initializeConfig(
+				feeTiers,
+				protocolFeeNumerator,
+				protocolFeeDenominator,
+				poolCreationFee
+			)
+			.accounts(initializeConfigAccounts(provider.wallet.publicKey))
+			.signers([admin])
+			.rpc();
+
+		const config = await program.account.config.fetch(configPk);
+		expect(config.admin.equals(adminPk)).to.be.true;
+		expect(config.feeTiers.length).to.equal(feeTiers.length);
+	});
+
+	it("update config fails for another signer", async () => {
+		await expectError(
+			program.methods
+				.updateConfig([], protocolFeeNumerator, protocolFeeDenominator, new anchor.BN(0))
+				.accounts({ admin: authorityPk, config: configPk })
+				.signers([authority])
+				.rpc(),
+			"Unauthorized"
+		);
+	});
+
+	it("create pool fails with a fee that is not a tier", async () => {
+		const [poolFeeNumerator, poolFeeDenominator] = [
+			new anchor.BN(1),
+			new anchor.BN(100),
+		];
+		await expectError(
+			program.methods
+				.createPool(
+					poolFeeNumerator,
+					poolFeeDenominator,
+					constantProduct,
+					new anchor.BN(0),
+					new anchor.BN(0),
+					new anchor.BN(0)
+				)
+				.accounts({
+					authority: authorityPk,
+					...createPoolAccounts(
+						tokenMintAPk,
+						tokenMintBPk,
+						poolFeeNumerator,
+						poolFeeDenominator
+					),
+				})
+				.signers([authority])
+				.rpc(),
+			"InvalidFeeTier"
+		);
+	});
+
+	it("create pool fails with unsorted mints", async () => {
+		await expectError(
+			program.methods
+				.createPool(
+					feeNumerator,
+					feeDenominator,
+					constantProduct,
+					new anchor.BN(0),
+					new anchor.BN(0),
+					new anchor.BN(0)
+				)
+				.accounts({
+					authority: authorityPk,
+					...createPoolAccounts(tokenMintBPk, tokenMintAPk),
+				})
+				.signers([authority])
+				.rpc(),
+			"UnsortedMints"
+		);
+	});
+
 	it("create pool", async () => {
+		const adminBalance = await connection.getBalance(adminPk);
 		const tx = await program.methods
-			.createPool()
+			.createPool(
+				feeNumerator,
+				feeDenominator,
+				constantProduct,
+				new anchor.BN(0),
+				new anchor.BN(0),
+				new anchor.BN(0)
+			)
 			.accounts({
 				authority: authorityPk,
-				pool: poolPk,
-				tokenMintA: tokenMintAPk,
-				tokenMintB: tokenMintBPk,
-				tokenVaultA: poolTokenVaultAPk,
-				tokenVaultB: poolTokenVaultBPk,
-				lpTokenMint: lpTokenMintPk,
+				...createPoolAccounts(tokenMintAPk, tokenMintBPk),
 			})
 			.signers([authority])
 			.rpc();
 		console.log("Your transaction signature", tx);
+
+		expect((await connection.getBalance(adminPk)) - adminBalance).to.equal(
+			poolCreationFee.toNumber()
+		);
+		const pool = await program.account.pool.fetch(poolPk);
+		expect(pool.protocolFeeNumerator.eq(protocolFeeNumerator)).to.be.true;
+		expect(pool.protocolFeeDenominator.eq(protocolFeeDenominator)).to.be.true;
+		const [, tokenVaultABump] = anchor.web3.PublicKey.findProgramAddressSync(
+			[Buffer.from("token-vault-a"), poolPk.toBuffer()],
+			programIdPk
+		);
+		expect(pool.tokenVaultABump).to.equal(tokenVaultABump);
+	});
+
+	it("create pool for the same mints at another fee tier", async () => {
+		const { feeNumerator: poolFeeNumerator, feeDenominator: poolFeeDenominator } =
+			feeTiers[1];
+		const accounts = createPoolAccounts(
+			tokenMintAPk,
+			tokenMintBPk,
+			poolFeeNumerator,
+			poolFeeDenominator
+		);
+
+		await program.methods
+			.createPool(
+				poolFeeNumerator,
+				poolFeeDenominator,
+				constantProduct,
+				new anchor.BN(0),
+				new anchor.BN(0),
+				new anchor.BN(0)
+			)
+			.accounts({ authority: authorityPk, ...accounts })
+			.signers([authority])
+			.rpc();
+
+		expect(accounts.pool.equals(poolPk)).to.be.false;
+		const pool = await program.account.pool.fetch(accounts.pool);
+		expect(pool.feeNumerator.eq(poolFeeNumerator)).to.be.true;
+		expect(pool.feeDenominator.eq(poolFeeDenominator)).to.be.true;
+	});
+
+	it("create another pool with token a", async () => {
+		const tokenMintCPk = await createMint(
+			connection,
+			authority,
+			authorityPk,
+			authorityPk,
+			6
+		);
+		const [mintXPk, mintYPk] = sortMints(tokenMintAPk, tokenMintCPk);
+		const accounts = createPoolAccounts(mintXPk, mintYPk);
+
+		await program.methods
+			.createPool(
+				feeNumerator,
+				feeDenominator,
+				constantProduct,
+				new anchor.BN(0),
+				new anchor.BN(0),
+				new anchor.BN(0)
+			)
+			.accounts({ authority: authorityPk, ...accounts })
+			.signers([authority])
+			.rpc();
+
+		// token a has a vault in each pool
+		const pool = await program.account.pool.fetch(accounts.pool);
+		const tokenAVaultPk = tokenMintAPk.equals(mintXPk)
+			? pool.tokenVaultA
+			: pool.tokenVaultB;
+		expect(tokenAVaultPk.equals(poolTokenVaultAPk)).to.be.false;
+		const tokenAVault = await getAccount(connection, tokenAVaultPk);
+		expect(tokenAVault.mint.toBase58()).to.equal(tokenMintAPk.toBase58());
+		expect(tokenAVault.owner.toBase58()).to.equal(accounts.pool.toBase58());
+	});
+
+	it("add initial liquidity", async () => {
+		// sqrt(4_000_000 * 9_000_000) = 6_000_000
+		await program.methods
+			.addLiquidity(
+				new anchor.BN(4_000_000),
+				new anchor.BN(9_000_000),
+				new anchor.BN(5_999_000)
+			)
+			.accounts(addLiquidityAccounts())
+			.signers([user])
+			.rpc();
+
+		const lpTokenMint = await getMint(connection, lpTokenMintPk);
+		expect(lpTokenMint.supply.toString()).to.equal("6000000");
+		const lockedLPTokenAccount = await getAccount(
+			connection,
+			lockedLPTokenAccountPk
+		);
+		expect(lockedLPTokenAccount.amount.toString()).to.equal("1000");
+		const userLPTokenAccount = await getAccount(connection, userLPTokenAccountPk);
+		expect(userLPTokenAccount.amount.toString()).to.equal("5999000");
+	});
+
+	it("add liquidity takes only what the pool ratio needs", async () => {
+		const [reserveA, reserveB] = await getReserves();
+		const userTokenAmountA = await getTokenAmount(userTokenAccountAPk);
+		const userTokenAmountB = await getTokenAmount(userTokenAccountBPk);
+		const maxAmountA = new anchor.BN(1_000_000);
+		// twice the token b the pool ratio needs for maxAmountA
+		const maxAmountB = maxAmountA.mul(reserveB).div(reserveA).mul(new anchor.BN(2));
+
+		await program.methods
+			.addLiquidity(maxAmountA, maxAmountB, new anchor.BN(0))
+			.accounts(addLiquidityAccounts())
+			.signers([user])
+			.rpc();
+
+		const newUserTokenAmountA = await getTokenAmount(userTokenAccountAPk);
+		const newUserTokenAmountB = await getTokenAmount(userTokenAccountBPk);
+		expect(userTokenAmountA.sub(newUserTokenAmountA).toString()).to.equal(
+			maxAmountA.toString()
+		);
+		const amountB = userTokenAmountB.sub(newUserTokenAmountB);
+		expect(amountB.lt(maxAmountB)).to.be.true;
+		// a / b stays at the pool ratio, up to rounding token b up
+		const [newReserveA, newReserveB] = await getReserves();
+		expect(newReserveA.mul(reserveB).lte(newReserveB.mul(reserveA))).to.be.true;
+	});
+
+	it("later deposits mint lp against pre-deposit reserves", async () => {
+		for (const [maxAmountA, maxAmountB] of [
+			[new anchor.BN(500_000), new anchor.BN(5_000_000)],
+			[new anchor.BN(3_000_000), new anchor.BN(250_000)],
+		]) {
+			const [reserveA, reserveB] = await getReserves();
+			const lpSupply = new anchor.BN(
+				(await getMint(connection, lpTokenMintPk)).supply.toString()
+			);
+			const userLPTokenAmount = await getTokenAmount(userLPTokenAccountPk);
+			const userTokenAmountA = await getTokenAmount(userTokenAccountAPk);
+			const userTokenAmountB = await getTokenAmount(userTokenAccountBPk);
+
+			await program.methods
+				.addLiquidity(maxAmountA, maxAmountB, new anchor.BN(0))
+				.accounts(addLiquidityAccounts())
+				.signers([user])
+				.rpc();
+
+			const amountA = userTokenAmountA.sub(await getTokenAmount(userTokenAccountAPk));
+			const amountB = userTokenAmountB.sub(await getTokenAmount(userTokenAccountBPk));
+			const expectedLPAmount = anchor.BN.min(
+				amountA.mul(lpSupply).div(reserveA),
+				amountB.mul(lpSupply).div(reserveB)
+			);
+			const lpAmount = (await getTokenAmount(userLPTokenAccountPk)).sub(
+				userLPTokenAmount
+			);
+			expect(lpAmount.toString()).to.equal(expectedLPAmount.toString());
+		}
+	});
+
+	it("same wallet deposits again into its lp account", async () => {
+		const amount = new anchor.BN(100_000);
+		let userLPTokenAmount = await getTokenAmount(userLPTokenAccountPk);
+		for (let i = 0; i < 3; i++) {
+			await program.methods
+				.addLiquidity(amount, amount.mul(new anchor.BN(3)), new anchor.BN(1))
+				.accounts(addLiquidityAccounts())
+				.signers([user])
+				.rpc();
+
+			const newUserLPTokenAmount = await getTokenAmount(userLPTokenAccountPk);
+			expect(newUserLPTokenAmount.gt(userLPTokenAmount)).to.be.true;
+			userLPTokenAmount = newUserLPTokenAmount;
+		}
+		const userLPTokenAccount = await getAccount(connection, userLPTokenAccountPk);
+		expect(userLPTokenAccount.owner.toBase58()).to.equal(userPk.toBase58());
+		expect(userLPTokenAccount.mint.toBase58()).to.equal(lpTokenMintPk.toBase58());
+	});
+
+	it("add liquidity fails below min lp out", async () => {
+		const amount = new anchor.BN(1_000);
+		await expectError(
+			program.methods
+				.addLiquidity(amount, amount, new anchor.BN(1_000_000_000))
+				.accounts(addLiquidityAccounts())
+				.signers([user])
+				.rpc(),
+			"SlippageExceeded"
+		);
+	});
+
+	it("swap a for b", async () => {
+		const amountIn = new anchor.BN(1_000_000);
+		const [reserveA, reserveB] = await getReserves();
+		const amountInAfterFee = amountIn
+			.mul(feeDenominator.sub(feeNumerator))
+			.div(feeDenominator);
+		const expectedAmountOut = reserveB
+			.mul(amountInAfterFee)
+			.div(reserveA.add(amountInAfterFee));
+		const userTokenAmountB = await getTokenAmount(userTokenAccountBPk);
+
+		await program.methods
+			.swap(amountIn, expectedAmountOut)
+			.accounts(swapAccounts())
+			.signers([user])
+			.rpc();
+
+		const [newReserveA, newReserveB] = await getReserves();
+		expect(newReserveA.toString()).to.equal(reserveA.add(amountIn).toString());
+		expect(newReserveB.toString()).to.equal(
+			reserveB.sub(expectedAmountOut).toString()
+		);
+		const newUserTokenAmountB = await getTokenAmount(userTokenAccountBPk);
+		expect(newUserTokenAmountB.sub(userTokenAmountB).toString()).to.equal(
+			expectedAmountOut.toString()
+		);
+	});
+
+	it("swap fails below minimum amount out", async () => {
+		await expectError(
+			program.methods
+				.swap(new anchor.BN(1_000), new anchor.BN(1_000_000))
+				.accounts(swapAccounts())
+				.signers([user])
+				.rpc(),
+			"SlippageExceeded"
+		);
+	});
+
+	it("swap exact out b for a", async () => {
+		const amountOut = new anchor.BN(500_000);
+		const userTokenAmountA = await getTokenAmount(userTokenAccountAPk);
+
+		await program.methods
+			.swapExactOut(amountOut, new anchor.BN(10_000_000))
+			.accounts({
+				...swapAccounts(),
+				userSource: userTokenAccountBPk,
+				userDestination: userTokenAccountAPk,
+				poolSourceVault: poolTokenVaultBPk,
+				poolDestinationVault: poolTokenVaultAPk,
+			})
+			.signers([user])
+			.rpc();
+
+		const newUserTokenAmountA = await getTokenAmount(userTokenAccountAPk);
+		expect(newUserTokenAmountA.sub(userTokenAmountA).toString()).to.equal(
+			amountOut.toString()
+		);
+	});
+
+	it("swap fees grow lp redemption value", async () => {
+		const [reserveA, reserveB] = await getReserves();
+		const lpSupply = (await getMint(connection, lpTokenMintPk)).supply;
+
+		// swap a for b and straight back again
+		for (let i = 0; i < 5; i++) {
+			const userTokenAmountB = await getTokenAmount(userTokenAccountBPk);
+			await program.methods
+				.swap(new anchor.BN(200_000), new anchor.BN(0))
+				.accounts(swapAccounts())
+				.signers([user])
+				.rpc();
+			const amountB = (await getTokenAmount(userTokenAccountBPk)).sub(
+				userTokenAmountB
+			);
+			await program.methods
+				.swap(amountB, new anchor.BN(0))
+				.accounts({
+					...swapAccounts(),
+					userSource: userTokenAccountBPk,
+					userDestination: userTokenAccountAPk,
+					poolSourceVault: poolTokenVaultBPk,
+					poolDestinationVault: poolTokenVaultAPk,
+				})
+				.signers([user])
+				.rpc();
+		}
+
+		// with no lp tokens minted or burned, a growing k means every lp
+		// token redeems for more of the pool
+		const [newReserveA, newReserveB] = await getReserves();
+		expect((await getMint(connection, lpTokenMintPk)).supply).to.equal(lpSupply);
+		expect(newReserveA.mul(newReserveB).gt(reserveA.mul(reserveB))).to.be.true;
+	});
+
+	it("swap accrues the protocol fee", async () => {
+		const pool = await program.account.pool.fetch(poolPk);
+		const amount = new anchor.BN(600_000);
+		await program.methods
+			.swap(amount, new anchor.BN(0))
+			.accounts(swapAccounts())
+			.signers([user])
+			.rpc();
+
+		// a sixth of the 0.3% fee on 600_000
+		const newPool = await program.account.pool.fetch(poolPk);
+		expect(newPool.protocolFeesA.sub(pool.protocolFeesA).toNumber()).to.equal(300);
+		expect(newPool.protocolFeesB.eq(pool.protocolFeesB)).to.be.true;
+	});
+
+	it("collect protocol fees fails for another signer", async () => {
+		await expectError(
+			program.methods
+				.collectProtocolFees()
+				.accounts({
+					authority: userPk,
+					pool: poolPk,
+					poolTokenVaultA: poolTokenVaultAPk,
+					poolTokenVaultB: poolTokenVaultBPk,
+					authorityTokenAccountA: userTokenAccountAPk,
+					authorityTokenAccountB: userTokenAccountBPk,
+				})
+				.signers([user])
+				.rpc(),
+			"Unauthorized"
+		);
+	});
+
+	it("collect protocol fees", async () => {
+		const authorityTokenAccountAPk = await createAccount(
+			connection,
+			authority,
+			tokenMintAPk,
+			authorityPk
+		);
+		const authorityTokenAccountBPk = await createAccount(
+			connection,
+			authority,
+			tokenMintBPk,
+			authorityPk
+		);
+		const pool = await program.account.pool.fetch(poolPk);
+		const [reserveA, reserveB] = await getReserves();
+
+		await program.methods
+			.collectProtocolFees()
+			.accounts({
+				authority: authorityPk,
+				pool: poolPk,
+				poolTokenVaultA: poolTokenVaultAPk,
+				poolTokenVaultB: poolTokenVaultBPk,
+				authorityTokenAccountA: authorityTokenAccountAPk,
+				authorityTokenAccountB: authorityTokenAccountBPk,
+			})
+			.signers([authority])
+			.rpc();
+
+		expect(
+			(await getTokenAmount(authorityTokenAccountAPk)).eq(pool.protocolFeesA)
+		).to.be.true;
+		expect(
+			(await getTokenAmount(authorityTokenAccountBPk)).eq(pool.protocolFeesB)
+		).to.be.true;
+		const newPool = await program.account.pool.fetch(poolPk);
+		expect(newPool.protocolFeesA.isZero()).to.be.true;
+		expect(newPool.protocolFeesB.isZero()).to.be.true;
+
+		// lp tokens redeem against the same reserves as before
+		const [newReserveA, newReserveB] = await getReserves();
+		expect(newReserveA.eq(reserveA)).to.be.true;
+		expect(newReserveB.eq(reserveB)).to.be.true;
+	});
+
+	it("add liquidity with token b only", async () => {
+		const userLPTokenAmount = await getTokenAmount(userLPTokenAccountPk);
+		const pool = await program.account.pool.fetch(poolPk);
+		const [reserveA, reserveB] = await getReserves();
+		const amountIn = new anchor.BN(1_000_000);
+
+		await program.methods
+			.addLiquiditySingle(amountIn, new anchor.BN(1))
+			.accounts({
+				user: userPk,
+				pool: poolPk,
+				userSource: userTokenAccountBPk,
+				poolSourceVault: poolTokenVaultBPk,
+				poolDestinationVault: poolTokenVaultAPk,
+				lpTokenMint: lpTokenMintPk,
+				userLpTokenAccount: userLPTokenAccountPk,
+			})
+			.signers([user])
+			.rpc();
+
+		const newUserLPTokenAmount = await getTokenAmount(userLPTokenAccountPk);
+		expect(newUserLPTokenAmount.gt(userLPTokenAmount)).to.be.true;
+
+		// the swapped part of the deposit pays the protocol fee
+		const newPool = await program.account.pool.fetch(poolPk);
+		const protocolFee = newPool.protocolFeesB.sub(pool.protocolFeesB);
+		expect(protocolFee.gtn(0)).to.be.true;
+		expect(newPool.protocolFeesA.eq(pool.protocolFeesA)).to.be.true;
+
+		const [newReserveA, newReserveB] = await getReserves();
+		expect(newReserveA.toString()).to.equal(reserveA.toString());
+		expect(newReserveB.toString()).to.equal(
+			reserveB.add(amountIn).sub(protocolFee).toString()
+		);
+	});
+
+	it("add liquidity with one token fails below minimum lp out", async () => {
+		await expectError(
+			program.methods
+				.addLiquiditySingle(new anchor.BN(1_000), new anchor.BN(1_000_000))
+				.accounts({
+					user: userPk,
+					pool: poolPk,
+					userSource: userTokenAccountAPk,
+					poolSourceVault: poolTokenVaultAPk,
+					poolDestinationVault: poolTokenVaultBPk,
+					lpTokenMint: lpTokenMintPk,
+					userLpTokenAccount: userLPTokenAccountPk,
+				})
+				.signers([user])
+				.rpc(),
+			"SlippageExceeded"
+		);
+	});
+
+	it("remove part of liquidity", async () => {
+		const lpAmount = (await getTokenAmount(userLPTokenAccountPk)).div(
+			new anchor.BN(4)
+		);
+		const lpSupply = new anchor.BN(
+			(await getMint(connection, lpTokenMintPk)).supply.toString()
+		);
+		const [reserveA, reserveB] = await getReserves();
+		const expectedAmountA = reserveA.mul(lpAmount).div(lpSupply);
+		const expectedAmountB = reserveB.mul(lpAmount).div(lpSupply);
+		const userLPTokenAmount = await getTokenAmount(userLPTokenAccountPk);
+		const userTokenAmountA = await getTokenAmount(userTokenAccountAPk);
+
+		await program.methods
+			.removeLiquidity(lpAmount, expectedAmountA, expectedAmountB)
+			.accounts(removeLiquidityAccounts())
+			.signers([user])
+			.rpc();
+
+		const newUserLPTokenAmount = await getTokenAmount(userLPTokenAccountPk);
+		expect(newUserLPTokenAmount.toString()).to.equal(
+			userLPTokenAmount.sub(lpAmount).toString()
+		);
+		const newUserTokenAmountA = await getTokenAmount(userTokenAccountAPk);
+		expect(newUserTokenAmountA.sub(userTokenAmountA).toString()).to.equal(
+			expectedAmountA.toString()
+		);
+		const [newReserveA, newReserveB] = await getReserves();
+		expect(newReserveB.toString()).to.equal(
+			reserveB.sub(expectedAmountB).toString()
+		);
+	});
+
+	it("remove liquidity fails above lp balance", async () => {
+		const lpAmount = (await getTokenAmount(userLPTokenAccountPk)).add(
+			new anchor.BN(1)
+		);
+		await expectError(
+			program.methods
+				.removeLiquidity(lpAmount, new anchor.BN(0), new anchor.BN(0))
+				.accounts(removeLiquidityAccounts())
+				.signers([user])
+				.rpc(),
+			"InsufficientLpTokens"
+		);
+	});
+
+	it("remove liquidity fails below minimum amounts", async () => {
+		const lpAmount = new anchor.BN(1_000);
+		const [reserveA] = await getReserves();
+		await expectError(
+			program.methods
+				.removeLiquidity(lpAmount, reserveA, new anchor.BN(0))
+				.accounts(removeLiquidityAccounts())
+				.signers([user])
+				.rpc(),
+			"SlippageExceeded"
+		);
+	});
+
+	describe("rejects substituted accounts", () => {
+		const amount = new anchor.BN(1_000);
+
+		it("add liquidity with another pool vault", async () => {
+			await expectError(
+				program.methods
+					.addLiquidity(amount, amount, new anchor.BN(0))
+					.accounts({
+						...addLiquidityAccounts(),
+						poolTokenVaultA: userTokenAccountAPk,
+					})
+					.signers([user])
+					.rpc(),
+				"InvalidVault"
+			);
+		});
+
+		it("add liquidity with another lp token mint", async () => {
+			await expectError(
+				program.methods
+					.addLiquidity(amount, amount, new anchor.BN(0))
+					.accounts({ ...addLiquidityAccounts(), lpTokenMint: tokenMintAPk })
+					.signers([user])
+					.rpc(),
+				"InvalidLpMint"
+			);
+		});
+
+		it("add liquidity from a user account of another mint", async () => {
+			await expectError(
+				program.methods
+					.addLiquidity(amount, amount, new anchor.BN(0))
+					.accounts({
+						...addLiquidityAccounts(),
+						userTokenAccountA: userTokenAccountBPk,
+					})
+					.signers([user])
+					.rpc(),
+				"ConstraintTokenMint"
+			);
+		});
+
+		it("add liquidity from an account the user does not own", async () => {
+			await expectError(
+				program.methods
+					.addLiquidity(amount, amount, new anchor.BN(0))
+					.accounts({
+						...addLiquidityAccounts(),
+						userTokenAccountA: poolTokenVaultAPk,
+					})
+					.signers([user])
+					.rpc(),
+				"ConstraintTokenOwner"
+			);
+		});
+
+		it("remove liquidity with another pool vault", async () => {
+			await expectError(
+				program.methods
+					.removeLiquidity(amount, new anchor.BN(0), new anchor.BN(0))
+					.accounts({
+						...removeLiquidityAccounts(),
+						poolTokenVaultB: userTokenAccountBPk,
+					})
+					.signers([user])
+					.rpc(),
+				"InvalidVault"
+			);
+		});
+
+		it("remove liquidity from an lp account the user does not own", async () => {
+			await expectError(
+				program.methods
+					.removeLiquidity(amount, new anchor.BN(0), new anchor.BN(0))
+					.accounts({
+						...removeLiquidityAccounts(),
+						userLpTokenAccount: lockedLPTokenAccountPk,
+					})
+					.signers([user])
+					.rpc(),
+				"ConstraintTokenOwner"
+			);
+		});
+
+		it("swap with another pool vault", async () => {
+			await expectError(
+				program.methods
+					.swap(amount, new anchor.BN(0))
+					.accounts({
+						...swapAccounts(),
+						poolDestinationVault: userTokenAccountBPk,
+					})
+					.signers([user])
+					.rpc(),
+				"InvalidVault"
+			);
+		});
+
+		it("swap into a user account of another mint", async () => {
+			await expectError(
+				program.methods
+					.swap(amount, new anchor.BN(0))
+					.accounts({ ...swapAccounts(), userDestination: userTokenAccountAPk })
+					.signers([user])
+					.rpc(),
+				"ConstraintTokenMint"
+			);
+		});
+
+		it("swap from an account the user does not own", async () => {
+			await expectError(
+				program.methods
+					.swap(amount, new anchor.BN(0))
+					.accounts({ ...swapAccounts(), userSource: poolTokenVaultAPk })
+					.signers([user])
+					.rpc(),
+				"ConstraintTokenOwner"
+			);
+		});
+	});
+
+	describe("pool status", () => {
+		const amount = new anchor.BN(10_000);
+
+		const setPoolStatus = (status: object, signer = authority) =>
+			program.methods
+				.setPoolStatus(status as any)
+				.accounts({ authority: signer.publicKey, pool: poolPk })
+				.signers([signer])
+				.rpc();
+
+		const swap = () =>
+			program.methods
+				.swap(amount, new anchor.BN(0))
+				.accounts(swapAccounts())
+				.signers([user])
+				.rpc();
+
+		const addLiquidity = () =>
+			program.methods
+				.addLiquidity(amount, amount.muln(3), new anchor.BN(1))
+				.accounts(addLiquidityAccounts())
+				.signers([user])
+				.rpc();
+
+		const removeLiquidity = () =>
+			program.methods
+				.removeLiquidity(amount, new anchor.BN(0), new anchor.BN(0))
+				.accounts(removeLiquidityAccounts())
+				.signers([user])
+				.rpc();
+
+		itChecksPoolStatus({
+			setStatus: setPoolStatus,
+			swap,
+			deposit: addLiquidity,
+			withdrawals: [removeLiquidity],
+		});
+	});
+
+	describe("pool signer", () => {
+		const bumpOf = (seeds: Buffer[]) =>
+			anchor.web3.PublicKey.findProgramAddressSync(seeds, programIdPk)[1];
+
+		it("pool stores the canonical bumps of its addresses", async () => {
+			const pool = await program.account.pool.fetch(poolPk);
+
+			expect(pool.bump).to.equal(
+				bumpOf([
+					Buffer.from("pool"),
+					tokenMintAPk.toBuffer(),
+					tokenMintBPk.toBuffer(),
+					feeNumerator.toArrayLike(Buffer, "le", 8),
+					feeDenominator.toArrayLike(Buffer, "le", 8),
+				])
+			);
+			expect(pool.tokenVaultABump).to.equal(
+				bumpOf([Buffer.from("token-vault-a"), poolPk.toBuffer()])
+			);
+			expect(pool.tokenVaultBBump).to.equal(
+				bumpOf([Buffer.from("token-vault-b"), poolPk.toBuffer()])
+			);
+			expect(pool.lpTokenMintBump).to.equal(
+				bumpOf([Buffer.from("lp-token-mint"), poolPk.toBuffer()])
+			);
+			expect(pool.lockedLpTokenAccountBump).to.equal(
+				bumpOf([
+					Buffer.from("locked-lp-token-account"),
+					lpTokenMintPk.toBuffer(),
+				])
+			);
+		});
+
+		it("pool signs vault transfers with its stored bump", async () => {
+			// only the pool address can move tokens out of its vaults, and
+			// signer seeds without the bump don't derive it
+			const vaultB = await getAccount(connection, poolTokenVaultBPk);
+			expect(vaultB.owner.toBase58()).to.equal(poolPk.toBase58());
+			const userTokenAmountB = await getTokenAmount(userTokenAccountBPk);
+
+			await program.methods
+				.swap(new anchor.BN(10_000), new anchor.BN(1))
+				.accounts(swapAccounts())
+				.signers([user])
+				.rpc();
+
+			const amountOut = (await getTokenAmount(userTokenAccountBPk)).sub(
+				userTokenAmountB
+			);
+			const newVaultB = await getAccount(connection, poolTokenVaultBPk);
+			expect(amountOut.gtn(0)).to.be.true;
+			expect(
+				new anchor.BN(vaultB.amount.toString())
+					.sub(new anchor.BN(newVaultB.amount.toString()))
+					.eq(amountOut)
+			).to.be.true;
+		});
+
+		it("pool signs lp token mints with its stored bump", async () => {
+			const lpTokenMint = await getMint(connection, lpTokenMintPk);
+			expect(lpTokenMint.mintAuthority.toBase58()).to.equal(poolPk.toBase58());
+			const userLPTokenAmount = await getTokenAmount(userLPTokenAccountPk);
+
+			const amount = new anchor.BN(100_000);
+			await program.methods
+				.addLiquidity(amount, amount.mul(new anchor.BN(3)), new anchor.BN(1))
+				.accounts(addLiquidityAccounts())
+				.signers([user])
+				.rpc();
+
+			const lpAmount = (await getTokenAmount(userLPTokenAccountPk)).sub(
+				userLPTokenAmount
+			);
+			const newLPTokenMint = await getMint(connection, lpTokenMintPk);
+			expect(lpAmount.gtn(0)).to.be.true;
+			expect(
+				new anchor.BN(newLPTokenMint.supply.toString())
+					.sub(new anchor.BN(lpTokenMint.supply.toString()))
+					.eq(lpAmount)
+			).to.be.true;
+		});
+	});
+
+	describe("compute units", () => {
+		// the units a transaction used, from the runtime's log of its
+		// instruction, along with the units it was allowed
+		const getComputeUnits = async (tx: string) => {
+			const transaction = await connection.getTransaction(tx, {
+				commitment: "confirmed",
+			});
+			const consumed = transaction.meta.logMessages
+				.map((log) => log.match(/consumed (\d+) of (\d+) compute units/))
+				.find((match) => match !== null);
+			return { consumed: Number(consumed[1]), limit: Number(consumed[2]) };
+		};
+
+		it("add liquidity stays within its compute budget", async () => {
+			const amount = new anchor.BN(100_000);
+			const tx = await program.methods
+				.addLiquidity(amount, amount.mul(new anchor.BN(3)), new anchor.BN(1))
+				.accounts(addLiquidityAccounts())
+				.signers([user])
+				.rpc({ commitment: "confirmed" });
+
+			const { consumed, limit } = await getComputeUnits(tx);
+			expect(consumed).to.be.lessThan(limit);
+			expect(consumed).to.be.lessThan(100_000);
+		});
+
+		it("swap stays within its compute budget", async () => {
+			const tx = await program.methods
+				.swap(new anchor.BN(10_000), new anchor.BN(1))
+				.accounts(swapAccounts())
+				.signers([user])
+				.rpc({ commitment: "confirmed" });
+
+			const { consumed, limit } = await getComputeUnits(tx);
+			expect(consumed).to.be.lessThan(limit);
+			expect(consumed).to.be.lessThan(50_000);
+		});
+	});
+
+	describe("stable pool", () => {
+		let stable: Awaited<ReturnType<typeof createUserPair>>;
+
+		before(async () => {
+			stable = await createUserPair();
+		});
+
+		it("create pool fails with amp out of range", async () => {
+			await expectError(
+				program.methods
+					.createPool(
+						feeNumerator,
+						feeDenominator,
+						stableSwap,
+						new anchor.BN(0),
+						new anchor.BN(0),
+						new anchor.BN(0)
+					)
+					.accounts({ authority: authorityPk, ...stable.accounts })
+					.signers([authority])
+					.rpc(),
+				"InvalidAmp"
+			);
+		});
+
+		it("create stable pool", async () => {
+			await program.methods
+				.createPool(
+					feeNumerator,
+					feeDenominator,
+					stableSwap,
+					new anchor.BN(100),
+					new anchor.BN(0),
+					new anchor.BN(0)
+				)
+				.accounts({ authority: authorityPk, ...stable.accounts })
+				.signers([authority])
+				.rpc();
+
+			const pool = await program.account.pool.fetch(stable.accounts.pool);
+			expect(pool.curveType).to.deep.equal(stableSwap);
+			expect(pool.initialAmp.toNumber()).to.equal(100);
+			expect(pool.targetAmp.toNumber()).to.equal(100);
+		});
+
+		it("first deposit mints the invariant", async () => {
+			// D of a balanced pool is the sum of its reserves
+			await program.methods
+				.addLiquidity(
+					new anchor.BN(100_000_000),
+					new anchor.BN(100_000_000),
+					new anchor.BN(0)
+				)
+				.accounts(stable.addLiquidityAccounts)
+				.signers([user])
+				.rpc();
+
+			const lpTokenMint = await getMint(connection, stable.accounts.lpTokenMint);
+			expect(lpTokenMint.supply.toString()).to.equal("200000000");
+		});
+
+		it("swap near the peg pays close to one for one", async () => {
+			const amountIn = new anchor.BN(10_000_000);
+			const userTokenAmountY = await getTokenAmount(stable.userTokenAccountYPk);
+
+			await program.methods
+				.swap(amountIn, new anchor.BN(0))
+				.accounts(stable.swapAccounts)
+				.signers([user])
+				.rpc();
+
+			// constant product would pay about 9_066_000
+			const amountOut = (await getTokenAmount(stable.userTokenAccountYPk)).sub(
+				userTokenAmountY
+			);
+			expect(amountOut.gt(new anchor.BN(9_900_000))).to.be.true;
+			expect(amountOut.lt(amountIn)).to.be.true;
+		});
+
+		it("ramp amp fails on a constant product pool", async () => {
+			await expectError(
+				program.methods
+					.rampAmp(new anchor.BN(200), new anchor.BN(0))
+					.accounts({ authority: authorityPk, pool: poolPk })
+					.signers([authority])
+					.rpc(),
+				"NotStablePool"
+			);
+		});
+
+		it("ramp amp fails for another signer", async () => {
+			await expectError(
+				program.methods
+					.rampAmp(new anchor.BN(200), new anchor.BN(0))
+					.accounts({ authority: userPk, pool: stable.accounts.pool })
+					.signers([user])
+					.rpc(),
+				"Unauthorized"
+			);
+		});
+
+		it("ramp amp fails over less than a day", async () => {
+			const now = Math.floor(Date.now() / 1000);
+			await expectError(
+				program.methods
+					.rampAmp(new anchor.BN(200), new anchor.BN(now + 60 * 60))
+					.accounts({ authority: authorityPk, pool: stable.accounts.pool })
+					.signers([authority])
+					.rpc(),
+				"InvalidRampTime"
+			);
+		});
+
+		it("ramp amp fails above ten times the current amp", async () => {
+			const now = Math.floor(Date.now() / 1000);
+			await expectError(
+				program.methods
+					.rampAmp(new anchor.BN(1_001), new anchor.BN(now + 7 * 24 * 60 * 60))
+					.accounts({ authority: authorityPk, pool: stable.accounts.pool })
+					.signers([authority])
+					.rpc(),
+				"AmpChangeTooLarge"
+			);
+		});
+
+		it("ramp amp and stop it", async () => {
+			const rampEndTimestamp = new anchor.BN(
+				Math.floor(Date.now() / 1000) + 7 * 24 * 60 * 60
+			);
+			await program.methods
+				.rampAmp(new anchor.BN(200), rampEndTimestamp)
+				.accounts({ authority: authorityPk, pool: stable.accounts.pool })
+				.signers([authority])
+				.rpc();
+
+			let pool = await program.account.pool.fetch(stable.accounts.pool);
+			expect(pool.initialAmp.toNumber()).to.equal(100);
+			expect(pool.targetAmp.toNumber()).to.equal(200);
+			expect(pool.rampEndTimestamp.eq(rampEndTimestamp)).to.be.true;
+
+			// a week long ramp has barely moved a few seconds in
+			await program.methods
+				.stopRampAmp()
+				.accounts({ authority: authorityPk, pool: stable.accounts.pool })
+				.signers([authority])
+				.rpc();
+
+			pool = await program.account.pool.fetch(stable.accounts.pool);
+			expect(pool.targetAmp.eq(pool.initialAmp)).to.be.true;
+			expect(pool.initialAmp.toNumber()).to.be.within(100, 101);
+			expect(pool.rampEndTimestamp.eq(pool.rampStartTimestamp)).to.be.true;
+		});
+
+		it("ramp amp fails within a day of the last ramp", async () => {
+			const now = Math.floor(Date.now() / 1000);
+			await expectError(
+				program.methods
+					.rampAmp(new anchor.BN(200), new anchor.BN(now + 7 * 24 * 60 * 60))
+					.accounts({ authority: authorityPk, pool: stable.accounts.pool })
+					.signers([authority])
+					.rpc(),
+				"RampTooSoon"
+			);
+		});
+	});
+
+	describe("stable pool of tokens with different decimals", () => {
+		let mixed: Awaited<ReturnType<typeof createUserPair>>;
+		// one whole token of x and of y
+		let unitX: anchor.BN;
+		let unitY: anchor.BN;
+
+		before(async () => {
+			mixed = await createUserPair([6, 9]);
+			const mintX = await getMint(connection, mixed.accounts.tokenMintA);
+			const mintY = await getMint(connection, mixed.accounts.tokenMintB);
+			unitX = new anchor.BN(10).pow(new anchor.BN(mintX.decimals));
+			unitY = new anchor.BN(10).pow(new anchor.BN(mintY.decimals));
+		});
+
+		it("create pool scales both tokens to 9 decimals", async () => {
+			await program.methods
+				.createPool(
+					feeNumerator,
+					feeDenominator,
+					stableSwap,
+					new anchor.BN(100),
+					new anchor.BN(0),
+					new anchor.BN(0)
+				)
+				.accounts({ authority: authorityPk, ...mixed.accounts })
+				.signers([authority])
+				.rpc();
+
+			const pool = await program.account.pool.fetch(mixed.accounts.pool);
+			const billion = new anchor.BN(1_000_000_000);
+			expect(pool.precisionMultiplierA.mul(unitX).eq(billion)).to.be.true;
+			expect(pool.precisionMultiplierB.mul(unitY).eq(billion)).to.be.true;
+		});
+
+		it("first deposit mints the invariant at 9 decimals", async () => {
+			await program.methods
+				.addLiquidity(unitX.muln(100), unitY.muln(100), new anchor.BN(0))
+				.accounts(mixed.addLiquidityAccounts)
+				.signers([user])
+				.rpc();
+
+			const lpTokenMint = await getMint(connection, mixed.accounts.lpTokenMint);
+			expect(lpTokenMint.supply.toString()).to.equal("200000000000");
+		});
+
+		it("swap pays close to one whole token for one", async () => {
+			const userTokenAmountY = await getTokenAmount(mixed.userTokenAccountYPk);
+
+			await program.methods
+				.swap(unitX.muln(10), new anchor.BN(0))
+				.accounts(mixed.swapAccounts)
+				.signers([user])
+				.rpc();
+
+			const amountOut = (await getTokenAmount(mixed.userTokenAccountYPk)).sub(
+				userTokenAmountY
+			);
+			expect(amountOut.gt(unitY.muln(99).divn(10))).to.be.true;
+			expect(amountOut.lt(unitY.muln(10))).to.be.true;
+		});
+	});
+
+	describe("weighted pool", () => {
+		let weightedPair: Awaited<ReturnType<typeof createUserPair>>;
+
+		before(async () => {
+			weightedPair = await createUserPair();
+		});
+
+		it("create pool fails with a weight below one percent", async () => {
+			await expectError(
+				program.methods
+					.createPool(
+						feeNumerator,
+						feeDenominator,
+						weighted,
+						new anchor.BN(0),
+						new anchor.BN(100),
+						new anchor.BN(0)
+					)
+					.accounts({ authority: authorityPk, ...weightedPair.accounts })
+					.signers([authority])
+					.rpc(),
+				"InvalidWeights"
+			);
+		});
+
+		it("create pool fails when the weights do not add up to 100", async () => {
+			for (const [weightA, weightB] of [[8, 2], [99, 2]]) {
+				await expectError(
+					program.methods
+						.createPool(
+							feeNumerator,
+							feeDenominator,
+							weighted,
+							new anchor.BN(0),
+							new anchor.BN(weightA),
+							new anchor.BN(weightB)
+						)
+						.accounts({ authority: authorityPk, ...weightedPair.accounts })
+						.signers([authority])
+						.rpc(),
+					"InvalidWeights"
+				);
+			}
+		});
+
+		it("create 80/20 pool", async () => {
+			await program.methods
+				.createPool(
+					feeNumerator,
+					feeDenominator,
+					weighted,
+					new anchor.BN(0),
+					new anchor.BN(80),
+					new anchor.BN(20)
+				)
+				.accounts({ authority: authorityPk, ...weightedPair.accounts })
+				.signers([authority])
+				.rpc();
+
+			const pool = await program.account.pool.fetch(weightedPair.accounts.pool);
+			expect(pool.curveType).to.deep.equal(weighted);
+			expect(pool.weightA.toNumber()).to.equal(80);
+			expect(pool.weightB.toNumber()).to.equal(20);
+		});
+
+		it("seed the pool with 80% of its value in token a", async () => {
+			// a and b are worth the same at 80_000_000 a to 20_000_000 b
+			await program.methods
+				.addLiquidity(
+					new anchor.BN(80_000_000),
+					new anchor.BN(20_000_000),
+					new anchor.BN(0)
+				)
+				.accounts(weightedPair.addLiquidityAccounts)
+				.signers([user])
+				.rpc();
+
+			// 80_000_000^0.8 * 20_000_000^0.2 = 60_628_662.8
+			const lpTokenMint = await getMint(
+				connection,
+				weightedPair.accounts.lpTokenMint
+			);
+			expect(Number(lpTokenMint.supply)).to.be.within(60_628_000, 60_628_662);
+		});
+
+		it("swap prices the tokens by their weights", async () => {
+			const amountIn = new anchor.BN(100_000);
+			const userTokenAmountY = await getTokenAmount(
+				weightedPair.userTokenAccountYPk
+			);
+
+			await program.methods
+				.swap(amountIn, new anchor.BN(0))
+				.accounts(weightedPair.swapAccounts)
+				.signers([user])
+				.rpc();
+
+			// one for one less the 0.3% fee and a little slippage
+			const amountOut = (
+				await getTokenAmount(weightedPair.userTokenAccountYPk)
+			).sub(userTokenAmountY);
+			expect(amountOut.toNumber()).to.be.within(99_000, 99_700);
+		});
+
+		it("swap fails above the trade limit", async () => {
+			await expectError(
+				program.methods
+					.swap(new anchor.BN(30_000_000), new anchor.BN(0))
+					.accounts(weightedPair.swapAccounts)
+					.signers([user])
+					.rpc(),
+				"TradeTooLarge"
+			);
+		});
+	});
+
+	describe("multi pool", () => {
+		const multiPool = anchor.web3.Keypair.generate();
+		const multiPoolPk = multiPool.publicKey;
+		const findPda = (seeds: Buffer[]) =>
+			anchor.web3.PublicKey.findProgramAddressSync(seeds, programIdPk)[0];
+		const multiPoolAuthorityPk = findPda([
+			Buffer.from("multi-pool-authority"),
+			multiPoolPk.toBuffer(),
+		]);
+		const multiLpTokenMintPk = findPda([
+			Buffer.from("lp-token-mint"),
+			multiPoolPk.toBuffer(),
+		]);
+		const multiLockedLpTokenAccountPk = findPda([
+			Buffer.from("locked-lp-token-account"),
+			multiLpTokenMintPk.toBuffer(),
+		]);
+		const multiUserLpTokenAccountPk = findPda([
+			Buffer.from("lp-token-account"),
+			multiLpTokenMintPk.toBuffer(),
+			userPk.toBuffer(),
+		]);
+		let mintPks: anchor.web3.PublicKey[];
+		let vaultPks: anchor.web3.PublicKey[];
+		let userTokenAccountPks: anchor.web3.PublicKey[];
+
+		const writable = (pubkey: anchor.web3.PublicKey) => ({
+			pubkey,
+			isWritable: true,
+			isSigner: false,
+		});
+
+		// the user's token account and the pool vault of each token in turn
+		const tokenAccountPairs = () =>
+			userTokenAccountPks.flatMap((userTokenAccountPk, i) => [
+				writable(userTokenAccountPk),
+				writable(vaultPks[i]),
+			]);
+
+		before(async () => {
+			mintPks = [];
+			for (let i = 0; i < 3; i++) {
+				mintPks.push(
+					await createMint(connection, authority, authorityPk, authorityPk, 6)
+				);
+			}
+			mintPks.sort((mintXPk, mintYPk) =>
+				Buffer.compare(mintXPk.toBuffer(), mintYPk.toBuffer())
+			);
+
+			vaultPks = [];
+			userTokenAccountPks = [];
+			for (const mintPk of mintPks) {
+				// vaults are plain token accounts owned by the pool authority
+				vaultPks.push(
+					await createAccount(
+						connection,
+						authority,
+						mintPk,
+						multiPoolAuthorityPk,
+						anchor.web3.Keypair.generate()
+					)
+				);
+				const userTokenAccountPk = await createAccount(
+					connection,
+					authority,
+					mintPk,
+					userPk
+				);
+				await mintTo(
+					connection,
+					authority,
+					mintPk,
+					userTokenAccountPk,
+					authority,
+					1000_000_000
+				);
+				userTokenAccountPks.push(userTokenAccountPk);
+			}
+		});
+
+		it("create multi pool fails with unsorted mints", async () => {
+			await expectError(
+				program.methods
+					.createMultiPool(feeNumerator, feeDenominator)
+					.accounts({
+						authority: authorityPk,
+						config: configPk,
+						admin: adminPk,
+						multiPool: multiPoolPk,
+						multiPoolAuthority: multiPoolAuthorityPk,
+						lpTokenMint: multiLpTokenMintPk,
+						lockedLpTokenAccount: multiLockedLpTokenAccountPk,
+					})
+					.remainingAccounts(
+						[2, 1, 0].flatMap((i) => [
+							writable(mintPks[i]),
+							writable(vaultPks[i]),
+						])
+					)
+					.signers([authority, multiPool])
+					.rpc(),
+				"UnsortedMints"
+			);
+		});
+
+		it("create multi pool", async () => {
+			await program.methods
+				.createMultiPool(feeNumerator, feeDenominator)
+				.accounts({
+					authority: authorityPk,
+					config: configPk,
+					admin: adminPk,
+					multiPool: multiPoolPk,
+					multiPoolAuthority: multiPoolAuthorityPk,
+					lpTokenMint: multiLpTokenMintPk,
+					lockedLpTokenAccount: multiLockedLpTokenAccountPk,
+				})
+				.remainingAccounts(
+					mintPks.flatMap((mintPk, i) => [
+						writable(mintPk),
+						writable(vaultPks[i]),
+					])
+				)
+				.signers([authority, multiPool])
+				.rpc();
+
+			const pool = await program.account.multiPool.fetch(multiPoolPk);
+			expect(pool.tokenMints.map((mintPk) => mintPk.toBase58())).to.deep.equal(
+				mintPks.map((mintPk) => mintPk.toBase58())
+			);
+			expect(pool.tokenVaults.length).to.equal(3);
+		});
+
+		it("add liquidity to all tokens", async () => {
+			// (1 * 8 * 27)^(1/3) * 1_000_000 = 6_000_000, less rounding
+			await program.methods
+				.addLiquidityMulti(
+					[
+						new anchor.BN(1_000_000),
+						new anchor.BN(8_000_000),
+						new anchor.BN(27_000_000),
+					],
+					new anchor.BN(0)
+				)
+				.accounts({
+					user: userPk,
+					multiPool: multiPoolPk,
+					multiPoolAuthority: multiPoolAuthorityPk,
+					lpTokenMint: multiLpTokenMintPk,
+					lockedLpTokenAccount: multiLockedLpTokenAccountPk,
+					userLpTokenAccount: multiUserLpTokenAccountPk,
+				})
+				.remainingAccounts(tokenAccountPairs())
+				.signers([user])
+				.rpc();
+
+			const lpTokenMint = await getMint(connection, multiLpTokenMintPk);
+			expect(Number(lpTokenMint.supply)).to.be.within(5_999_990, 6_000_000);
+		});
+
+		it("add liquidity fails with a missing token", async () => {
+			await expectError(
+				program.methods
+					.addLiquidityMulti(
+						[new anchor.BN(1_000), new anchor.BN(8_000)],
+						new anchor.BN(0)
+					)
+					.accounts({
+						user: userPk,
+						multiPool: multiPoolPk,
+						multiPoolAuthority: multiPoolAuthorityPk,
+						lpTokenMint: multiLpTokenMintPk,
+						lockedLpTokenAccount: multiLockedLpTokenAccountPk,
+						userLpTokenAccount: multiUserLpTokenAccountPk,
+					})
+					.remainingAccounts(tokenAccountPairs().slice(0, 4))
+					.signers([user])
+					.rpc(),
+				"InvalidTokenCount"
+			);
+		});
+
+		it("swap the first token for the last", async () => {
+			const amountIn = new anchor.BN(100_000);
+			const reserveIn = await getTokenAmount(vaultPks[0]);
+			const reserveOut = await getTokenAmount(vaultPks[2]);
+			const userTokenAmount = await getTokenAmount(userTokenAccountPks[2]);
+
+			await program.methods
+				.swapMulti(amountIn, new anchor.BN(0))
+				.accounts({
+					user: userPk,
+					multiPool: multiPoolPk,
+					multiPoolAuthority: multiPoolAuthorityPk,
+					userSource: userTokenAccountPks[0],
+					userDestination: userTokenAccountPks[2],
+					poolSourceVault: vaultPks[0],
+					poolDestinationVault: vaultPks[2],
+				})
+				.signers([user])
+				.rpc();
+
+			// constant product of the two reserves
+			const amountInAfterFee = amountIn
+				.mul(feeDenominator.sub(feeNumerator))
+				.div(feeDenominator);
+			const expectedAmountOut = reserveOut
+				.mul(amountInAfterFee)
+				.div(reserveIn.add(amountInAfterFee));
+			const amountOut = (await getTokenAmount(userTokenAccountPks[2])).sub(
+				userTokenAmount
+			);
+			expect(amountOut.toString()).to.equal(expectedAmountOut.toString());
+		});
+
+		it("remove liquidity from all tokens", async () => {
+			const lpAmount = (await getTokenAmount(multiUserLpTokenAccountPk)).divn(2);
+			const lpSupply = new anchor.BN(
+				(await getMint(connection, multiLpTokenMintPk)).supply.toString()
+			);
+			const reserves = await Promise.all(vaultPks.map(getTokenAmount));
+			const userTokenAmounts = await Promise.all(
+				userTokenAccountPks.map(getTokenAmount)
+			);
+
+			await program.methods
+				.removeLiquidityMulti(lpAmount, [
+					new anchor.BN(0),
+					new anchor.BN(0),
+					new anchor.BN(0),
+				])
+				.accounts({
+					user: userPk,
+					multiPool: multiPoolPk,
+					multiPoolAuthority: multiPoolAuthorityPk,
+					lpTokenMint: multiLpTokenMintPk,
+					userLpTokenAccount: multiUserLpTokenAccountPk,
+				})
+				.remainingAccounts(tokenAccountPairs())
+				.signers([user])
+				.rpc();
+
+			for (let i = 0; i < 3; i++) {
+				const amount = (await getTokenAmount(userTokenAccountPks[i])).sub(
+					userTokenAmounts[i]
+				);
+				expect(amount.toString()).to.equal(
+					reserves[i].mul(lpAmount).div(lpSupply).toString()
+				);
+			}
+		});
+
+		describe("status", () => {
+			const amount = new anchor.BN(10_000);
+
+			const setMultiPoolStatus = (status: object, signer = authority) =>
+				program.methods
+					.setMultiPoolStatus(status as any)
+					.accounts({ authority: signer.publicKey, multiPool: multiPoolPk })
+					.signers([signer])
+					.rpc();
+
+			const swap = () =>
+				program.methods
+					.swapMulti(amount, new anchor.BN(0))
+					.accounts({
+						user: userPk,
+						multiPool: multiPoolPk,
+						multiPoolAuthority: multiPoolAuthorityPk,
+						userSource: userTokenAccountPks[0],
+						userDestination: userTokenAccountPks[2],
+						poolSourceVault: vaultPks[0],
+						poolDestinationVault: vaultPks[2],
+					})
+					.signers([user])
+					.rpc();
+
+			const addLiquidity = () =>
+				program.methods
+					.addLiquidityMulti(
+						[amount.muln(10), amount.muln(10), amount.muln(10)],
+						new anchor.BN(1)
+					)
+					.accounts({
+						user: userPk,
+						multiPool: multiPoolPk,
+						multiPoolAuthority: multiPoolAuthorityPk,
+						lpTokenMint: multiLpTokenMintPk,
+						lockedLpTokenAccount: multiLockedLpTokenAccountPk,
+						userLpTokenAccount: multiUserLpTokenAccountPk,
+					})
+					.remainingAccounts(tokenAccountPairs())
+					.signers([user])
+					.rpc();
+
+			const removeLiquidity = () =>
+				program.methods
+					.removeLiquidityMulti(amount, [
+						new anchor.BN(0),
+						new anchor.BN(0),
+						new anchor.BN(0),
+					])
+					.accounts({
+						user: userPk,
+						multiPool: multiPoolPk,
+						multiPoolAuthority: multiPoolAuthorityPk,
+						lpTokenMint: multiLpTokenMintPk,
+						userLpTokenAccount: multiUserLpTokenAccountPk,
+					})
+					.remainingAccounts(tokenAccountPairs())
+					.signers([user])
+					.rpc();
+
+			itChecksPoolStatus({
+				setStatus: setMultiPoolStatus,
+				swap,
+				deposit: addLiquidity,
+				withdrawals: [removeLiquidity],
+			});
+		});
+	});
+
+	describe("concentrated pool", () => {
+		let pair: Awaited<ReturnType<typeof createUserPair>>;
+		let concentratedPoolPk: anchor.web3.PublicKey;
+		let positionPk: anchor.web3.PublicKey;
+		let vaultAPk: anchor.web3.PublicKey;
+		let vaultBPk: anchor.web3.PublicKey;
+		const findPda = (seeds: Buffer[]) =>
+			anchor.web3.PublicKey.findProgramAddressSync(seeds, programIdPk)[0];
+		const tickBuffer = (tick: number) => {
+			const buffer = Buffer.alloc(4);
+			buffer.writeInt32LE(tick);
+			return buffer;
+		};
+		// a price of one, as the square root in Q64.64
+		const sqrtPriceOne = new anchor.BN(1).shln(64);
+		const liquidity = new anchor.BN(10_000_000_000);
+		// a tick array holds 16 ticks on the tick spacing of 60
+		const ticksInArray = 16 * 60;
+		const tickArrayPk = (startTickIndex: number) =>
+			findPda([
+				Buffer.from("tick-array"),
+				concentratedPoolPk.toBuffer(),
+				tickBuffer(startTickIndex),
+			]);
+		const writable = (pubkey: anchor.web3.PublicKey) => ({
+			pubkey,
+			isWritable: true,
+			isSigner: false,
+		});
+
+		// the tick arrays a swap from the current price passes through
+		const swapTickArrays = async (aToB: boolean) => {
+			const pool = await program.account.concentratedPool.fetch(
+				concentratedPoolPk
+			);
+			const start =
+				Math.floor(pool.tickCurrent / ticksInArray) * ticksInArray;
+			return [0, 1, 2].map((i) =>
+				writable(tickArrayPk(start + (aToB ? -i : i) * ticksInArray))
+			);
+		};
+
+		const createAccounts = () => ({
+			authority: authorityPk,
+			config: configPk,
+			admin: adminPk,
+			concentratedPool: concentratedPoolPk,
+			tokenMintA: pair.accounts.tokenMintA,
+			tokenMintB: pair.accounts.tokenMintB,
+			tokenVaultA: vaultAPk,
+			tokenVaultB: vaultBPk,
+		});
+
+		const positionAccounts = () => ({
+			owner: userPk,
+			concentratedPool: concentratedPoolPk,
+			position: positionPk,
+			tickArrayLower: tickArrayPk(-960),
+			tickArrayUpper: tickArrayPk(0),
+			userTokenAccountA: pair.userTokenAccountXPk,
+			userTokenAccountB: pair.userTokenAccountYPk,
+			poolTokenVaultA: vaultAPk,
+			poolTokenVaultB: vaultBPk,
+		});
+
+		// swaps token a for token b
+		const swapAccounts = () => ({
+			user: userPk,
+			concentratedPool: concentratedPoolPk,
+			userSource: pair.userTokenAccountXPk,
+			userDestination: pair.userTokenAccountYPk,
+			poolSourceVault: vaultAPk,
+			poolDestinationVault: vaultBPk,
+		});
+
+		before(async () => {
+			pair = await createUserPair();
+			concentratedPoolPk = findPda([
+				Buffer.from("concentrated-pool"),
+				pair.accounts.tokenMintA.toBuffer(),
+				pair.accounts.tokenMintB.toBuffer(),
+				feeNumerator.toArrayLike(Buffer, "le", 8),
+				feeDenominator.toArrayLike(Buffer, "le", 8),
+			]);
+			vaultAPk = findPda([
+				Buffer.from("token-vault-a"),
+				concentratedPoolPk.toBuffer(),
+			]);
+			vaultBPk = findPda([
+				Buffer.from("token-vault-b"),
+				concentratedPoolPk.toBuffer(),
+			]);
+			positionPk = findPda([
+				Buffer.from("position"),
+				concentratedPoolPk.toBuffer(),
+				userPk.toBuffer(),
+				tickBuffer(-600),
+				tickBuffer(600),
+			]);
+		});
+
+		it("create concentrated pool fails without a tick spacing", async () => {
+			await expectError(
+				program.methods
+					.createConcentratedPool(
+						feeNumerator,
+						feeDenominator,
+						0,
+						sqrtPriceOne
+					)
+					.accounts(createAccounts())
+					.signers([authority])
+					.rpc(),
+				"InvalidTickSpacing"
+			);
+		});
+
+		it("create concentrated pool", async () => {
+			await program.methods
+				.createConcentratedPool(feeNumerator, feeDenominator, 60, sqrtPriceOne)
+				.accounts(createAccounts())
+				.signers([authority])
+				.rpc();
+
+			const pool = await program.account.concentratedPool.fetch(
+				concentratedPoolPk
+			);
+			expect(pool.tickCurrent).to.equal(0);
+			expect(pool.liquidity.toNumber()).to.equal(0);
+		});
+
+		it("initialize tick array fails off the tick array starts", async () => {
+			await expectError(
+				program.methods
+					.initializeTickArray(60)
+					.accounts({
+						payer: userPk,
+						concentratedPool: concentratedPoolPk,
+						tickArray: tickArrayPk(60),
+					})
+					.signers([user])
+					.rpc(),
+				"InvalidTickArray"
+			);
+		});
+
+		it("initialize tick arrays", async () => {
+			for (let i = -3; i < 3; i++) {
+				await program.methods
+					.initializeTickArray(i * ticksInArray)
+					.accounts({
+						payer: userPk,
+						concentratedPool: concentratedPoolPk,
+						tickArray: tickArrayPk(i * ticksInArray),
+					})
+					.signers([user])
+					.rpc();
+			}
+
+			const tickArray = await program.account.tickArray.fetch(
+				tickArrayPk(-960)
+			);
+			expect(tickArray.ticks.map((tick) => tick.index)).to.deep.equal(
+				[...Array(16).keys()].map((i) => -960 + i * 60)
+			);
+			expect(tickArray.ticks.every((tick) => tick.liquidityGross.isZero())).to
+				.be.true;
+		});
+
+		it("open position fails off the tick spacing", async () => {
+			await expectError(
+				program.methods
+					.openPosition(-50, 600)
+					.accounts({
+						owner: userPk,
+						concentratedPool: concentratedPoolPk,
+						position: findPda([
+							Buffer.from("position"),
+							concentratedPoolPk.toBuffer(),
+							userPk.toBuffer(),
+							tickBuffer(-50),
+							tickBuffer(600),
+						]),
+					})
+					.signers([user])
+					.rpc(),
+				"InvalidTickRange"
+			);
+		});
+
+		it("open position", async () => {
+			await program.methods
+				.openPosition(-600, 600)
+				.accounts({
+					owner: userPk,
+					concentratedPool: concentratedPoolPk,
+					position: positionPk,
+				})
+				.signers([user])
+				.rpc();
+
+			const position = await program.account.position.fetch(positionPk);
+			expect(position.owner.toBase58()).to.equal(userPk.toBase58());
+			expect(position.liquidity.toNumber()).to.equal(0);
+		});
+
+		it("increase liquidity fails past the slippage bound", async () => {
+			await expectError(
+				program.methods
+					.increaseLiquidity(
+						liquidity,
+						new anchor.BN(1_000),
+						new anchor.BN(1_000)
+					)
+					.accounts(positionAccounts())
+					.signers([user])
+					.rpc(),
+				"SlippageExceeded"
+			);
+		});
+
+		it("increase liquidity", async () => {
+			await program.methods
+				.increaseLiquidity(
+					liquidity,
+					new anchor.BN(1000_000_000),
+					new anchor.BN(1000_000_000)
+				)
+				.accounts(positionAccounts())
+				.signers([user])
+				.rpc();
+
+			// the range is symmetric around the price, so it holds as much of
+			// each token
+			const amountA = await getTokenAmount(vaultAPk);
+			const amountB = await getTokenAmount(vaultBPk);
+			expect(amountA.sub(amountB).abs().toNumber()).to.be.at.most(1);
+			const pool = await program.account.concentratedPool.fetch(
+				concentratedPoolPk
+			);
+			expect(pool.liquidity.toString()).to.equal(liquidity.toString());
+			// ticks -600 and 600
+			const lowerTickArray = await program.account.tickArray.fetch(
+				tickArrayPk(-960)
+			);
+			const upperTickArray = await program.account.tickArray.fetch(
+				tickArrayPk(0)
+			);
+			expect(lowerTickArray.ticks[6].liquidityGross.toString()).to.equal(
+				liquidity.toString()
+			);
+			expect(upperTickArray.ticks[10].liquidityGross.toString()).to.equal(
+				liquidity.toString()
+			);
+		});
+
+		it("swap within the position's range", async () => {
+			const amountIn = new anchor.BN(1_000_000);
+			const userTokenAmount = await getTokenAmount(pair.userTokenAccountYPk);
+
+			await program.methods
+				.swapConcentrated(amountIn, new anchor.BN(0))
+				.accounts(swapAccounts())
+				.remainingAccounts(await swapTickArrays(true))
+				.signers([user])
+				.rpc();
+
+			// near one for one, less the 0.3% fee and a little price impact
+			const amountOut = (await getTokenAmount(pair.userTokenAccountYPk)).sub(
+				userTokenAmount
+			);
+			expect(amountOut.toNumber()).to.be.within(996_000, 997_000);
+			const pool = await program.account.concentratedPool.fetch(
+				concentratedPoolPk
+			);
+			expect(pool.tickCurrent).to.be.below(0);
+		});
+
+		it("swap fails past the last liquidity", async () => {
+			await expectError(
+				program.methods
+					.swapConcentrated(new anchor.BN(500_000_000), new anchor.BN(0))
+					.accounts(swapAccounts())
+					.remainingAccounts(await swapTickArrays(true))
+					.signers([user])
+					.rpc(),
+				"InsufficientLiquidity"
+			);
+		});
+
+		it("swap fails with the tick arrays out of order", async () => {
+			await expectError(
+				program.methods
+					.swapConcentrated(new anchor.BN(1_000), new anchor.BN(0))
+					.accounts(swapAccounts())
+					.remainingAccounts((await swapTickArrays(true)).reverse())
+					.signers([user])
+					.rpc(),
+				"InvalidTickArray"
+			);
+		});
+
+		it("collect fees", async () => {
+			const userTokenAmount = await getTokenAmount(pair.userTokenAccountXPk);
+
+			await program.methods
+				.collectFees()
+				.accounts(positionAccounts())
+				.signers([user])
+				.rpc();
+
+			// the whole fee on the swap, as the only position in range
+			const fees = (await getTokenAmount(pair.userTokenAccountXPk)).sub(
+				userTokenAmount
+			);
+			expect(fees.toNumber()).to.be.within(2_998, 3_000);
+			const position = await program.account.position.fetch(positionPk);
+			expect(position.tokensOwedA.toNumber()).to.equal(0);
+		});
+
+		it("close position fails while it has liquidity", async () => {
+			await expectError(
+				program.methods
+					.closePosition()
+					.accounts({ owner: userPk, position: positionPk })
+					.signers([user])
+					.rpc(),
+				"PositionNotEmpty"
+			);
+		});
+
+		it("decrease liquidity and close position", async () => {
+			await program.methods
+				.decreaseLiquidity(liquidity, new anchor.BN(0), new anchor.BN(0))
+				.accounts(positionAccounts())
+				.signers([user])
+				.rpc();
+
+			const pool = await program.account.concentratedPool.fetch(
+				concentratedPoolPk
+			);
+			expect(pool.liquidity.toNumber()).to.equal(0);
+			// the ticks are cleared for other positions
+			const lowerTickArray = await program.account.tickArray.fetch(
+				tickArrayPk(-960)
+			);
+			expect(lowerTickArray.ticks[6].liquidityGross.isZero()).to.be.true;
+			// only rounding is left behind
+			expect((await getTokenAmount(vaultAPk)).toNumber()).to.be.at.most(5);
+			expect((await getTokenAmount(vaultBPk)).toNumber()).to.be.at.most(5);
+
+			await program.methods
+				.closePosition()
+				.accounts({ owner: userPk, position: positionPk })
+				.signers([user])
+				.rpc();
+
+			expect(await connection.getAccountInfo(positionPk)).to.be.null;
+		});
+
+		describe("status", () => {
+			const amount = new anchor.BN(10_000);
+			let statusPositionPk: anchor.web3.PublicKey;
+
+			const setConcentratedPoolStatus = (status: object, signer = authority) =>
+				program.methods
+					.setConcentratedPoolStatus(status as any)
+					.accounts({
+						authority: signer.publicKey,
+						concentratedPool: concentratedPoolPk,
+					})
+					.signers([signer])
+					.rpc();
+
+			const statusPositionAccounts = () => ({
+				...positionAccounts(),
+				position: statusPositionPk,
+				tickArrayLower: tickArrayPk(-1920),
+				tickArrayUpper: tickArrayPk(960),
+			});
+
+			const swap = async () =>
+				program.methods
+					.swapConcentrated(amount, new anchor.BN(0))
+					.accounts(swapAccounts())
+					.remainingAccounts(await swapTickArrays(true))
+					.signers([user])
+					.rpc();
+
+			const increaseLiquidity = () =>
+				program.methods
+					.increaseLiquidity(
+						amount,
+						new anchor.BN(1_000_000),
+						new anchor.BN(1_000_000)
+					)
+					.accounts(statusPositionAccounts())
+					.signers([user])
+					.rpc();
+
+			const decreaseLiquidity = () =>
+				program.methods
+					.decreaseLiquidity(amount, new anchor.BN(0), new anchor.BN(0))
+					.accounts(statusPositionAccounts())
+					.signers([user])
+					.rpc();
+
+			const collectFees = () =>
+				program.methods
+					.collectFees()
+					.accounts(statusPositionAccounts())
+					.signers([user])
+					.rpc();
+
+			before(async () => {
+				statusPositionPk = findPda([
+					Buffer.from("position"),
+					concentratedPoolPk.toBuffer(),
+					userPk.toBuffer(),
+					tickBuffer(-1200),
+					tickBuffer(1200),
+				]);
+				await program.methods
+					.openPosition(-1200, 1200)
+					.accounts({
+						owner: userPk,
+						concentratedPool: concentratedPoolPk,
+						position: statusPositionPk,
+					})
+					.signers([user])
+					.rpc();
+				await program.methods
+					.increaseLiquidity(
+						liquidity,
+						new anchor.BN(1000_000_000),
+						new anchor.BN(1000_000_000)
+					)
+					.accounts(statusPositionAccounts())
+					.signers([user])
+					.rpc();
+			});
+
+			itChecksPoolStatus({
+				setStatus: setConcentratedPoolStatus,
+				swap,
+				deposit: increaseLiquidity,
+				withdrawals: [decreaseLiquidity, collectFees],
+			});
+		});
+
+		describe("full tick arrays", () => {
+			const dustLiquidity = new anchor.BN(1);
+
+			const dustPositionPk = (tickLower: number, tickUpper: number) =>
+				findPda([
+					Buffer.from("position"),
+					concentratedPoolPk.toBuffer(),
+					userPk.toBuffer(),
+					tickBuffer(tickLower),
+					tickBuffer(tickUpper),
+				]);
+
+			const tickArrayOf = (tick: number) =>
+				tickArrayPk(Math.floor(tick / ticksInArray) * ticksInArray);
+
+			it("dust positions take more ticks than a pool used to hold", async () => {
+				// 66 ticks, from -1980 to 2040
+				for (let i = 0; i < 33; i++) {
+					const [tickLower, tickUpper] = [-(i + 1) * 60, (i + 2) * 60];
+					const position = dustPositionPk(tickLower, tickUpper);
+					await program.methods
+						.openPosition(tickLower, tickUpper)
+						.accounts({
+							owner: userPk,
+							concentratedPool: concentratedPoolPk,
+							position,
+						})
+						.signers([user])
+						.rpc();
+					await program.methods
+						.increaseLiquidity(
+							dustLiquidity,
+							new anchor.BN(10),
+							new anchor.BN(10)
+						)
+						.accounts({
+							...positionAccounts(),
+							position,
+							tickArrayLower: tickArrayOf(tickLower),
+							tickArrayUpper: tickArrayOf(tickUpper),
+						})
+						.signers([user])
+						.rpc();
+				}
+
+				const tickArray = await program.account.tickArray.fetch(
+					tickArrayPk(-960)
+				);
+				expect(tickArray.ticks.every((tick) => !tick.liquidityGross.isZero()))
+					.to.be.true;
+			});
+
+			it("another lp still opens a position", async () => {
+				const tokenAccountAPk = await createAccount(
+					connection,
+					authority,
+					pair.accounts.tokenMintA,
+					authorityPk
+				);
+				const tokenAccountBPk = await createAccount(
+					connection,
+					authority,
+					pair.accounts.tokenMintB,
+					authorityPk
+				);
+				for (const [mintPk, tokenAccountPk] of [
+					[pair.accounts.tokenMintA, tokenAccountAPk],
+					[pair.accounts.tokenMintB, tokenAccountBPk],
+				]) {
+					await mintTo(
+						connection,
+						authority,
+						mintPk,
+						tokenAccountPk,
+						authority,
+						1000_000_000
+					);
+				}
+				const position = findPda([
+					Buffer.from("position"),
+					concentratedPoolPk.toBuffer(),
+					authorityPk.toBuffer(),
+					tickBuffer(-2820),
+					tickBuffer(2820),
+				]);
+
+				await program.methods
+					.openPosition(-2820, 2820)
+					.accounts({
+						owner: authorityPk,
+						concentratedPool: concentratedPoolPk,
+						position,
+					})
+					.signers([authority])
+					.rpc();
+				await program.methods
+					.increaseLiquidity(
+						liquidity,
+						new anchor.BN(1000_000_000),
+						new anchor.BN(1000_000_000)
+					)
+					.accounts({
+						owner: authorityPk,
+						concentratedPool: concentratedPoolPk,
+						position,
+						tickArrayLower: tickArrayPk(-2880),
+						tickArrayUpper: tickArrayPk(1920),
+						userTokenAccountA: tokenAccountAPk,
+						userTokenAccountB: tokenAccountBPk,
+						poolTokenVaultA: vaultAPk,
+						poolTokenVaultB: vaultBPk,
+					})
+					.signers([authority])
+					.rpc();
+
+				const lp = await program.account.position.fetch(position);
+				expect(lp.liquidity.toString()).to.equal(liquidity.toString());
+			});
+		});
+	});
+
+	describe("authority transfer", () => {
+		const newAuthority = anchor.web3.Keypair.generate();
+		const newAuthorityPk = newAuthority.publicKey;
+
+		it("propose authority fails for another signer", async () => {
+			await expectError(
+				program.methods
+					.proposeAuthority(userPk)
+					.accounts({ authority: userPk, pool: poolPk })
+					.signers([user])
+					.rpc(),
+				"Unauthorized"
+			);
+		});
+
+		it("accept authority fails for a key that was not proposed", async () => {
+			await program.methods
+				.proposeAuthority(newAuthorityPk)
+				.accounts({ authority: authorityPk, pool: poolPk })
+				.signers([authority])
+				.rpc();
+
+			await expectError(
+				program.methods
+					.acceptAuthority()
+					.accounts({ pendingAuthority: userPk, pool: poolPk })
+					.signers([user])
+					.rpc(),
+				"Unauthorized"
+			);
+		});
+
+		it("accept authority", async () => {
+			await program.methods
+				.acceptAuthority()
+				.accounts({ pendingAuthority: newAuthorityPk, pool: poolPk })
+				.signers([newAuthority])
+				.rpc();
+
+			const pool = await program.account.pool.fetch(poolPk);
+			expect(pool.authority.equals(newAuthorityPk)).to.be.true;
+			expect(pool.pendingAuthority.equals(anchor.web3.PublicKey.default)).to.be
+				.true;
+
+			// the old authority has no say any more
+			await expectError(
+				program.methods
+					.setPoolStatus({ frozen: {} } as any)
+					.accounts({ authority: authorityPk, pool: poolPk })
+					.signers([authority])
+					.rpc(),
+				"Unauthorized"
+			);
+		});
+
+		it("renounce authority", async () => {
+			await program.methods
+				.renounceAuthority()
+				.accounts({ authority: newAuthorityPk, pool: poolPk })
+				.signers([newAuthority])
+				.rpc();
+
+			const pool = await program.account.pool.fetch(poolPk);
+			expect(pool.authority.equals(anchor.web3.PublicKey.default)).to.be.true;
+			expect(pool.protocolFeeNumerator.isZero()).to.be.true;
+			expect(pool.protocolFeesA.isZero()).to.be.true;
+			expect(pool.protocolFeesB.isZero()).to.be.true;
+			await expectError(
+				program.methods
+					.proposeAuthority(newAuthorityPk)
+					.accounts({ authority: newAuthorityPk, pool: poolPk })
+					.signers([newAuthority])
+					.rpc(),
+				"Unauthorized"
+			);
+		});
+
+		it("swaps after a renounce accrue no protocol fees", async () => {
+			await program.methods
+				.swap(new anchor.BN(10_000), new anchor.BN(1))
+				.accounts(swapAccounts())
+				.signers([user])
+				.rpc();
+
+			const pool = await program.account.pool.fetch(poolPk);
+			expect(pool.protocolFeesA.isZero()).to.be.true;
+			expect(pool.protocolFeesB.isZero()).to.be.true;
+		});
 	});
 });